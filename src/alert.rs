@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serde_derive::Deserialize;
+
+use crate::de::deserialize_opt_duration;
+use crate::errors::*;
+use crate::threshold::{Sample, Threshold};
+use crate::widget::{AlertHold, State};
+
+/// How a block turns its readings into states, given as an `alert` table, e.g.
+/// `alert = { smoothing = 3, hysteresis = 2 }`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AlertPolicyConfig {
+    /// Number of readings averaged, so a single outlier doesn't change the state
+    #[serde(default = "AlertPolicyConfig::default_smoothing")]
+    pub smoothing: usize,
+
+    /// How far a reading has to move back past a threshold to leave its state
+    #[serde(default)]
+    pub hysteresis: f64,
+
+    /// Minimum time a warning or critical state is shown
+    #[serde(default, deserialize_with = "deserialize_opt_duration")]
+    pub min_duration: Option<Duration>,
+
+    /// Failed readings in a row for which the last good one is shown instead
+    #[serde(default)]
+    pub error_retries: usize,
+}
+
+impl AlertPolicyConfig {
+    fn default_smoothing() -> usize {
+        1
+    }
+}
+
+impl Default for AlertPolicyConfig {
+    fn default() -> Self {
+        AlertPolicyConfig {
+            smoothing: AlertPolicyConfig::default_smoothing(),
+            hysteresis: 0.0,
+            min_duration: None,
+            error_retries: 0,
+        }
+    }
+}
+
+/// Turns the readings of a block into the state to show and the value to display.
+///
+/// `levels` are checked in order, so the most important comes first, and the state of the
+/// first one reached is used, or `default` if none is. A state is entered as soon as its
+/// threshold is reached, but with `hysteresis` it is only left for one further down the
+/// list once the threshold is missed by that much.
+#[derive(Debug, Clone)]
+pub struct AlertPolicy {
+    config: AlertPolicyConfig,
+    levels: Vec<(State, Threshold)>,
+    default: State,
+    readings: VecDeque<f64>,
+    previous: Option<f64>,
+    /// The index in `levels` of the current state, if any is reached
+    current: Option<usize>,
+    hold: AlertHold,
+    failures: usize,
+    last: Option<(State, f64)>,
+}
+
+impl AlertPolicy {
+    pub fn new(config: AlertPolicyConfig, levels: Vec<(State, Threshold)>, default: State) -> Self {
+        AlertPolicy {
+            config,
+            levels,
+            default,
+            readings: VecDeque::new(),
+            previous: None,
+            current: None,
+            hold: AlertHold::default(),
+            failures: 0,
+            last: None,
+        }
+    }
+
+    /// Feeds the next reading, returning the state and the smoothed value to show. Failed
+    /// readings repeat the last result for up to `error_retries` times in a row.
+    pub fn reading(&mut self, reading: Result<f64>) -> Result<(State, f64)> {
+        let value = match reading {
+            Ok(value) => value,
+            Err(e) => {
+                self.failures += 1;
+                return match self.last {
+                    Some(last) if self.failures <= self.config.error_retries => Ok(last),
+                    _ => Err(e),
+                };
+            }
+        };
+        self.failures = 0;
+
+        self.readings.push_back(value);
+        while self.readings.len() > self.config.smoothing.max(1) {
+            self.readings.pop_front();
+        }
+        let value = self.readings.iter().sum::<f64>() / self.readings.len() as f64;
+        let sample = Sample {
+            value,
+            previous: self.previous,
+        };
+        self.previous = Some(value);
+
+        let reached = self
+            .levels
+            .iter()
+            .position(|(_, threshold)| threshold.reached(sample));
+        let hysteresis = self.config.hysteresis;
+        let levels = &self.levels;
+        // Whichever way the threshold goes, it still counts within the hysteresis
+        let still_reached = |index: usize| {
+            [value - hysteresis, value + hysteresis]
+                .iter()
+                .any(|&value| {
+                    levels[index].1.reached(Sample {
+                        value,
+                        previous: sample.previous,
+                    })
+                })
+        };
+        self.current = match (self.current, reached) {
+            (Some(current), reached)
+                if reached.map_or(true, |reached| reached > current) && still_reached(current) =>
+            {
+                Some(current)
+            }
+            (_, reached) => reached,
+        };
+
+        let state = self
+            .current
+            .map_or(self.default, |index| self.levels[index].0);
+        let state = self.hold.apply(state, self.config.min_duration);
+        self.last = Some((state, value));
+        Ok((state, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alert::{AlertPolicy, AlertPolicyConfig};
+    use crate::errors::*;
+    use crate::threshold::Threshold;
+    use crate::widget::State;
+
+    fn policy(config: AlertPolicyConfig) -> AlertPolicy {
+        AlertPolicy::new(
+            config,
+            vec![
+                (State::Critical, Threshold::Above(90.0)),
+                (State::Warning, Threshold::Above(60.0)),
+            ],
+            State::Idle,
+        )
+    }
+
+    fn state(policy: &mut AlertPolicy, value: f64) -> State {
+        policy.reading(Ok(value)).unwrap().0
+    }
+
+    #[test]
+    fn test_alert_policy_levels() {
+        let mut policy = policy(AlertPolicyConfig::default());
+        assert_eq!(state(&mut policy, 10.0), State::Idle);
+        assert_eq!(state(&mut policy, 61.0), State::Warning);
+        assert_eq!(state(&mut policy, 95.0), State::Critical);
+        assert_eq!(state(&mut policy, 89.0), State::Warning);
+    }
+
+    #[test]
+    fn test_alert_policy_hysteresis() {
+        let mut policy = policy(AlertPolicyConfig {
+            hysteresis: 5.0,
+            ..Default::default()
+        });
+        assert_eq!(state(&mut policy, 91.0), State::Critical);
+        assert_eq!(state(&mut policy, 86.0), State::Critical);
+        assert_eq!(state(&mut policy, 85.0), State::Warning);
+        // Going up is not held back
+        assert_eq!(state(&mut policy, 91.0), State::Critical);
+        assert_eq!(state(&mut policy, 50.0), State::Idle);
+
+        // Thresholds going down work the same
+        let mut policy = AlertPolicy::new(
+            AlertPolicyConfig {
+                hysteresis: 2.0,
+                ..Default::default()
+            },
+            vec![(State::Warning, Threshold::at_most(15.0))],
+            State::Idle,
+        );
+        assert_eq!(state(&mut policy, 15.0), State::Warning);
+        assert_eq!(state(&mut policy, 17.0), State::Warning);
+        assert_eq!(state(&mut policy, 18.0), State::Idle);
+    }
+
+    #[test]
+    fn test_alert_policy_smoothing() {
+        let mut policy = policy(AlertPolicyConfig {
+            smoothing: 3,
+            ..Default::default()
+        });
+        assert_eq!(policy.reading(Ok(30.0)).unwrap(), (State::Idle, 30.0));
+        assert_eq!(policy.reading(Ok(90.0)).unwrap(), (State::Idle, 60.0));
+        assert_eq!(policy.reading(Ok(90.0)).unwrap(), (State::Warning, 70.0));
+        assert_eq!(policy.reading(Ok(120.0)).unwrap(), (State::Critical, 100.0));
+    }
+
+    #[test]
+    fn test_alert_policy_error_retries() {
+        let error = || Err(BlockError("test".to_owned(), "failed".to_owned()));
+        let mut policy = policy(AlertPolicyConfig {
+            error_retries: 2,
+            ..Default::default()
+        });
+        // Nothing to repeat yet
+        assert!(policy.reading(error()).is_err());
+        assert_eq!(state(&mut policy, 70.0), State::Warning);
+        assert_eq!(policy.reading(error()).unwrap(), (State::Warning, 70.0));
+        assert_eq!(policy.reading(error()).unwrap(), (State::Warning, 70.0));
+        assert!(policy.reading(error()).is_err());
+        // A good reading starts the count over
+        assert_eq!(state(&mut policy, 70.0), State::Warning);
+        assert!(policy.reading(error()).is_ok());
+    }
+}
@@ -1,23 +1,34 @@
 pub mod activity;
+pub mod aggregate;
 pub mod backlight;
 pub mod battery;
+#[cfg(feature = "dbus")]
 pub mod bluetooth;
+pub mod container_layout;
 pub mod cpu;
 pub mod custom;
+#[cfg(feature = "dbus")]
 pub mod custom_dbus;
 pub mod disk_space;
 pub mod docker;
 pub mod focused_window;
 pub mod github;
 pub mod gmailcount;
+#[cfg(feature = "dbus")]
 pub mod ibus;
+pub mod idle;
+pub mod journal;
+#[cfg(feature = "dbus")]
 pub mod kdeconnect;
 pub mod keyboard_layout;
 pub mod load;
 pub mod maildir;
 pub mod memory;
+pub mod mirror;
+#[cfg(feature = "dbus")]
 pub mod music;
 pub mod net;
+#[cfg(feature = "dbus")]
 pub mod networkmanager;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
@@ -26,6 +37,7 @@ pub mod pacman;
 pub mod pomodoro;
 pub mod sound;
 pub mod speedtest;
+pub mod sql;
 pub mod taskwarrior;
 pub mod temperature;
 pub mod template;
@@ -34,28 +46,40 @@ pub mod toggle;
 pub mod uptime;
 pub mod watson;
 pub mod weather;
+pub mod window_count;
 pub mod xrandr;
 
 use self::activity::*;
+use self::aggregate::*;
 use self::backlight::*;
 use self::battery::*;
+#[cfg(feature = "dbus")]
 use self::bluetooth::*;
+use self::container_layout::*;
 use self::cpu::*;
 use self::custom::*;
+#[cfg(feature = "dbus")]
 use self::custom_dbus::*;
 use self::disk_space::*;
 use self::docker::*;
 use self::focused_window::*;
 use self::github::*;
 use self::gmailcount::*;
+#[cfg(feature = "dbus")]
 use self::ibus::*;
+use self::idle::*;
+use self::journal::*;
+#[cfg(feature = "dbus")]
 use self::kdeconnect::*;
 use self::keyboard_layout::*;
 use self::load::*;
 use self::maildir::*;
 use self::memory::*;
+use self::mirror::*;
+#[cfg(feature = "dbus")]
 use self::music::*;
 use self::net::*;
+#[cfg(feature = "dbus")]
 use self::networkmanager::*;
 #[cfg(feature = "notmuch")]
 use self::notmuch::*;
@@ -64,6 +88,7 @@ use self::pacman::*;
 use self::pomodoro::*;
 use self::sound::*;
 use self::speedtest::*;
+use self::sql::*;
 use self::taskwarrior::*;
 use self::temperature::*;
 use self::template::*;
@@ -72,24 +97,44 @@ use self::toggle::*;
 use self::uptime::*;
 use self::watson::*;
 use self::weather::*;
+use self::window_count::*;
 use self::xrandr::*;
 
-use std::time::Duration;
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
 use serde::de::Deserialize;
-use toml::value::Value;
+use serde_derive::Deserialize;
+use toml::value::{Table, Value};
 
-use crate::config::Config;
+use crate::config::{Config, IconPosition};
+use crate::de::{deserialize_opt_color, deserialize_opt_duration};
 use crate::errors::*;
-use crate::input::I3BarEvent;
+use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
-use crate::widget::I3BarWidget;
+use crate::stats::BlockStatsHandle;
+use crate::subprocess::{copy_to_clipboard, spawn_in_terminal, spawn_shell_async};
+use crate::widget::{I3BarWidget, State};
+use crate::widgets::text::TextWidget;
 
+/// When the scheduler calls `update` next.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Update {
     Every(Duration),
+    /// Not scheduled again. The block is still redrawn after each `click`, and can ask for an
+    /// update through the `Sender<Task>` given to `new`.
     Once,
+    /// For a failed update, e.g. while the network is down: retry after `base`, twice as long
+    /// after each further failure in a row, but never later than after `max`.
+    RetryBackoff {
+        base: Duration,
+        max: Duration,
+    },
 }
 
 impl Default for Update {
@@ -108,6 +153,12 @@ pub trait Block {
     /// A unique id for the block.
     fn id(&self) -> &str;
 
+    /// The name given to the block with the common `id` option, if any. Unlike `id`, it stays
+    /// the same across runs, so scripts and other blocks can refer to the block by it.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
     /// The current "view" of the block, comprised of widgets.
     fn view(&self) -> Vec<&dyn I3BarWidget>;
 
@@ -116,11 +167,32 @@ pub trait Block {
         Ok(None)
     }
 
+    /// How often `render` is called between updates, for blocks whose display changes more
+    /// often than their data needs to be fetched.
+    fn render_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Refreshes the widgets from the data fetched by the last update, without doing any I/O.
+    fn render(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Values other blocks can show through a `mirror` block, keyed by placeholder. The text
+    /// of the block is available as `{text}` anyway.
+    fn mirror_values(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
     /// Sends click events to the block. This function is called on every block
     /// for every click; filter events by using the `event.name` property.
     fn click(&mut self, _event: &I3BarEvent) -> Result<()> {
         Ok(())
     }
+
+    /// Called once before the bar exits (e.g. on SIGTERM). Blocks holding external
+    /// resources should release them here rather than relying on `Drop`.
+    fn shutdown(&mut self) {}
 }
 
 pub trait ConfigBlock: Block {
@@ -136,6 +208,470 @@ pub trait ConfigBlock: Block {
         Self: Sized;
 }
 
+/// Options shared by all blocks. They are taken out of the block's table before the
+/// block specific configuration is deserialized.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CommonBlockConfig {
+    /// Number of consecutive failed updates before the error is shown
+    #[serde(default = "CommonBlockConfig::default_error_after")]
+    pub error_after: usize,
+
+    /// Overrides the global `icon_position` for this block
+    #[serde(default = "CommonBlockConfig::default_icon_position")]
+    pub icon_position: Option<IconPosition>,
+
+    /// Font family to render this block's text in
+    #[serde(default = "CommonBlockConfig::default_font")]
+    pub font: Option<String>,
+
+    /// Shell command to run when the block is clicked, with the button in `$button`
+    #[serde(default = "CommonBlockConfig::default_on_click")]
+    pub on_click: Option<String>,
+
+    /// Command to run in a terminal when the block is left clicked
+    #[serde(default = "CommonBlockConfig::default_on_click_terminal")]
+    pub on_click_terminal: Option<String>,
+
+    /// i3 or sway command to run over IPC when the block is left clicked
+    #[serde(default = "CommonBlockConfig::default_on_click_i3")]
+    pub on_click_i3: Option<String>,
+
+    /// Minimum time for which warning and critical states are shown
+    #[serde(
+        default = "CommonBlockConfig::default_min_alert_duration",
+        deserialize_with = "deserialize_opt_duration"
+    )]
+    pub min_alert_duration: Option<Duration>,
+
+    /// Copy the text of the block to the clipboard when it is middle clicked
+    #[serde(default = "CommonBlockConfig::default_copy_on_middle_click")]
+    pub copy_on_middle_click: bool,
+
+    /// Text colors of this block per state, instead of the theme's
+    #[serde(default, deserialize_with = "deserialize_opt_color")]
+    pub color_idle: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_opt_color")]
+    pub color_info: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_opt_color")]
+    pub color_good: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_opt_color")]
+    pub color_warning: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_opt_color")]
+    pub color_critical: Option<String>,
+
+    /// Name of the block for `order` and `mirror` blocks
+    #[serde(default = "CommonBlockConfig::default_id")]
+    pub id: Option<String>,
+}
+
+impl CommonBlockConfig {
+    const KEYS: &'static [&'static str] = &[
+        "error_after",
+        "icon_position",
+        "font",
+        "on_click",
+        "on_click_terminal",
+        "on_click_i3",
+        "min_alert_duration",
+        "copy_on_middle_click",
+        "color_idle",
+        "color_info",
+        "color_good",
+        "color_warning",
+        "color_critical",
+        "id",
+    ];
+
+    /// Blocks with an `on_click` option of their own, which takes precedence
+    const OWN_ON_CLICK: &'static [&'static str] =
+        &["custom", "net", "networkmanager", "sound", "time"];
+
+    fn default_error_after() -> usize {
+        1
+    }
+
+    fn default_icon_position() -> Option<IconPosition> {
+        None
+    }
+
+    fn default_font() -> Option<String> {
+        None
+    }
+
+    fn default_on_click() -> Option<String> {
+        None
+    }
+
+    fn default_on_click_terminal() -> Option<String> {
+        None
+    }
+
+    fn default_on_click_i3() -> Option<String> {
+        None
+    }
+
+    fn default_min_alert_duration() -> Option<Duration> {
+        None
+    }
+
+    fn default_copy_on_middle_click() -> bool {
+        false
+    }
+
+    fn default_id() -> Option<String> {
+        None
+    }
+
+    /// Removes the common options from the `block_config` of the block called `name` and
+    /// parses them.
+    fn extract(name: &str, block_config: &mut Value) -> Result<Self> {
+        let mut common = Table::new();
+        if let Value::Table(ref mut table) = block_config {
+            for key in Self::KEYS {
+                if *key == "on_click" && Self::OWN_ON_CLICK.contains(&name) {
+                    continue;
+                }
+                if let Some(value) = table.remove(*key) {
+                    common.insert((*key).to_owned(), value);
+                }
+            }
+        }
+        CommonBlockConfig::deserialize(Value::Table(common))
+            .configuration_error("Failed to deserialize common block config.")
+    }
+}
+
+/// The text of the non-empty widgets, separated by spaces.
+fn widgets_text(widgets: &[&dyn I3BarWidget]) -> String {
+    widgets
+        .iter()
+        .map(|widget| widget.text().trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Runs a command of the window manager over its IPC socket, which works with both i3 and sway.
+fn run_i3_command(command: &str) -> std::result::Result<(), String> {
+    let outcomes = swayipc::Connection::new()
+        .and_then(|mut con| con.run_command(command))
+        .map_err(|e| e.to_string())?;
+    // One outcome is sent for every command in the string
+    let errors: Vec<String> = outcomes
+        .into_iter()
+        .filter(|outcome| !outcome.success)
+        .map(|outcome| outcome.error.unwrap_or_else(|| "unknown error".to_owned()))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join(", "))
+    }
+}
+
+/// Isolates a block from the rest of the bar. If any of its methods panics, the panic is
+/// logged, the block is shown in an error state and it is never called again. Failed updates
+/// are only passed on after `error_after` of them in a row; until then the block keeps
+/// showing its last good state. Options common to all blocks which act on clicks are handled
+/// here too.
+pub struct BlockGuard {
+    name: String,
+    inner: Box<dyn Block>,
+    error: TextWidget,
+    poisoned: Cell<bool>,
+    /// Whether `error` shows the error of the last update
+    failed: bool,
+    exit_on_error: bool,
+    error_after: usize,
+    failures: usize,
+    last_update: Option<Update>,
+    on_click: Option<String>,
+    on_click_terminal: Option<String>,
+    on_click_i3: Option<String>,
+    terminal: Option<String>,
+    copy_on_middle_click: bool,
+    copy_command: Option<String>,
+    /// Shown briefly in place of the block after copying it
+    copied: TextWidget,
+    copied_until: Cell<Option<Instant>>,
+    update_request: Sender<Task>,
+    /// The block's values are published under its `id`, if it has one
+    mirror_id: Option<String>,
+    mirrors: MirrorRegistry,
+    stats: Option<BlockStatsHandle>,
+}
+
+impl BlockGuard {
+    pub fn new(
+        name: &str,
+        inner: Box<dyn Block>,
+        common: CommonBlockConfig,
+        config: Config,
+        update_request: Sender<Task>,
+    ) -> Self {
+        BlockGuard {
+            name: name.to_owned(),
+            inner,
+            terminal: config.terminal.clone(),
+            exit_on_error: config.exit_on_error,
+            copy_on_middle_click: common.copy_on_middle_click,
+            copy_command: config.copy_command.clone(),
+            copied: TextWidget::new(config.clone())
+                .with_state(State::Good)
+                .with_text("copied"),
+            copied_until: Cell::new(None),
+            update_request,
+            mirror_id: common.id,
+            mirrors: config.mirrors.clone(),
+            stats: config.stats.register(name),
+            error: TextWidget::new(config)
+                .with_state(State::Critical)
+                .with_text(&format!(
+                    "{}",
+                    BlockError(name.to_owned(), "panicked".to_owned())
+                )),
+            poisoned: Cell::new(false),
+            failed: false,
+            error_after: common.error_after,
+            failures: 0,
+            // retry soon if the very first update fails
+            last_update: Some(Update::Every(Duration::from_secs(1))),
+            on_click: common.on_click,
+            on_click_terminal: common.on_click_terminal,
+            on_click_i3: common.on_click_i3,
+        }
+    }
+
+    /// Copies the text of the block's widgets and shows the confirmation for a second.
+    fn copy(&mut self) -> Result<()> {
+        let text = widgets_text(&self.view());
+        copy_to_clipboard(self.copy_command.as_deref(), &text)
+            .block_error(&self.name, "could not copy to the clipboard")?;
+
+        const CONFIRMATION: Duration = Duration::from_secs(1);
+        self.copied_until.set(Some(Instant::now() + CONFIRMATION));
+        // Redraw once the confirmation is over
+        let task = Task {
+            id: self.inner.id().to_owned(),
+            update_time: Instant::now(),
+        };
+        let update_request = self.update_request.clone();
+        thread::Builder::new()
+            .name("copied".into())
+            .spawn(move || {
+                thread::sleep(CONFIRMATION);
+                update_request.send(task).ok();
+            })
+            .internal_error("copy", "failed to start thread")?;
+        Ok(())
+    }
+
+    /// Runs the `on_click` command, passing the button and the values of the block, like
+    /// `{text}`, as environment variables without the braces.
+    fn run_on_click(&mut self, command: &str, button: MouseButton) -> Result<()> {
+        let values = self.guard(|block| {
+            let mut values = block.mirror_values();
+            values.insert("{text}".to_owned(), widgets_text(&block.view()));
+            Ok(values)
+        });
+        let mut env = vec![("button".to_owned(), button.name().to_owned())];
+        if let Some(Ok(values)) = values {
+            env.extend(values.into_iter().map(|(key, value)| {
+                let key = key
+                    .trim_matches(|c| c == '{' || c == '}')
+                    .replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+                (key, value)
+            }));
+        }
+        spawn_shell_async(command, &env).block_error(&self.name, "could not run 'on_click'")
+    }
+
+    /// Makes the values of the block available to the mirror blocks showing it.
+    fn publish(&mut self) {
+        if self.mirror_id.is_none() {
+            return;
+        }
+        let values = self.guard(|block| {
+            let mut values = block.mirror_values();
+            values.insert("{text}".to_owned(), widgets_text(&block.view()));
+            Ok(values)
+        });
+        if let (Some(id), Some(Ok(values))) = (&self.mirror_id, values) {
+            self.mirrors.publish(id, values);
+        }
+    }
+
+    fn poison(&self, payload: Box<dyn Any + Send>) -> String {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| (*s).to_owned())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_owned());
+        eprintln!("Block '{}' panicked: {}", self.name, message);
+        self.poisoned.set(true);
+        message
+    }
+
+    fn guard<T, F>(&mut self, f: F) -> Option<Result<T>>
+    where
+        F: FnOnce(&mut dyn Block) -> Result<T>,
+    {
+        if self.poisoned.get() {
+            return None;
+        }
+        // The inner block is never touched again after a panic, so it doesn't matter
+        // if it was left in an inconsistent state.
+        let inner = &mut self.inner;
+        match panic::catch_unwind(AssertUnwindSafe(|| f(inner.as_mut()))) {
+            Ok(result) => Some(result),
+            Err(payload) => {
+                let message = self.poison(payload);
+                self.error.set_text(format!(
+                    "{}",
+                    BlockError(self.name.clone(), format!("panicked: {}", message))
+                ));
+                None
+            }
+        }
+    }
+}
+
+impl Block for BlockGuard {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.mirror_id.as_deref()
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.poisoned.get() || self.failed {
+            return vec![&self.error];
+        }
+        match self.copied_until.get() {
+            Some(until) if until > Instant::now() => return vec![&self.copied],
+            Some(_) => self.copied_until.set(None),
+            None => {}
+        }
+        match panic::catch_unwind(AssertUnwindSafe(|| self.inner.view())) {
+            Ok(widgets) => widgets,
+            Err(payload) => {
+                self.poison(payload);
+                vec![&self.error]
+            }
+        }
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let start = Instant::now();
+        let was_poisoned = self.poisoned.get();
+        let result = self.guard(|block| block.update());
+        // A poisoned block is no longer updated, which isn't counted
+        if let (Some(ref stats), false) = (&self.stats, was_poisoned) {
+            let error = match result {
+                Some(Ok(_)) => None,
+                Some(Err(ref error)) => Some(error.to_string()),
+                None => Some("panicked".to_owned()),
+            };
+            stats.record(start.elapsed(), error);
+        }
+        match result {
+            Some(Ok(update)) => {
+                self.failures = 0;
+                self.failed = false;
+                self.last_update = update.clone();
+                self.publish();
+                Ok(update)
+            }
+            Some(Err(error)) => {
+                self.failures += 1;
+                if self.failures < self.error_after {
+                    eprintln!(
+                        "Block '{}' failed to update ({} of {}): {}",
+                        self.name, self.failures, self.error_after, error
+                    );
+                    Ok(self.last_update.clone())
+                } else if self.exit_on_error {
+                    Err(error)
+                } else {
+                    // Keep trying, the error is shown until an update works again
+                    eprintln!("Block '{}' failed to update: {}", self.name, error);
+                    self.error.set_error(&error);
+                    self.failed = true;
+                    if let Some(ref id) = self.mirror_id {
+                        let message = widgets_text(&[&self.error]);
+                        self.mirrors.publish(
+                            id,
+                            map!("{text}".to_owned() => message.clone(), "{error}".to_owned() => message),
+                        );
+                    }
+                    Ok(self.last_update.clone())
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn render_interval(&self) -> Option<Duration> {
+        if self.poisoned.get() {
+            return None;
+        }
+        self.inner.render_interval()
+    }
+
+    fn render(&mut self) -> Result<()> {
+        self.guard(|block| block.render()).unwrap_or(Ok(()))
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if let Some(command) = self.on_click.clone() {
+            if event.instance.as_deref() == Some(self.inner.id()) {
+                self.run_on_click(&command, event.button)?;
+            }
+        }
+        if let Some(ref command) = self.on_click_terminal {
+            if event.button == MouseButton::Left
+                && event.instance.as_deref() == Some(self.inner.id())
+            {
+                spawn_in_terminal(self.terminal.as_deref(), command)
+                    .block_error(&self.name, "could not spawn terminal")?;
+            }
+        }
+        if let Some(ref command) = self.on_click_i3 {
+            if event.button == MouseButton::Left
+                && event.instance.as_deref() == Some(self.inner.id())
+            {
+                // A failed command shouldn't break the block
+                if let Err(error) = run_i3_command(command) {
+                    eprintln!(
+                        "Block '{}' failed to run '{}' over IPC: {}",
+                        self.name, command, error
+                    );
+                }
+            }
+        }
+        if self.copy_on_middle_click
+            && event.button == MouseButton::Middle
+            && event.instance.as_deref() == Some(self.inner.id())
+        {
+            self.copy()?;
+        }
+        let result = self.guard(|block| block.click(event)).unwrap_or(Ok(()));
+        // Blocks often update themselves on clicks
+        self.publish();
+        result
+    }
+
+    fn shutdown(&mut self) {
+        self.guard(|block| {
+            block.shutdown();
+            Ok(())
+        });
+    }
+}
+
 macro_rules! block {
     ($block_type:ident, $block_config:expr, $config:expr, $update_request:expr) => {{
         let block_config: <$block_type as ConfigBlock>::Config =
@@ -146,6 +682,103 @@ macro_rules! block {
 }
 
 pub fn create_block(
+    name: &str,
+    mut block_config: Value,
+    mut config: Config,
+    update_request: Sender<Task>,
+) -> Result<Box<dyn Block>> {
+    let common = CommonBlockConfig::extract(name, &mut block_config)?;
+    if let Some(icon_position) = common.icon_position {
+        config.icon_position = icon_position;
+    }
+    config.font = common.font.clone();
+    config.min_alert_duration = common.min_alert_duration;
+    let theme = &mut config.theme;
+    for (color, fg) in [
+        (&common.color_idle, &mut theme.idle_fg),
+        (&common.color_info, &mut theme.info_fg),
+        (&common.color_good, &mut theme.good_fg),
+        (&common.color_warning, &mut theme.warning_fg),
+        (&common.color_critical, &mut theme.critical_fg),
+    ] {
+        if let Some(color) = color {
+            *fg = color.clone();
+        }
+    }
+    if let Some(ref id) = common.id {
+        config.mirrors.register(id);
+    }
+    let guard_config = config.clone();
+    let block = create_block_by_name(name, block_config, config, update_request.clone())?;
+    Ok(Box::new(BlockGuard::new(
+        name,
+        block,
+        common,
+        guard_config,
+        update_request,
+    )))
+}
+
+/// Every block type, including those left out of this build by disabled features.
+pub const BLOCK_NAMES: &[&str] = &[
+    "activity",
+    "aggregate",
+    "backlight",
+    "battery",
+    "bluetooth",
+    "container_layout",
+    "cpu",
+    "custom",
+    "custom_dbus",
+    "disk_space",
+    "docker",
+    "focused_window",
+    "github",
+    "gmailcount",
+    "ibus",
+    "idle",
+    "journal",
+    "kdeconnect",
+    "keyboard_layout",
+    "load",
+    "maildir",
+    "memory",
+    "mirror",
+    "music",
+    "net",
+    "networkmanager",
+    "notmuch",
+    "nvidia_gpu",
+    "pacman",
+    "pomodoro",
+    "sound",
+    "speedtest",
+    "sql",
+    "taskwarrior",
+    "temperature",
+    "template",
+    "time",
+    "toggle",
+    "uptime",
+    "watson",
+    "weather",
+    "window_count",
+    "xrandr",
+];
+
+/// The error for blocks left out of this build, naming the cargo feature to build them with.
+#[cfg(any(not(feature = "dbus"), not(feature = "notmuch")))]
+fn not_compiled(name: &str, feature: &str) -> Result<Box<dyn Block>> {
+    Err(BlockError(
+        name.to_string(),
+        format!(
+            "This build does not include the {} block, it needs the {} feature",
+            name, feature
+        ),
+    ))
+}
+
+fn create_block_by_name(
     name: &str,
     block_config: Value,
     config: Config,
@@ -154,33 +787,59 @@ pub fn create_block(
     match name {
         // Please keep these in alphabetical order.
         "activity" => block!(Activity, block_config, config, update_request),
+        "aggregate" => block!(Aggregate, block_config, config, update_request),
         "backlight" => block!(Backlight, block_config, config, update_request),
         "battery" => block!(Battery, block_config, config, update_request),
+        #[cfg(feature = "dbus")]
         "bluetooth" => block!(Bluetooth, block_config, config, update_request),
+        #[cfg(not(feature = "dbus"))]
+        "bluetooth" => not_compiled("bluetooth", "dbus"),
+        "container_layout" => block!(ContainerLayout, block_config, config, update_request),
         "cpu" => block!(Cpu, block_config, config, update_request),
         "custom" => block!(Custom, block_config, config, update_request),
+        #[cfg(feature = "dbus")]
         "custom_dbus" => block!(CustomDBus, block_config, config, update_request),
+        #[cfg(not(feature = "dbus"))]
+        "custom_dbus" => not_compiled("custom_dbus", "dbus"),
         "disk_space" => block!(DiskSpace, block_config, config, update_request),
         "docker" => block!(Docker, block_config, config, update_request),
         "focused_window" => block!(FocusedWindow, block_config, config, update_request),
         "github" => block!(Github, block_config, config, update_request),
         "gmailcount" => block!(GmailCount, block_config, config, update_request),
+        #[cfg(feature = "dbus")]
         "ibus" => block!(IBus, block_config, config, update_request),
+        #[cfg(not(feature = "dbus"))]
+        "ibus" => not_compiled("ibus", "dbus"),
+        "idle" => block!(Idle, block_config, config, update_request),
+        "journal" => block!(Journal, block_config, config, update_request),
+        #[cfg(feature = "dbus")]
         "kdeconnect" => block!(KDEConnect, block_config, config, update_request),
+        #[cfg(not(feature = "dbus"))]
+        "kdeconnect" => not_compiled("kdeconnect", "dbus"),
         "keyboard_layout" => block!(KeyboardLayout, block_config, config, update_request),
         "load" => block!(Load, block_config, config, update_request),
         "maildir" => block!(Maildir, block_config, config, update_request),
         "memory" => block!(Memory, block_config, config, update_request),
+        "mirror" => block!(Mirror, block_config, config, update_request),
+        #[cfg(feature = "dbus")]
         "music" => block!(Music, block_config, config, update_request),
+        #[cfg(not(feature = "dbus"))]
+        "music" => not_compiled("music", "dbus"),
         "net" => block!(Net, block_config, config, update_request),
+        #[cfg(feature = "dbus")]
         "networkmanager" => block!(NetworkManager, block_config, config, update_request),
+        #[cfg(not(feature = "dbus"))]
+        "networkmanager" => not_compiled("networkmanager", "dbus"),
         #[cfg(feature = "notmuch")]
         "notmuch" => block!(Notmuch, block_config, config, update_request),
+        #[cfg(not(feature = "notmuch"))]
+        "notmuch" => not_compiled("notmuch", "notmuch"),
         "nvidia_gpu" => block!(NvidiaGpu, block_config, config, update_request),
         "pacman" => block!(Pacman, block_config, config, update_request),
         "pomodoro" => block!(Pomodoro, block_config, config, update_request),
         "sound" => block!(Sound, block_config, config, update_request),
         "speedtest" => block!(SpeedTest, block_config, config, update_request),
+        "sql" => block!(Sql, block_config, config, update_request),
         "taskwarrior" => block!(Taskwarrior, block_config, config, update_request),
         "temperature" => block!(Temperature, block_config, config, update_request),
         "template" => block!(Template, block_config, config, update_request),
@@ -189,6 +848,7 @@ pub fn create_block(
         "uptime" => block!(Uptime, block_config, config, update_request),
         "watson" => block!(Watson, block_config, config, update_request),
         "weather" => block!(Weather, block_config, config, update_request),
+        "window_count" => block!(WindowCount, block_config, config, update_request),
         "xrandr" => block!(Xrandr, block_config, config, update_request),
         other => Err(BlockError(other.to_string(), "Unknown block!".to_string())),
     }
@@ -1,86 +1,368 @@
-use x11::{
-    xlib::{Display, XCloseDisplay, XDefaultRootWindow, XFree, XOpenDisplay},
-    xss::{XScreenSaverAllocInfo, XScreenSaverInfo, XScreenSaverQueryInfo}
-};
-
-use std::{
-    ptr,
-    os::{
-        raw::{c_void},
-    },
-    env,
-    ffi::CString
-};
-
-use std::time::{Duration, Instant};
-use crossbeam_channel::Sender;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+#[cfg(feature = "dbus")]
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::{env, thread};
+
+use chrono::{DateTime, Local};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+#[cfg(feature = "dbus")]
+use dbus::ffidisp::stdintf::org_freedesktop_dbus::Properties;
+#[cfg(feature = "dbus")]
+use dbus::ffidisp::{BusType, Connection};
 use serde::Deserialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::Config;
 use crate::de::deserialize_duration;
 use crate::errors::*;
-use crate::widgets::text::TextWidget;
-use crate::widget::{I3BarWidget, State};
-use crate::input::I3BarEvent;
+use crate::idle::{IdleBackend, IdleSource, SystemIdle};
+use crate::input::{I3BarEvent, MouseButton};
+use crate::notify::{Notification, Urgency};
 use crate::scheduler::Task;
+use crate::subprocess::spawn_shell_async;
+use crate::util::{check_min_interval, xdg_data_home, FormatTemplate};
+use crate::widget::{I3BarWidget, State};
+use crate::widgets::button::ButtonWidget;
 
 use uuid::Uuid;
 
-struct DeferXClose(*mut Display);
-impl Drop for DeferXClose {
-    fn drop(&mut self) {
-        unsafe { XCloseDisplay(self.0); }
-    }
+/// Looks up the object path of the logind session we are running in.
+#[cfg(feature = "dbus")]
+fn logind_session_path(con: &Connection) -> Result<dbus::Path<'static>> {
+    let msg = dbus::Message::new_method_call(
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+        "GetSessionByPID",
+    )
+    .block_error("activity", "Failed to create D-Bus message")?
+    .append1(process::id());
+
+    con.send_with_reply_and_block(msg, 1000)
+        .block_error("activity", "Failed to find the logind session")?
+        .read1()
+        .block_error("activity", "Failed to read the logind session path")
 }
-struct DeferXFree(*mut c_void);
-impl Drop for DeferXFree {
-    fn drop(&mut self) {
-        unsafe { XFree(self.0); }
-    }
+
+/// A session counts as locked while logind says so, and while it is not the active session,
+/// e.g. after switching to another VT or user.
+#[cfg(feature = "dbus")]
+fn logind_session_locked(con: &Connection, path: &dbus::Path) -> Result<bool> {
+    let session = con.with_path("org.freedesktop.login1", path, 1000);
+    let locked: bool = session
+        .get("org.freedesktop.login1.Session", "LockedHint")
+        .block_error("activity", "Failed to read the LockedHint property")?;
+    let active: bool = session
+        .get("org.freedesktop.login1.Session", "Active")
+        .block_error("activity", "Failed to read the Active property")?;
+    Ok(locked || !active)
 }
 
-fn get_idle(display: *mut Display, info: *mut XScreenSaverInfo) -> Result<u64> {
-    if unsafe { XScreenSaverQueryInfo(display, XDefaultRootWindow(display), info) } == 0 {
-        // not supported
-        Ok(0)
-    } else {
-        Ok(unsafe { (*info).idle })
+/// Follows the lock state of the logind session, so the block knows for sure that the screen
+/// is locked instead of inferring it from the idle time.
+struct LogindLock {
+    locked: Arc<AtomicBool>,
+}
+
+impl LogindLock {
+    #[cfg(feature = "dbus")]
+    fn new(id: String, update_request: Sender<Task>) -> Result<Self> {
+        let con = Connection::get_private(BusType::System)
+            .block_error("activity", "Failed to establish D-Bus connection.")?;
+        let path = logind_session_path(&con)?;
+        let locked = Arc::new(AtomicBool::new(logind_session_locked(&con, &path)?));
+
+        let thread_locked = locked.clone();
+        thread::Builder::new()
+            .name("activity".into())
+            .spawn(move || {
+                let con = Connection::get_private(BusType::System)
+                    .expect("Failed to establish D-Bus connection.");
+                // Lock and Unlock are sent by `loginctl lock-session` and friends, while
+                // PropertiesChanged covers LockedHint set by the locker and Active
+                let rule = format!(
+                    "type='signal',\
+                 sender='org.freedesktop.login1',\
+                 path='{}'",
+                    path
+                );
+
+                // Skip the NameAcquired event.
+                con.incoming(10_000).next();
+
+                con.add_match(&rule)
+                    .expect("Failed to add D-Bus match rule.");
+
+                loop {
+                    if let Some(msg) = con.incoming(10_000).next() {
+                        let locked = match msg.member().as_deref() {
+                            Some("Lock") => true,
+                            Some("Unlock") => false,
+                            Some("PropertiesChanged") => match logind_session_locked(&con, &path) {
+                                Ok(locked) => locked,
+                                Err(_) => continue,
+                            },
+                            _ => continue,
+                        };
+                        thread_locked.store(locked, Ordering::SeqCst);
+                        update_request
+                            .send(Task {
+                                id: id.clone(),
+                                update_time: Instant::now(),
+                            })
+                            .unwrap();
+                    }
+                }
+            })
+            .block_error("activity", "Failed to start the logind thread")?;
+
+        Ok(LogindLock { locked })
+    }
+
+    #[cfg(not(feature = "dbus"))]
+    fn new(_id: String, _update_request: Sender<Task>) -> Result<Self> {
+        Err(BlockError(
+            "activity".to_string(),
+            "Following the logind session needs the dbus feature".to_string(),
+        ))
+    }
+
+    fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::SeqCst)
     }
 }
 
+/// Appends lines to the transition log in a thread of its own, so a slow or full disk can't
+/// hold up updates.
+fn start_log_writer(path: PathBuf) -> Result<Sender<String>> {
+    let (send, recv): (Sender<String>, Receiver<String>) = unbounded();
+    thread::Builder::new()
+        .name("activity_log".into())
+        .spawn(move || {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).ok();
+            }
+            let file = match OpenOptions::new().create(true).append(true).open(&path) {
+                Ok(file) => file,
+                Err(e) => {
+                    eprintln!("activity: failed to open {}: {}", path.display(), e);
+                    return;
+                }
+            };
+            let mut writer = BufWriter::new(file);
+            let mut failed = false;
+            for line in recv.iter() {
+                let written = writeln!(writer, "{}", line).and_then(|_| {
+                    // Flush once there is nothing else to write
+                    if recv.is_empty() {
+                        writer.flush()
+                    } else {
+                        Ok(())
+                    }
+                });
+                // Lines which can't be written are dropped, without repeating the complaint
+                match written {
+                    Err(e) if !failed => {
+                        eprintln!("activity: failed to write to {}: {}", path.display(), e);
+                        failed = true;
+                    }
+                    Err(_) => {}
+                    Ok(()) => failed = false,
+                }
+            }
+        })
+        .block_error("activity", "Failed to start the log thread")?;
+    Ok(send)
+}
+
 pub struct Activity {
-    text: TextWidget,
+    text: ButtonWidget,
     id: String,
-    update_interval: Duration,
-    reset_time: Duration,
-    idle_threshold: Duration,
+    block_config: ActivityConfig,
+    format: FormatTemplate,
+    away_format: Option<FormatTemplate>,
     start_time: Instant,
-    display: *mut Display,
-    info: *mut XScreenSaverInfo,
-    _defer_free_display: DeferXClose,
-    _defer_free_info: DeferXFree,
+    idle_source: Box<dyn IdleSource>,
     idle_start_time: Instant,
     idle_last_reading: u64,
+    /// Updates in a row which read the same idle time
+    stable_ticks: u32,
+    logind: Option<LogindLock>,
+    log: Option<Sender<String>>,
+    /// Whether the last update found the user idle, and since when
+    last_transition: Option<(bool, DateTime<Local>)>,
+    paused: bool,
+    /// When the timer was paused, if it is
+    paused_since: Instant,
+    /// The displayed time, in seconds
+    elapsed: u64,
+    notification: Option<Notification>,
+    /// The state of the last update while not paused
+    last_state: State,
+    /// Where the session is kept, `None` unless `persist` is set or once writing it failed
+    state_path: Option<PathBuf>,
+    /// When the left button was last clicked, for double clicks
+    last_click: Option<Instant>,
+    send: Sender<Task>,
+    /// Whether an update is already due when the session is reset
+    reset_wake: Arc<AtomicBool>,
 }
 
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct ActivityConfig {
     /// Update interval in seconds
-    #[serde(default = "ActivityConfig::default_interval", deserialize_with = "deserialize_duration")]
+    #[serde(
+        default = "ActivityConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
     pub interval: Duration,
 
     /// Reset after idle time
-    #[serde(default = "ActivityConfig::default_reset_time", deserialize_with = "deserialize_duration")]
+    #[serde(
+        default = "ActivityConfig::default_reset_time",
+        deserialize_with = "deserialize_duration"
+    )]
     pub reset_time: Duration,
 
     /// Only count as idle if over the threshold
-    #[serde(default = "ActivityConfig::default_idle_threshold", deserialize_with = "deserialize_duration")]
+    #[serde(
+        default = "ActivityConfig::default_idle_threshold",
+        deserialize_with = "deserialize_duration"
+    )]
     pub idle_threshold: Duration,
 
+    /// Format override, with the placeholders {d}, {h}, {m} and {s}
+    #[serde(default = "ActivityConfig::default_format")]
+    pub format: String,
+
+    /// Format used while idle for `idle_threshold` or longer, `format` if not set
+    #[serde(default = "ActivityConfig::default_away_format")]
+    pub away_format: Option<String>,
+
+    /// Icon shown while idle for `idle_threshold` or longer
+    #[serde(default = "ActivityConfig::default_away_icon")]
+    pub away_icon: Option<String>,
+
+    /// Session time after which state is set to warning
+    #[serde(
+        default = "ActivityConfig::default_warning_time",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub warning_time: Duration,
+
+    /// Session time after which state is set to critical
+    #[serde(
+        default = "ActivityConfig::default_critical_time",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub critical_time: Duration,
+
+    /// Button which pauses or resumes the timer, any other click resets the session
+    #[serde(default = "ActivityConfig::default_pause_button")]
+    pub pause_button: MouseButton,
+
+    /// Whether one click resets the session, or a double click with the left button
+    #[serde(default = "ResetRequires::default")]
+    pub reset_requires: ResetRequires,
+
+    /// Send a desktop notification when the session time becomes critical
+    #[serde(default = "ActivityConfig::default_notify_on_critical")]
+    pub notify_on_critical: bool,
+
+    /// Shell command run instead of the notification
+    #[serde(default = "ActivityConfig::default_notify_command")]
+    pub notify_command: Option<String>,
+
+    /// What the displayed time counts
+    #[serde(default = "ActivityMode::default")]
+    pub mode: ActivityMode,
+
+    /// Keep the idle time growing when XScreenSaver stops updating it under i3lock
+    #[serde(
+        default = "ActivityConfig::default_i3lock_workaround",
+        alias = "lock_detection"
+    )]
+    pub i3lock_workaround: bool,
+
+    /// Updates in a row the idle time has to stay the same before the workaround kicks in
+    #[serde(default = "ActivityConfig::default_lock_detection_ticks")]
+    pub lock_detection_ticks: u32,
+
+    /// Reset the session as soon as logind reports the session as locked
+    #[serde(default = "ActivityConfig::default_reset_on_lock")]
+    pub reset_on_lock: bool,
+
+    /// Append each change between active and idle to a log file
+    #[serde(default = "ActivityConfig::default_log_transitions")]
+    pub log_transitions: bool,
+
+    /// The file transitions are logged to, as JSON lines
+    #[serde(default = "ActivityConfig::default_log_path")]
+    pub log_path: Option<PathBuf>,
+
+    /// Keep the session across restarts of the bar, in a file under `$XDG_RUNTIME_DIR`
+    #[serde(default = "ActivityConfig::default_persist")]
+    pub persist: bool,
+
+    /// How old the kept session may be to be picked up again
+    #[serde(
+        default = "ActivityConfig::default_persist_max_age",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub persist_max_age: Duration,
+
+    /// The X screen whose idle time is read, the default screen if not given
+    #[serde(default = "ActivityConfig::default_screen")]
+    pub screen: Option<usize>,
+
+    /// Where the idle time is read from, detected from the environment if not given
+    #[serde(default = "ActivityConfig::default_force_backend")]
+    pub force_backend: Option<IdleBackend>,
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityMode {
+    /// Count up the session time while active, and down to the reset while idle
+    Hybrid,
+    /// Always count up the session time
+    Elapsed,
+    /// Always count down the time left until the session is reset
+    UntilReset,
+}
+
+impl Default for ActivityMode {
+    fn default() -> Self {
+        ActivityMode::Hybrid
+    }
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResetRequires {
+    /// Any click other than with `pause_button`
+    Single,
+    /// Two left clicks within `DOUBLE_CLICK`
+    Double,
+}
+
+impl Default for ResetRequires {
+    fn default() -> Self {
+        ResetRequires::Single
+    }
 }
 
+/// How close together the clicks of a double click have to be. This is measured when the
+/// clicks are handled rather than when they happened, so a busy bar can split one up.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
 impl ActivityConfig {
     fn default_interval() -> Duration {
         Duration::from_secs(1)
@@ -93,118 +375,695 @@ impl ActivityConfig {
     fn default_idle_threshold() -> Duration {
         Duration::from_secs(10)
     }
+
+    fn default_warning_time() -> Duration {
+        Duration::from_secs(1800)
+    }
+
+    fn default_critical_time() -> Duration {
+        Duration::from_secs(3000)
+    }
+
+    fn default_notify_on_critical() -> bool {
+        false
+    }
+
+    fn default_notify_command() -> Option<String> {
+        None
+    }
+
+    fn default_pause_button() -> MouseButton {
+        MouseButton::Right
+    }
+
+    fn default_format() -> String {
+        "{h}h{m}m{s}s".to_owned()
+    }
+
+    fn default_away_format() -> Option<String> {
+        None
+    }
+
+    fn default_away_icon() -> Option<String> {
+        None
+    }
+
+    fn default_i3lock_workaround() -> bool {
+        true
+    }
+
+    fn default_lock_detection_ticks() -> u32 {
+        3
+    }
+
+    fn default_reset_on_lock() -> bool {
+        false
+    }
+
+    fn default_log_transitions() -> bool {
+        false
+    }
+
+    fn default_log_path() -> Option<PathBuf> {
+        None
+    }
+
+    fn default_persist() -> bool {
+        false
+    }
+
+    fn default_persist_max_age() -> Duration {
+        Duration::from_secs(300)
+    }
+
+    fn default_screen() -> Option<usize> {
+        None
+    }
+
+    fn default_force_backend() -> Option<IdleBackend> {
+        None
+    }
+}
+
+/// Whether the user counts as idle, after `idle_secs` without input.
+fn is_idle(idle_secs: u64, config: &ActivityConfig) -> bool {
+    idle_secs >= config.idle_threshold.as_secs()
+}
+
+/// Determines what to display from the current idle time and the time elapsed since the
+/// session started, both in seconds.
+///
+/// The state only depends on the idle time and the session time:
+///
+/// - Once idle for `reset_time` or longer the session is over, so the state is `Info`.
+/// - While idle for `idle_threshold` or longer the session is about to be reset, so the state
+///   is `Warning`.
+/// - Otherwise the state is `Info` for the first `warning_time` of the session, `Warning` up
+///   to `critical_time` and `Critical` beyond that.
+///
+/// The displayed time depends on the mode:
+///
+/// - `Elapsed` shows the session time, which is zero once the session is reset.
+/// - `UntilReset` shows the time left until the session is reset, i.e. `reset_time` minus
+///   the idle time, which is zero once the session is reset.
+/// - `Hybrid` shows the session time while active and the time left until the reset while
+///   idle.
+fn compute(idle_secs: u64, session_elapsed: u64, config: &ActivityConfig) -> (u64, State) {
+    let reset_time = config.reset_time.as_secs();
+    if idle_secs >= reset_time {
+        return (0, State::Info);
+    }
+
+    // idle_secs < reset_time here, so there is always at least a second left
+    let until_reset = reset_time - idle_secs;
+    let idle = is_idle(idle_secs, config);
+    let state = if idle {
+        State::Warning
+    } else {
+        match session_elapsed {
+            s if s > config.critical_time.as_secs() => State::Critical,
+            s if s > config.warning_time.as_secs() => State::Warning,
+            _ => State::Info,
+        }
+    };
+    let display_secs = match config.mode {
+        ActivityMode::Elapsed => session_elapsed,
+        ActivityMode::UntilReset => until_reset,
+        ActivityMode::Hybrid if idle => until_reset,
+        ActivityMode::Hybrid => session_elapsed,
+    };
+    (display_secs, state)
+}
+
+/// Renders `secs` in `format`. Each unit only wraps around if a larger one is shown too, so
+/// without `{d}` the hours keep counting past a day. Hours, minutes and seconds are padded
+/// to two digits.
+fn format_time(format: &FormatTemplate, uses: &str, secs: u64) -> Result<String> {
+    let shown = |unit: &str| uses.contains(unit);
+    let wrap = |value: u64, modulo: u64, larger: &[&str]| {
+        if larger.iter().any(|unit| shown(unit)) {
+            value % modulo
+        } else {
+            value
+        }
+    };
+    let values = map!(
+        "{d}" => (secs / 86400).to_string(),
+        "{h}" => format!("{:02}", wrap(secs / 3600, 24, &["{d}"])),
+        "{m}" => format!("{:02}", wrap(secs / 60, 60, &["{d}", "{h}"])),
+        "{s}" => format!("{:02}", wrap(secs, 60, &["{d}", "{h}", "{m}"]))
+    );
+    format.render_static_str(&values)
 }
 
 impl ConfigBlock for Activity {
     type Config = ActivityConfig;
 
-    fn new(block_config: Self::Config, config: Config, _send: Sender<Task>) -> Result<Self> {
-
+    fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
         let id = Uuid::new_v4().to_simple().to_string();
 
-        let (_disp_name_ptr, disp_name) = match env::var("DISPLAY") {
-            Ok(name) => {
-                let cstr = CString::new(name.as_str()).unwrap();
-                (cstr.as_ptr(), name)
-            },
-            Err(_) => (ptr::null(), String::from("N/A"))
-        };
+        // More often wouldn't change the displayed seconds
+        check_min_interval("activity", block_config.interval, Duration::from_secs(1))?;
 
-        //let display = unsafe { XOpenDisplay(disp_name_ptr as *const i8) };
-        // https://github.com/pftbest/x11-rust-example/blob/master/src/lib.rs
-        let display = unsafe { XOpenDisplay(ptr::null_mut()) };
-        if display.is_null() {
-            panic!("failed to open x server: {}", disp_name);
+        if block_config.warning_time > block_config.critical_time {
+            return Err(ConfigurationError(
+                "activity".to_string(),
+                (
+                    "'warning_time' can't be longer than 'critical_time'".to_string(),
+                    "invalid thresholds".to_string(),
+                ),
+            ));
         }
-        let display_cleanup = DeferXClose(display);
 
-        let info = unsafe { XScreenSaverAllocInfo() };
-        let info_cleanup = DeferXFree(info as *mut c_void);
+        let template = |format: &str, option: &str| -> Result<FormatTemplate> {
+            let template = FormatTemplate::from_string(format)
+                .block_error("activity", "Invalid format specified for activity")?;
+            // Rendering once finds unknown placeholders before they show up in the bar
+            if format_time(&template, format, 0).is_err() {
+                return Err(ConfigurationError(
+                    "activity".to_string(),
+                    (
+                        format!(
+                            "unknown placeholder in '{}', only {} are available",
+                            option, "{d}, {h}, {m} and {s}"
+                        ),
+                        "invalid format".to_string(),
+                    ),
+                ));
+            }
+            Ok(template)
+        };
+        let format = template(&block_config.format, "format")?;
+        let away_format = block_config
+            .away_format
+            .as_ref()
+            .map(|away_format| template(away_format, "away_format"))
+            .transpose()?;
+
+        let mut idle_source = Box::new(SystemIdle::new(
+            "activity",
+            block_config.force_backend,
+            block_config.screen,
+        )?);
 
-        Ok(Activity {
-            id: id,
-            update_interval: block_config.interval,
-            text: TextWidget::new(config),
-            idle_threshold: block_config.idle_threshold,
-            reset_time: block_config.reset_time,
+        // Without logind we can still guess, so don't fail the block over it
+        let logind = if block_config.reset_on_lock {
+            LogindLock::new(id.clone(), send.clone()).ok()
+        } else {
+            None
+        };
+
+        let log = if block_config.log_transitions {
+            let path = block_config
+                .log_path
+                .clone()
+                .unwrap_or_else(|| xdg_data_home().join("i3status-rust").join("activity.jsonl"));
+            Some(start_log_writer(path)?)
+        } else {
+            None
+        };
+
+        let notification = if block_config.notify_on_critical {
+            Some(Notification::new(&config))
+        } else {
+            None
+        };
+
+        let state_path = if block_config.persist {
+            let dir = env::var_os("XDG_RUNTIME_DIR").map_or_else(env::temp_dir, PathBuf::from);
+            Some(dir.join("i3status-rust").join("activity.json"))
+        } else {
+            None
+        };
+
+        // Coming back shows right away instead of with the next update
+        let wake_send = send.clone();
+        let wake_id = id.clone();
+        idle_source.set_wake(Box::new(move || {
+            wake_send
+                .send(Task {
+                    id: wake_id.clone(),
+                    update_time: Instant::now(),
+                })
+                .ok();
+        }));
+
+        let mut activity = Activity {
+            state_path,
+            last_click: None,
+            send,
+            reset_wake: Arc::new(AtomicBool::new(false)),
+            notification,
+            last_state: State::Idle,
+            block_config,
+            format,
+            away_format,
+            text: ButtonWidget::new(config, &id),
             start_time: Instant::now(),
-            display: display,
-            info: info,
-            _defer_free_display: display_cleanup,
-            _defer_free_info: info_cleanup,
+            idle_source,
             idle_start_time: Instant::now(),
-            idle_last_reading: 0
-        })
+            idle_last_reading: 0,
+            stable_ticks: 0,
+            logind,
+            log,
+            last_transition: None,
+            paused: false,
+            paused_since: Instant::now(),
+            elapsed: 0,
+            id,
+        };
+        activity.restore_state();
+        Ok(activity)
+    }
+}
+
+impl Activity {
+    /// Shows `elapsed` seconds, in whole hours, or minutes in the first hour, when the bar is
+    /// short of space. While `away` the away format and icon are used.
+    fn show_time(&mut self, elapsed: u64, away: bool) -> Result<()> {
+        self.elapsed = elapsed;
+        let (format, uses) = match (&self.away_format, &self.block_config.away_format) {
+            (Some(format), Some(uses)) if away => (format, uses),
+            _ => (&self.format, &self.block_config.format),
+        };
+        self.text.set_text(format_time(format, uses, elapsed)?);
+        match self.block_config.away_icon {
+            Some(ref icon) if away => self.text.set_icon(icon),
+            _ => self.text.clear_icon(),
+        }
+        self.text.set_short_text(if elapsed >= 3600 {
+            format!("{}h", elapsed / 3600)
+        } else {
+            format!("{}m", elapsed / 60)
+        });
+        Ok(())
+    }
+
+    /// Updates the block once `until_reset` is over, if that is before the next update, so the
+    /// reset shows right away. Only one such update is pending at a time.
+    fn wake_on_reset(&self, until_reset: Duration) {
+        if until_reset >= self.block_config.interval || self.reset_wake.swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
+        let reset_wake = self.reset_wake.clone();
+        let send = self.send.clone();
+        let id = self.id.clone();
+        let spawned = thread::Builder::new()
+            .name("activity_reset".into())
+            .spawn(move || {
+                thread::sleep(until_reset);
+                reset_wake.store(false, Ordering::SeqCst);
+                send.send(Task {
+                    id,
+                    update_time: Instant::now(),
+                })
+                .ok();
+            });
+        if spawned.is_err() {
+            self.reset_wake.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Starts a new session.
+    fn reset(&mut self) {
+        self.start_time = Instant::now();
+        self.paused_since = self.start_time;
+    }
+
+    /// The session time in seconds, not counting while paused.
+    fn session_secs(&self) -> u64 {
+        if self.paused {
+            self.paused_since.duration_since(self.start_time).as_secs()
+        } else {
+            self.start_time.elapsed().as_secs()
+        }
+    }
+
+    fn save_state(&mut self) {
+        let path = match self.state_path {
+            Some(ref path) => path,
+            None => return,
+        };
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let state = json!({
+            "saved_at": saved_at,
+            "session_secs": self.session_secs(),
+            "paused": self.paused,
+        });
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, state.to_string()));
+        if let Err(e) = written {
+            // Don't complain on every update
+            eprintln!("activity: failed to write {}: {}", path.display(), e);
+            self.state_path = None;
+        }
+    }
+
+    /// Picks up the session kept by an earlier run, if it is recent enough. The time the bar
+    /// was down for counts as part of the session, unless it was paused.
+    fn restore_state(&mut self) {
+        let state = match self
+            .state_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|state| serde_json::from_str::<serde_json::Value>(&state).ok())
+        {
+            Some(state) => state,
+            None => return,
+        };
+        let (saved_at, session_secs, paused) = match (
+            state["saved_at"].as_u64(),
+            state["session_secs"].as_u64(),
+            state["paused"].as_bool(),
+        ) {
+            (Some(saved_at), Some(session_secs), Some(paused)) => (saved_at, session_secs, paused),
+            _ => return,
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let age = now.saturating_sub(saved_at);
+        if age > self.block_config.persist_max_age.as_secs() {
+            return;
+        }
+        let session = Duration::from_secs(if paused {
+            session_secs
+        } else {
+            session_secs + age
+        });
+        let now = Instant::now();
+        self.start_time = now.checked_sub(session).unwrap_or(now);
+        self.paused_since = now;
+        self.paused = paused;
+    }
+
+    fn notify_critical(&mut self) {
+        if !self.block_config.notify_on_critical {
+            return;
+        }
+        if let Some(ref command) = self.block_config.notify_command {
+            if let Err(e) = spawn_shell_async(command, &[]) {
+                eprintln!("activity: failed to run 'notify_command': {}", e);
+            }
+        } else if let Some(ref mut notification) = self.notification {
+            let minutes = self.start_time.elapsed().as_secs() / 60;
+            let body = format!("You have been active for {} minutes.", minutes);
+            notification.notify("Time for a break", &body, Urgency::Critical, "");
+        }
     }
 }
 
 impl Block for Activity {
     fn update(&mut self) -> Result<Option<Update>> {
+        if self.paused {
+            // Neither counting nor reset while paused, just show the time it was paused at
+            let elapsed = self.paused_since.duration_since(self.start_time).as_secs();
+            self.show_time(elapsed, false)?;
+            self.text.set_state(State::Idle);
+            self.save_state();
+            return Ok(Some(Update::Every(self.block_config.interval)));
+        }
 
-        let mut idle = get_idle(self.display, self.info).unwrap();
+        let mut idle = self.idle_source.idle()?;
 
-        // the XScreenSaver details for some reason stops increasing when
-        // i3lock starts. This only seems to happen when running in i3bar
-        // and not when running in the terminal. The following code is to
-        // attempt to detect this happening, and to keep the idle counter
-        // increasing even if XScreenSaver isn't giving an updated number
-        if idle == self.idle_last_reading {
-            if self.idle_start_time.elapsed().as_secs() >= self.idle_threshold.as_secs() {
-                idle += self.idle_start_time.elapsed().as_secs() * 1000;
+        if let Some(ref logind) = self.logind {
+            // Locking ends the session right away, and unlocking starts a new one
+            if logind.is_locked() {
+                idle = self.block_config.reset_time.as_millis() as u64;
+            }
+        } else if self.block_config.i3lock_workaround && self.idle_source.may_stall_when_locked() {
+            // the XScreenSaver details for some reason stops increasing when
+            // i3lock starts. This only seems to happen when running in i3bar
+            // and not when running in the terminal. The following code is to
+            // attempt to detect this happening, and to keep the idle counter
+            // increasing even if XScreenSaver isn't giving an updated number
+            if idle == self.idle_last_reading {
+                // A single repeated reading may just be a very still mouse
+                self.stable_ticks = self.stable_ticks.saturating_add(1);
+                if self.stable_ticks >= self.block_config.lock_detection_ticks
+                    && self.idle_start_time.elapsed() >= self.block_config.idle_threshold
+                {
+                    idle += self.idle_start_time.elapsed().as_secs() * 1000;
+                }
+            } else {
+                self.idle_start_time = Instant::now();
+                self.idle_last_reading = idle;
+                self.stable_ticks = 0;
             }
-        } else {
-            self.idle_start_time = Instant::now();
-            self.idle_last_reading = idle;
         }
 
         idle /= 1000;
 
-        let (elapsed, state) = if idle >= self.reset_time.as_secs() {
-            self.start_time = Instant::now();
-            (0, State::Info)
-        } else if idle >= self.idle_threshold.as_secs() {
-            let elapsed = self.reset_time.as_secs() - idle;
-            let state = if elapsed > 0 {
-                State::Warning
-            } else {
-                State::Info
-            };
-            (elapsed, state)
-        } else {
-            let elapsed = self.start_time.elapsed().as_secs();
-            (elapsed, match elapsed {
-                0..=1800 => State::Info, // 30 minute warning
-                1801..=3000 => State::Warning,
-                _ => State::Critical,
-            })
-        };
-
-        let mut seconds = elapsed;
-        let mut minutes = (seconds - (seconds % 60)) / 60;
-        if seconds >= 60 {
-            seconds %= 60;
+        if let Some(ref log) = self.log {
+            let now_idle = is_idle(idle, &self.block_config);
+            if self.last_transition.map(|(was_idle, _)| was_idle) != Some(now_idle) {
+                // Being idle started with the last input, not when the threshold was crossed
+                let now = Local::now();
+                let since = if now_idle {
+                    now - chrono::Duration::seconds(idle as i64)
+                } else {
+                    now
+                };
+                let previous_secs = self
+                    .last_transition
+                    .map(|(_, previous)| (since - previous).num_seconds().max(0));
+                let line = json!({
+                    "time": since.to_rfc3339(),
+                    "state": if now_idle { "idle" } else { "active" },
+                    "previous_secs": previous_secs,
+                });
+                log.send(line.to_string()).ok();
+                self.last_transition = Some((now_idle, since));
+            }
         }
-        let hours = (minutes - (minutes % 60)) / 60;
-        if minutes >= 60 {
-            minutes %= 60;
+
+        let reset_secs = self.block_config.reset_time.as_secs();
+        if idle >= reset_secs {
+            self.start_time = Instant::now();
+        } else if is_idle(idle, &self.block_config) {
+            self.wake_on_reset(Duration::from_secs(reset_secs - idle));
         }
+        let (elapsed, state) = compute(
+            idle,
+            self.start_time.elapsed().as_secs(),
+            &self.block_config,
+        );
 
-        self.text.set_text(format!("{:02}h{:02}m{:02}", hours, minutes, seconds));
+        self.show_time(elapsed, is_idle(idle, &self.block_config))?;
         self.text.set_state(state);
 
-        Ok(Some(Update::Every(self.update_interval)))
+        // Only once per session, until the state is not critical anymore
+        if state == State::Critical && self.last_state != State::Critical {
+            self.notify_critical();
+        }
+        self.last_state = state;
+        self.save_state();
+
+        Ok(Some(Update::Every(self.block_config.interval)))
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
         vec![&self.text]
     }
 
-    fn click(&mut self, _: &I3BarEvent) -> Result<()> {
-        self.start_time = Instant::now();
+    fn mirror_values(&self) -> HashMap<String, String> {
+        map!(
+            "{elapsed}".to_owned() => self.elapsed.to_string(),
+            "{paused}".to_owned() => self.paused.to_string()
+        )
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if e.matches_name(&self.id) && e.button == self.block_config.pause_button {
+            if self.paused {
+                // Resume where it left off
+                self.start_time += self.paused_since.elapsed();
+            } else {
+                self.paused_since = Instant::now();
+            }
+            self.paused = !self.paused;
+        } else if self.block_config.reset_requires == ResetRequires::Single {
+            self.reset();
+        } else if e.button == MouseButton::Left {
+            let now = Instant::now();
+            match self.last_click {
+                Some(last) if now.duration_since(last) <= DOUBLE_CLICK => {
+                    self.last_click = None;
+                    self.reset();
+                }
+                _ => self.last_click = Some(now),
+            }
+        }
+        self.update()?;
         Ok(())
     }
 
+    fn shutdown(&mut self) {
+        self.idle_source.close();
+    }
+
     fn id(&self) -> &str {
         &self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use crossbeam_channel::unbounded;
+
+    use crate::blocks::activity::{compute, format_time, Activity, ActivityConfig, ActivityMode};
+    use crate::blocks::{Block, ConfigBlock};
+    use crate::config::Config;
+    use crate::errors::*;
+    use crate::idle::IdleSource;
+    use crate::util::FormatTemplate;
+    use crate::widget::State;
+    use crate::widgets::button::ButtonWidget;
+
+    /// Reads no idle time at all, like the Wayland backend while the user is active
+    struct MockIdle {
+        may_stall: bool,
+    }
+
+    impl IdleSource for MockIdle {
+        fn idle(&self) -> Result<u64> {
+            Ok(0)
+        }
+
+        fn may_stall_when_locked(&self) -> bool {
+            self.may_stall
+        }
+    }
+
+    /// An activity block 100 seconds into a session, whose idle time has read 0 all along.
+    fn activity(idle_source: MockIdle) -> Activity {
+        let mut block_config = config();
+        block_config.reset_time = Duration::from_secs(60);
+        let (send, _recv) = unbounded();
+        let started = Instant::now()
+            .checked_sub(Duration::from_secs(100))
+            .unwrap();
+        Activity {
+            text: ButtonWidget::new(Config::default(), "activity"),
+            id: "activity".to_owned(),
+            format: FormatTemplate::from_string(&block_config.format).unwrap(),
+            away_format: None,
+            block_config,
+            start_time: started,
+            idle_source: Box::new(idle_source),
+            idle_start_time: started,
+            idle_last_reading: 0,
+            stable_ticks: 3,
+            logind: None,
+            log: None,
+            last_transition: None,
+            paused: false,
+            paused_since: Instant::now(),
+            elapsed: 0,
+            notification: None,
+            last_state: State::Idle,
+            state_path: None,
+            last_click: None,
+            send,
+            reset_wake: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    #[test]
+    fn test_i3lock_workaround() {
+        // Only XScreenSaver stops counting under i3lock, other backends are taken as they are
+        let mut block = activity(MockIdle { may_stall: false });
+        block.update().unwrap();
+        assert_eq!(block.elapsed, 100);
+
+        // A reading stuck for longer than reset_time is taken as the screen being locked
+        let mut block = activity(MockIdle { may_stall: true });
+        block.update().unwrap();
+        assert_eq!(block.elapsed, 0);
+    }
+
+    fn config() -> ActivityConfig {
+        // reset_time = 300, idle_threshold = 10
+        toml::from_str("").unwrap()
+    }
+
+    #[test]
+    fn test_new_without_display() {
+        env::set_var("DISPLAY", ":bogus");
+        let (send, _recv) = unbounded();
+        assert!(Activity::new(config(), Config::default(), send).is_err());
+    }
+
+    #[test]
+    fn test_compute_active() {
+        let config = config();
+        assert_eq!(compute(0, 0, &config), (0, State::Info));
+        assert_eq!(compute(9, 1800, &config), (1800, State::Info));
+        assert_eq!(compute(0, 1801, &config), (1801, State::Warning));
+        assert_eq!(compute(0, 3001, &config), (3001, State::Critical));
+    }
+
+    #[test]
+    fn test_compute_thresholds() {
+        let mut config = config();
+        config.warning_time = Duration::from_secs(1500);
+        config.critical_time = Duration::from_secs(1500);
+        assert_eq!(compute(0, 1500, &config), (1500, State::Info));
+        assert_eq!(compute(0, 1501, &config), (1501, State::Critical));
+    }
+
+    #[test]
+    fn test_compute_idle_threshold() {
+        let config = config();
+        // Just below the threshold still counts as active time
+        assert_eq!(compute(9, 100, &config), (100, State::Info));
+        // At and above the threshold, count down to the reset
+        assert_eq!(compute(10, 100, &config), (290, State::Warning));
+        assert_eq!(compute(11, 100, &config), (289, State::Warning));
+    }
+
+    #[test]
+    fn test_compute_reset_time() {
+        let config = config();
+        assert_eq!(compute(299, 100, &config), (1, State::Warning));
+        assert_eq!(compute(300, 100, &config), (0, State::Info));
+        assert_eq!(compute(301, 100, &config), (0, State::Info));
+    }
+
+    #[test]
+    fn test_compute_modes() {
+        let mut config = config();
+        config.mode = ActivityMode::Elapsed;
+        assert_eq!(compute(0, 100, &config), (100, State::Info));
+        assert_eq!(compute(20, 100, &config), (100, State::Warning));
+        assert_eq!(compute(300, 100, &config), (0, State::Info));
+
+        config.mode = ActivityMode::UntilReset;
+        assert_eq!(compute(0, 100, &config), (300, State::Info));
+        assert_eq!(compute(20, 100, &config), (280, State::Warning));
+        assert_eq!(compute(300, 100, &config), (0, State::Info));
+    }
+
+    #[test]
+    fn test_format_time() {
+        let format =
+            |s: &str, secs| format_time(&FormatTemplate::from_string(s).unwrap(), s, secs).unwrap();
+        assert_eq!(format("{h}h{m}m{s}s", 3725), "01h02m05s");
+        assert_eq!(format("{h}:{m}", 90061), "25:01");
+        assert_eq!(format("{d}d {h}:{m}", 90061), "1d 01:01");
+        assert_eq!(format("{m}m", 3725), "62m");
+
+        let template = FormatTemplate::from_string("{x}").unwrap();
+        assert!(format_time(&template, "{x}", 0).is_err());
+    }
+}
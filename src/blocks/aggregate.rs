@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use uuid::Uuid;
+
+use crate::blocks::mirror::MirrorRegistry;
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::Config;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::threshold::{Sample, Threshold};
+use crate::util::FormatTemplate;
+use crate::widget::{I3BarWidget, State};
+use crate::widgets::text::TextWidget;
+
+pub struct Aggregate {
+    id: String,
+    text: TextWidget,
+    sources: Vec<String>,
+    value: String,
+    weights: HashMap<String, f64>,
+    format: FormatTemplate,
+    warning: Option<Threshold>,
+    critical: Option<Threshold>,
+    previous: Option<f64>,
+    mirrors: MirrorRegistry,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AggregateConfig {
+    /// The `id`s of the blocks to sum up
+    pub sources: Vec<String>,
+
+    /// The placeholder of the sources which is summed
+    #[serde(default = "AggregateConfig::default_value")]
+    pub value: String,
+
+    /// What the value of a source is multiplied by, 1 if not given
+    #[serde(default)]
+    pub weights: HashMap<String, f64>,
+
+    /// Format override
+    #[serde(default = "AggregateConfig::default_format")]
+    pub format: String,
+
+    /// Total above which state is set to warning
+    #[serde(default)]
+    pub warning: Option<Threshold>,
+
+    /// Total above which state is set to critical
+    #[serde(default)]
+    pub critical: Option<Threshold>,
+}
+
+impl AggregateConfig {
+    fn default_value() -> String {
+        "{count}".to_owned()
+    }
+
+    fn default_format() -> String {
+        "{total}".to_owned()
+    }
+}
+
+impl ConfigBlock for Aggregate {
+    type Config = AggregateConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let mirrors = config.mirrors.clone();
+        for source in &block_config.sources {
+            mirrors.subscribe(source, &id, tx.clone());
+        }
+
+        Ok(Aggregate {
+            id,
+            text: TextWidget::new(config),
+            sources: block_config.sources,
+            value: block_config.value,
+            weights: block_config.weights,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("aggregate", "Invalid format specified for aggregate")?,
+            warning: block_config.warning,
+            critical: block_config.critical,
+            previous: None,
+            mirrors,
+        })
+    }
+}
+
+impl Block for Aggregate {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let mut total = 0.0;
+        let mut failed = false;
+        for source in &self.sources {
+            let values = self
+                .mirrors
+                .values(source)
+                .block_error("aggregate", &format!("no block has id {}", source))?;
+            // Sources which haven't been updated yet count as 0, failed ones are left out
+            if values.is_empty() {
+                continue;
+            }
+            match values.get(&self.value).map(|value| value.parse::<f64>()) {
+                Some(Ok(value)) if !values.contains_key("{error}") => {
+                    total += value * self.weights.get(source).cloned().unwrap_or(1.0);
+                }
+                _ => failed = true,
+            }
+        }
+
+        let values = map!("{total}" => total.to_string());
+        self.text.set_text(self.format.render_static_str(&values)?);
+
+        let sample = Sample {
+            value: total,
+            previous: self.previous,
+        };
+        self.previous = Some(total);
+        let reached = |threshold: &Option<Threshold>| {
+            threshold
+                .as_ref()
+                .map_or(false, |threshold| threshold.reached(sample))
+        };
+        self.text.set_state(if reached(&self.critical) {
+            State::Critical
+        } else if reached(&self.warning) || failed {
+            State::Warning
+        } else {
+            State::Idle
+        });
+
+        // Updated when the values of a source change
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
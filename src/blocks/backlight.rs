@@ -135,6 +135,7 @@ impl BacklitDevice {
             .block_error("backlight", "Failed to write into brightness file")
     }
 
+    #[cfg(feature = "dbus")]
     fn set_brightness_via_dbus(&self, raw_value: u64) -> Result<()> {
         let device_name = self
             .device_path
@@ -159,6 +160,14 @@ impl BacklitDevice {
             .map(|_| ())
     }
 
+    #[cfg(not(feature = "dbus"))]
+    fn set_brightness_via_dbus(&self, _raw_value: u64) -> Result<()> {
+        Err(BlockError(
+            "backlight".to_string(),
+            "Failed to open brightness file for writing".to_string(),
+        ))
+    }
+
     /// The brightness file itself.
     // amdgpu drivers set the actual_brightness in a different scale than [0, max_brightness],
     // so we have to use the 'brightness' file instead. This may be fixed in the new 5.7 kernel?
@@ -4,23 +4,32 @@
 //! display the status, capacity, and time remaining for (dis)charge for an
 //! internal power supply.
 
+use std::fs;
 use std::path::{Path, PathBuf};
+#[cfg(feature = "dbus")]
 use std::thread;
 use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
+#[cfg(feature = "dbus")]
 use dbus::arg::Array;
+#[cfg(feature = "dbus")]
 use dbus::ffidisp::stdintf::org_freedesktop_dbus::Properties;
 use serde_derive::Deserialize;
 use uuid::Uuid;
 
+use crate::alert::{AlertPolicy, AlertPolicyConfig};
 use crate::blocks::Update;
 use crate::blocks::{Block, ConfigBlock};
 use crate::config::Config;
 use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::scheduler::Task;
-use crate::util::{battery_level_to_icon, format_percent_bar, read_file, FormatTemplate};
+use crate::threshold::Threshold;
+use crate::util::{
+    battery_level_to_icon, default_gauge, format_percent_bar, format_percent_gauge, read_file,
+    xdg_data_home, FormatTemplate,
+};
 use crate::widget::{I3BarWidget, State};
 use crate::widgets::text::TextWidget;
 
@@ -40,6 +49,12 @@ pub trait BatteryDevice {
 
     /// Query the current power consumption, in uW.
     fn power_consumption(&self) -> Result<u64>;
+
+    /// How much the device holds when full, as a fraction of its design
+    /// capacity. `None` if the device doesn't report its design capacity.
+    fn design_ratio(&self) -> Option<f64> {
+        None
+    }
 }
 
 /// Represents a physical power supply device, as known to sysfs.
@@ -47,6 +62,20 @@ pub struct PowerSupplyDevice {
     device_path: PathBuf,
     charge_full: Option<u64>,
     energy_full: Option<u64>,
+    charge_full_design: Option<u64>,
+    energy_full_design: Option<u64>,
+}
+
+/// Reads the sysfs attribute `name` of a power supply, if it exists.
+fn read_optional(device_path: &Path, name: &str) -> Result<Option<u64>> {
+    let path = device_path.join(name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    read_file("battery", &path)?
+        .parse::<u64>()
+        .map(Some)
+        .block_error("battery", &format!("failed to parse {}", name))
 }
 
 impl PowerSupplyDevice {
@@ -65,32 +94,13 @@ impl PowerSupplyDevice {
             ));
         }
 
-        // Read charge_full exactly once, if it exists, units are µAh
-        let charge_full = if device_path.join("charge_full").exists() {
-            Some(
-                read_file("battery", &device_path.join("charge_full"))?
-                    .parse::<u64>()
-                    .block_error("battery", "failed to parse charge_full")?,
-            )
-        } else {
-            None
-        };
-
-        // Read energy_full exactly once, if it exists. Units are µWh.
-        let energy_full = if device_path.join("energy_full").exists() {
-            Some(
-                read_file("battery", &device_path.join("energy_full"))?
-                    .parse::<u64>()
-                    .block_error("battery", "failed to parse energy_full")?,
-            )
-        } else {
-            None
-        };
-
+        // Read these exactly once, if they exist. Units are µAh for charge and µWh for energy.
         Ok(PowerSupplyDevice {
+            charge_full: read_optional(&device_path, "charge_full")?,
+            energy_full: read_optional(&device_path, "energy_full")?,
+            charge_full_design: read_optional(&device_path, "charge_full_design")?,
+            energy_full_design: read_optional(&device_path, "energy_full_design")?,
             device_path,
-            charge_full,
-            energy_full,
         })
     }
 }
@@ -272,14 +282,31 @@ impl BatteryDevice for PowerSupplyDevice {
             ))
         }
     }
+
+    fn design_ratio(&self) -> Option<f64> {
+        // Some drivers report a design capacity of zero
+        match (
+            self.energy_full,
+            self.energy_full_design,
+            self.charge_full,
+            self.charge_full_design,
+        ) {
+            (Some(full), Some(design), _, _) | (_, _, Some(full), Some(design)) if design > 0 => {
+                Some(full as f64 / design as f64)
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Represents a battery known to UPower.
+#[cfg(feature = "dbus")]
 pub struct UpowerDevice {
     device_path: String,
     con: dbus::ffidisp::Connection,
 }
 
+#[cfg(feature = "dbus")]
 impl UpowerDevice {
     /// Create the UPower device from the `device` string, which is converted to
     /// the path `"/org/freedesktop/UPower/devices/battery_<device>"`, except if
@@ -372,6 +399,7 @@ impl UpowerDevice {
     }
 }
 
+#[cfg(feature = "dbus")]
 impl BatteryDevice for UpowerDevice {
     fn status(&self) -> Result<String> {
         let status: u32 = self
@@ -432,6 +460,108 @@ impl BatteryDevice for UpowerDevice {
         // FIXME: Might want to make the interface send Watts instead.
         Ok((energy_rate * 1_000_000.0) as u64)
     }
+
+    fn design_ratio(&self) -> Option<f64> {
+        let device = self
+            .con
+            .with_path("org.freedesktop.UPower", &self.device_path, 1000);
+        let full: f64 = device
+            .get("org.freedesktop.UPower.Device", "EnergyFull")
+            .ok()?;
+        let design: f64 = device
+            .get("org.freedesktop.UPower.Device", "EnergyFullDesign")
+            .ok()?;
+        if design > 0.0 {
+            Some(full / design)
+        } else {
+            None
+        }
+    }
+}
+
+/// A rolling average of the discharge rate, persisted across sessions, used to
+/// steady the time remaining estimate.
+struct DischargeHistory {
+    path: PathBuf,
+    /// Average power draw while discharging, in W.
+    average: Option<f64>,
+    last_sample: Option<Instant>,
+    last_saved: Instant,
+}
+
+impl DischargeHistory {
+    /// Time constant of the average: readings older than this carry little weight.
+    const WINDOW: Duration = Duration::from_secs(60 * 60);
+    const SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+    fn load(device: &str) -> Self {
+        let path = xdg_data_home()
+            .join("i3status-rust")
+            .join(format!("battery_{}", device));
+        let average = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| s.trim().parse::<f64>().ok())
+            .filter(|avg| *avg > 0.0);
+        DischargeHistory {
+            path,
+            average,
+            last_sample: None,
+            last_saved: Instant::now(),
+        }
+    }
+
+    /// Adds a power reading (in W) taken while discharging.
+    fn record(&mut self, power: f64) {
+        let now = Instant::now();
+        // Weigh each reading by the time it covers, so the interval doesn't matter.
+        let elapsed = self
+            .last_sample
+            .map(|t| now.duration_since(t))
+            .unwrap_or_default()
+            .min(Self::WINDOW)
+            .as_secs_f64();
+        self.last_sample = Some(now);
+        self.average = Some(match self.average {
+            None => power,
+            Some(avg) => {
+                let weight = elapsed / (Self::WINDOW.as_secs_f64() + elapsed);
+                avg + (power - avg) * weight
+            }
+        });
+        if now.duration_since(self.last_saved) >= Self::SAVE_INTERVAL {
+            self.save();
+        }
+    }
+
+    /// Stops the current discharge session, so the time spent not discharging isn't
+    /// counted towards the next reading.
+    fn pause(&mut self) {
+        self.last_sample = None;
+    }
+
+    fn save(&mut self) {
+        self.last_saved = Instant::now();
+        if let Some(avg) = self.average {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir).ok();
+            }
+            if let Err(e) = fs::write(&self.path, format!("{}\n", avg)) {
+                eprintln!("battery: failed to save discharge history: {}", e);
+            }
+        }
+    }
+
+    /// Blends the instantaneous time remaining (in minutes, at `power` W) with the time
+    /// the remaining energy lasts at the average rate. `None` without any history.
+    fn estimate(&self, time: u64, power: f64) -> Option<u64> {
+        let avg = self.average?;
+        if power <= 0.0 {
+            return None;
+        }
+        let energy = time as f64 / 60.0 * power;
+        let historic = energy / avg * 60.0;
+        Some(((time as f64 + historic) / 2.0).round() as u64)
+    }
 }
 
 /// A block for displaying information about an internal power supply.
@@ -442,10 +572,10 @@ pub struct Battery {
     device: Box<dyn BatteryDevice>,
     format: FormatTemplate,
     driver: BatteryDriver,
-    good: u64,
-    info: u64,
-    warning: u64,
-    critical: u64,
+    alert: AlertPolicy,
+    history: Option<DischargeHistory>,
+    gauge: Vec<String>,
+    show_design_relative: bool,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -482,10 +612,14 @@ pub struct BatteryConfig {
     pub show: Option<String>,
 
     /// Format string for displaying battery information.
-    /// placeholders: {percentage}, {bar}, {time} and {power}
+    /// placeholders: {percentage}, {design_pct}, {bar}, {gauge}, {time} and {power}
     #[serde(default = "BatteryConfig::default_format")]
     pub format: String,
 
+    /// Glyphs for the {gauge} placeholder, from empty to full
+    #[serde(default = "default_gauge")]
+    pub gauge: Vec<String>,
+
     /// (DEPRECATED) Use UPower to monitor battery status and events.
     #[serde(default = "BatteryConfig::default_upower")]
     pub upower: bool,
@@ -508,6 +642,18 @@ pub struct BatteryConfig {
     /// The threshold below which the remaining capacity is shown as critical
     #[serde(default = "BatteryConfig::default_critical")]
     pub critical: u64,
+
+    /// Smoothing, hysteresis and retries for the states of the capacity
+    #[serde(default)]
+    pub alert: AlertPolicyConfig,
+
+    /// Blend the time remaining with the discharge rate learned over previous sessions
+    #[serde(default = "BatteryConfig::default_adaptive_estimate")]
+    pub adaptive_estimate: bool,
+
+    /// Show the battery level relative to the design capacity rather than the current full capacity
+    #[serde(default = "BatteryConfig::default_show_design_relative")]
+    pub show_design_relative: bool,
 }
 
 impl BatteryConfig {
@@ -542,11 +688,21 @@ impl BatteryConfig {
     fn default_good() -> u64 {
         60
     }
+
+    fn default_adaptive_estimate() -> bool {
+        false
+    }
+
+    fn default_show_design_relative() -> bool {
+        false
+    }
 }
 
 impl ConfigBlock for Battery {
     type Config = BatteryConfig;
 
+    // Only the upower driver is updated asynchronously
+    #[cfg_attr(not(feature = "dbus"), allow(unused_variables))]
     fn new(
         block_config: Self::Config,
         config: Config,
@@ -574,13 +730,26 @@ impl ConfigBlock for Battery {
 
         let id = Uuid::new_v4().to_simple().to_string();
         let device: Box<dyn BatteryDevice> = match driver {
+            #[cfg(feature = "dbus")]
             BatteryDriver::Upower => {
                 let out = UpowerDevice::from_device(&block_config.device)?;
                 out.monitor(id.clone(), update_request);
                 Box::new(out)
             }
+            #[cfg(not(feature = "dbus"))]
+            BatteryDriver::Upower => {
+                return Err(BlockError(
+                    "battery".to_string(),
+                    "The upower driver needs the dbus feature".to_string(),
+                ));
+            }
             BatteryDriver::Sysfs => Box::new(PowerSupplyDevice::from_device(&block_config.device)?),
         };
+        let history = if block_config.adaptive_estimate {
+            Some(DischargeHistory::load(&block_config.device))
+        } else {
+            None
+        };
 
         Ok(Battery {
             id,
@@ -589,10 +758,25 @@ impl ConfigBlock for Battery {
             device,
             format: FormatTemplate::from_string(&format)?,
             driver,
-            good: block_config.good,
-            info: block_config.info,
-            warning: block_config.warning,
-            critical: block_config.critical,
+            alert: AlertPolicy::new(
+                block_config.alert,
+                vec![
+                    (
+                        State::Critical,
+                        Threshold::at_most(block_config.critical as f64),
+                    ),
+                    (
+                        State::Warning,
+                        Threshold::at_most(block_config.warning as f64),
+                    ),
+                    (State::Info, Threshold::at_most(block_config.info as f64)),
+                    (State::Good, Threshold::Above(block_config.good as f64)),
+                ],
+                State::Idle,
+            ),
+            history,
+            gauge: block_config.gauge,
+            show_design_relative: block_config.show_design_relative,
         })
     }
 }
@@ -602,6 +786,16 @@ impl Block for Battery {
         // TODO: Maybe use dbus to immediately signal when the battery state changes.
 
         let status = self.device.status()?;
+        let power = self.device.power_consumption();
+
+        if let Some(ref mut history) = self.history {
+            match power {
+                Ok(power) if status == "Discharging" && power > 0 => {
+                    history.record(power as f64 / 1000.0 / 1000.0)
+                }
+                _ => history.pause(),
+            }
+        }
 
         if status == "Full" || status == "Not charging" {
             self.output.set_icon("bat_full");
@@ -609,28 +803,70 @@ impl Block for Battery {
             self.output.set_state(State::Good);
         } else {
             let capacity = self.device.capacity();
-            let percentage = match capacity {
-                Ok(capacity) => format!("{}", capacity),
-                Err(_) => "×".into(),
+            let reading =
+                self.alert
+                    .reading(
+                        capacity
+                            .as_ref()
+                            .map(|&capacity| capacity as f64)
+                            .map_err(|_| {
+                                BlockError("battery".into(), "failed to read the capacity".into())
+                            }),
+                    );
+            let design_capacity = match (&capacity, self.device.design_ratio()) {
+                (Ok(capacity), Some(ratio)) => Some((*capacity as f64 * ratio).round() as u64),
+                _ => None,
             };
-            let bar = match capacity {
-                Ok(capacity) => format_percent_bar(capacity as f32),
-                Err(_) => "×".into(),
+            // Thresholds keep using the capacity as reported, which is what runs out
+            let shown = match design_capacity {
+                Some(design_capacity) if self.show_design_relative => Some(design_capacity),
+                _ => reading
+                    .as_ref()
+                    .ok()
+                    .map(|&(_, capacity)| capacity.round() as u64),
+            };
+            let percentage = match shown {
+                Some(capacity) => format!("{}", capacity),
+                None => "×".into(),
+            };
+            let design_pct = match design_capacity {
+                Some(design_capacity) => format!("{}", design_capacity),
+                None => "N/A".into(),
+            };
+            let bar = match shown {
+                Some(capacity) => format_percent_bar(capacity as f32),
+                None => "×".into(),
+            };
+            let gauge = match shown {
+                Some(capacity) => format_percent_gauge(capacity as f32, &self.gauge),
+                None => "×".into(),
             };
             let time = match self.device.time_remaining() {
-                Ok(time) => match time {
-                    0 => "".into(),
-                    _ => format!("{}:{:02}", time / 60, time % 60),
-                },
+                Ok(0) => "".into(),
+                Ok(time) => {
+                    let estimate = match (&self.history, &power) {
+                        (Some(history), Ok(power)) if status == "Discharging" => {
+                            history.estimate(time, *power as f64 / 1000.0 / 1000.0)
+                        }
+                        _ => None,
+                    };
+                    match estimate {
+                        // marked, so it isn't mistaken for the driver's own figure
+                        Some(time) => format!("~{}:{:02}", time / 60, time % 60),
+                        None => format!("{}:{:02}", time / 60, time % 60),
+                    }
+                }
                 Err(_) => "×".into(),
             };
             // convert µW to W for display
-            let power = match self.device.power_consumption() {
+            let power = match power {
                 Ok(power) => format!("{:.2}", power as f64 / 1000.0 / 1000.0),
                 Err(_) => "×".into(),
             };
             let values = map!("{percentage}" => percentage,
+                              "{design_pct}" => design_pct,
                               "{bar}" => bar,
+                              "{gauge}" => gauge,
                               "{time}" => time,
                               "{power}" => power);
             self.output
@@ -643,20 +879,8 @@ impl Block for Battery {
                     self.output.set_state(State::Good);
                 }
                 _ => {
-                    self.output.set_state(match capacity {
-                        Ok(capacity) => {
-                            if capacity <= self.critical {
-                                State::Critical
-                            } else if capacity <= self.warning {
-                                State::Warning
-                            } else if capacity <= self.info {
-                                State::Info
-                            } else if capacity > self.good {
-                                State::Good
-                            } else {
-                                State::Idle
-                            }
-                        }
+                    self.output.set_state(match reading {
+                        Ok((state, _)) => state,
                         Err(_) => State::Warning,
                     });
                 }
@@ -679,6 +903,12 @@ impl Block for Battery {
         vec![&self.output]
     }
 
+    fn shutdown(&mut self) {
+        if let Some(ref mut history) = self.history {
+            history.save();
+        }
+    }
+
     fn id(&self) -> &str {
         &self.id
     }
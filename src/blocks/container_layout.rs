@@ -0,0 +1,223 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use swayipc::reply::{Node, NodeLayout};
+use swayipc::{Connection, EventType};
+use uuid::Uuid;
+
+use crate::blocks::{run_i3_command, Block, ConfigBlock, Update};
+use crate::config::Config;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widget::I3BarWidget;
+use crate::widgets::button::ButtonWidget;
+
+fn layout_name(layout: &NodeLayout) -> Option<&'static str> {
+    match layout {
+        NodeLayout::SplitH => Some("splith"),
+        NodeLayout::SplitV => Some("splitv"),
+        NodeLayout::Stacked => Some("stacked"),
+        NodeLayout::Tabbed => Some("tabbed"),
+        NodeLayout::Output | NodeLayout::None => None,
+    }
+}
+
+/// The layout a `layout` command would change: that of the container holding the focused
+/// window, or of the focused container itself, e.g. an empty workspace.
+fn focused_layout(node: &Node, parent: Option<&'static str>) -> Option<&'static str> {
+    if node.focused {
+        let is_window = node.nodes.is_empty() && (node.window.is_some() || node.app_id.is_some());
+        return if is_window {
+            parent
+        } else {
+            layout_name(&node.layout)
+        };
+    }
+    // Floating windows stay floating whatever they are wrapped in
+    let own = if parent == Some("floating") {
+        parent
+    } else {
+        layout_name(&node.layout)
+    };
+    node.nodes
+        .iter()
+        .find_map(|child| focused_layout(child, own))
+        .or_else(|| {
+            node.floating_nodes
+                .iter()
+                .find_map(|child| focused_layout(child, Some("floating")))
+        })
+}
+
+fn read_layout() -> Result<Option<&'static str>> {
+    let tree = Connection::new()
+        .and_then(|mut con| con.get_tree())
+        .block_error("container_layout", "failed to get the window tree over IPC")?;
+    Ok(focused_layout(&tree, None))
+}
+
+pub struct ContainerLayout {
+    id: String,
+    text: ButtonWidget,
+    format: FormatTemplate,
+    cycle: Vec<String>,
+    /// `None` while nothing is focused
+    layout: Arc<Mutex<Option<&'static str>>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ContainerLayoutConfig {
+    /// Format override
+    #[serde(default = "ContainerLayoutConfig::default_format")]
+    pub format: String,
+
+    /// Layouts switched through on click
+    #[serde(default = "ContainerLayoutConfig::default_cycle")]
+    pub cycle: Vec<String>,
+}
+
+impl ContainerLayoutConfig {
+    fn default_format() -> String {
+        "{layout}".to_owned()
+    }
+
+    fn default_cycle() -> Vec<String> {
+        vec![
+            "splith".to_owned(),
+            "splitv".to_owned(),
+            "tabbed".to_owned(),
+            "stacked".to_owned(),
+        ]
+    }
+}
+
+impl ConfigBlock for ContainerLayout {
+    type Config = ContainerLayoutConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        if let Some(unknown) = block_config
+            .cycle
+            .iter()
+            .find(|layout| !["splith", "splitv", "tabbed", "stacked"].contains(&layout.as_str()))
+        {
+            return Err(ConfigurationError(
+                "container_layout".to_string(),
+                (
+                    format!("unknown layout '{}' in 'cycle'", unknown),
+                    "invalid cycle".to_string(),
+                ),
+            ));
+        }
+        let layout = Arc::new(Mutex::new(read_layout()?));
+
+        let thread_layout = layout.clone();
+        let thread_id = id.clone();
+        thread::Builder::new()
+            .name("container_layout".into())
+            .spawn(move || {
+                // Bindings are where layouts are usually changed, and windows and workspaces
+                // where the focus moves
+                let events = Connection::new()
+                    .and_then(|con| {
+                        con.subscribe(&[
+                            EventType::Window,
+                            EventType::Workspace,
+                            EventType::Binding,
+                        ])
+                    })
+                    .expect("failed to subscribe to window events");
+                for event in events {
+                    if event.is_err() {
+                        break;
+                    }
+                    if let Ok(new_layout) = read_layout() {
+                        let mut layout = thread_layout.lock().unwrap();
+                        if *layout != new_layout {
+                            *layout = new_layout;
+                            tx.send(Task {
+                                id: thread_id.clone(),
+                                update_time: Instant::now(),
+                            })
+                            .unwrap();
+                        }
+                    }
+                }
+            })
+            .block_error("container_layout", "failed to start the event thread")?;
+
+        Ok(ContainerLayout {
+            text: ButtonWidget::new(config, &id),
+            id,
+            format: FormatTemplate::from_string(&block_config.format).block_error(
+                "container_layout",
+                "Invalid format specified for container_layout",
+            )?,
+            cycle: block_config.cycle,
+            layout,
+        })
+    }
+}
+
+impl Block for ContainerLayout {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let layout = *self
+            .layout
+            .lock()
+            .block_error("container_layout", "failed to acquire lock")?;
+        if let Some(layout) = layout {
+            let values = map!("{layout}" => layout.to_owned());
+            self.text.set_text(self.format.render_static_str(&values)?);
+            self.text.set_icon(&format!("layout_{}", layout));
+        }
+
+        // Updated by the event thread when the layout changes
+        Ok(None)
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if e.name.as_deref() != Some(self.id.as_str()) || e.button != MouseButton::Left {
+            return Ok(());
+        }
+        let current = *self
+            .layout
+            .lock()
+            .block_error("container_layout", "failed to acquire lock")?;
+        // Floating windows don't have a layout to change
+        if current.is_none() || current == Some("floating") || self.cycle.is_empty() {
+            return Ok(());
+        }
+        let next = match self.cycle.iter().position(|l| Some(l.as_str()) == current) {
+            Some(i) => &self.cycle[(i + 1) % self.cycle.len()],
+            None => &self.cycle[0],
+        };
+        run_i3_command(&format!("layout {}", next))
+            .map_err(|e| BlockError("container_layout".to_owned(), e))?;
+
+        // Commands don't cause any of the events we listen to
+        let layout = read_layout()?;
+        *self
+            .layout
+            .lock()
+            .block_error("container_layout", "failed to acquire lock")? = layout;
+        self.update()?;
+        Ok(())
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        match *self.layout.lock().unwrap() {
+            Some(_) => vec![&self.text],
+            None => vec![],
+        }
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -1,7 +1,8 @@
-use std::fs::File;
+use std::collections::HashMap;
+use std::fs::{self, File};
 use std::io::prelude::*;
 use std::io::BufReader;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
 use serde_derive::Deserialize;
@@ -13,26 +14,54 @@ use crate::config::Config;
 use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::scheduler::Task;
-use crate::util::{format_percent_bar, FormatTemplate};
+use crate::threshold::{Sample, Threshold};
+use crate::util::{
+    default_gauge, format_percent_bar, format_percent_gauge, CounterWindow, FormatTemplate,
+    Rounding,
+};
 use crate::widget::{I3BarWidget, State};
 use crate::widgets::text::TextWidget;
 
 /// Maximum number of CPUs we support.
 const MAX_CPUS: usize = 32;
 
+/// How long the busiest process found by a scan is shown before scanning again.
+const TOP_PROCESS_CACHE: Duration = Duration::from_secs(5);
+
+/// Scans older than this are too old to compare the next one against.
+const TOP_PROCESS_BASELINE: Duration = Duration::from_secs(60);
+
+/// The CPU time used by every process at the last scan for the busiest one.
+struct ProcessScan {
+    time: Instant,
+    cpu_times: HashMap<u32, u64>,
+    top: Option<String>,
+}
+
 pub struct Cpu {
     output: TextWidget,
-    prev_idles: [u64; MAX_CPUS],
-    prev_non_idles: [u64; MAX_CPUS],
+    /// Idle and total time of every CPU, the average of all first
+    idles: Vec<CounterWindow>,
+    totals: Vec<CounterWindow>,
+    average_over: Duration,
     id: String,
     update_interval: Duration,
-    minimum_info: u64,
-    minimum_warning: u64,
-    minimum_critical: u64,
+    minimum_info: Threshold,
+    minimum_warning: Threshold,
+    minimum_critical: Threshold,
+    prev_utilization: Option<f64>,
     format: FormatTemplate,
     has_barchart: bool,
     has_frequency: bool,
     per_core: bool,
+    warn_on_throttle: bool,
+    prev_throttle_count: Option<u64>,
+    gauge: Vec<String>,
+    show_top_process: bool,
+    process_scan: Option<ProcessScan>,
+    rounding: Rounding,
+    /// The placeholders of the last update, for mirror blocks
+    values: HashMap<String, String>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -47,15 +76,15 @@ pub struct CpuConfig {
 
     /// Minimum usage, where state is set to info
     #[serde(default = "CpuConfig::default_info")]
-    pub info: u64,
+    pub info: Threshold,
 
     /// Minimum usage, where state is set to warning
     #[serde(default = "CpuConfig::default_warning")]
-    pub warning: u64,
+    pub warning: Threshold,
 
     /// Minimum usage, where state is set to critical
     #[serde(default = "CpuConfig::default_critical")]
-    pub critical: u64,
+    pub critical: Threshold,
 
     /// Display frequency
     #[serde(default = "CpuConfig::default_frequency")]
@@ -65,9 +94,32 @@ pub struct CpuConfig {
     #[serde(default = "CpuConfig::default_format")]
     pub format: String,
 
+    /// Glyphs for the {utilizationgauge} placeholder, from idle to fully used
+    #[serde(default = "default_gauge")]
+    pub gauge: Vec<String>,
+
     /// Compute the metrics (utilization and frequency) per core.
     #[serde(default)]
     pub per_core: bool,
+
+    /// Show the block as critical while the CPU is being thermally throttled.
+    #[serde(default = "CpuConfig::default_warn_on_throttle")]
+    pub warn_on_throttle: bool,
+
+    /// Append the name of the busiest process while the block is critical.
+    #[serde(default = "CpuConfig::default_show_top_process_when_critical")]
+    pub show_top_process_when_critical: bool,
+
+    /// How the utilization is rounded
+    #[serde(default)]
+    pub rounding: Rounding,
+
+    /// Time over which the utilization is averaged, at least one update interval.
+    #[serde(
+        default = "CpuConfig::default_average_over",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub average_over: Duration,
 }
 
 impl CpuConfig {
@@ -79,21 +131,33 @@ impl CpuConfig {
         Duration::from_secs(1)
     }
 
-    fn default_info() -> u64 {
-        30
+    fn default_info() -> Threshold {
+        Threshold::Above(30.)
     }
 
-    fn default_warning() -> u64 {
-        60
+    fn default_warning() -> Threshold {
+        Threshold::Above(60.)
     }
 
-    fn default_critical() -> u64 {
-        90
+    fn default_critical() -> Threshold {
+        Threshold::Above(90.)
     }
 
     fn default_frequency() -> bool {
         false
     }
+
+    fn default_warn_on_throttle() -> bool {
+        false
+    }
+
+    fn default_show_top_process_when_critical() -> bool {
+        false
+    }
+
+    fn default_average_over() -> Duration {
+        Duration::from_secs(0)
+    }
 }
 
 impl ConfigBlock for Cpu {
@@ -116,20 +180,70 @@ impl ConfigBlock for Cpu {
             id: Uuid::new_v4().to_simple().to_string(),
             update_interval: block_config.interval,
             output: TextWidget::new(config).with_icon("cpu"),
-            prev_idles: [0; MAX_CPUS],
-            prev_non_idles: [0; MAX_CPUS],
+            idles: Vec::new(),
+            totals: Vec::new(),
+            average_over: block_config.average_over,
             minimum_info: block_config.info,
             minimum_warning: block_config.warning,
             minimum_critical: block_config.critical,
+            prev_utilization: None,
             format: FormatTemplate::from_string(&format)
                 .block_error("cpu", "Invalid format specified for cpu")?,
             has_frequency: format.contains("{frequency}"),
             has_barchart: format.contains("{barchart}"),
             per_core: block_config.per_core,
+            warn_on_throttle: block_config.warn_on_throttle,
+            prev_throttle_count: None,
+            gauge: block_config.gauge,
+            show_top_process: block_config.show_top_process_when_critical,
+            process_scan: None,
+            rounding: block_config.rounding,
+            values: HashMap::new(),
         })
     }
 }
 
+impl Cpu {
+    /// The process which used the most CPU time since the last scan. A recent scan is
+    /// reused, and the first one only serves to compare the next against.
+    fn top_process(&mut self) -> Option<String> {
+        let now = Instant::now();
+        if let Some(ref scan) = self.process_scan {
+            if scan.top.is_some() && now.duration_since(scan.time) < TOP_PROCESS_CACHE {
+                return scan.top.clone();
+            }
+        }
+
+        let processes = read_process_times();
+        let baseline = self
+            .process_scan
+            .as_ref()
+            .filter(|scan| now.duration_since(scan.time) < TOP_PROCESS_BASELINE);
+        let top = baseline.and_then(|scan| {
+            processes
+                .iter()
+                .map(|(pid, (name, time))| {
+                    // Processes started since the last scan used all of their time since then
+                    let prev = scan.cpu_times.get(pid).copied().unwrap_or(0);
+                    (time.saturating_sub(prev), name)
+                })
+                .filter(|(delta, _)| *delta > 0)
+                .max_by_key(|(delta, _)| *delta)
+                .map(|(_, name)| name.clone())
+        });
+
+        self.process_scan = Some(ProcessScan {
+            time: now,
+            cpu_times: processes
+                .into_iter()
+                .map(|(pid, (_, time))| (pid, time))
+                .collect(),
+            top: top.clone(),
+        });
+        top
+    }
+}
+
 impl Block for Cpu {
     fn update(&mut self) -> Result<Option<Update>> {
         let f = File::open("/proc/stat")
@@ -161,6 +275,7 @@ impl Block for Cpu {
             }
         }
 
+        let now = Instant::now();
         let mut cpu_utilizations: [f64; MAX_CPUS] = [0.0; MAX_CPUS];
         let mut cpu_i = 0;
         for line in f.lines().scan((), |_, x| x.ok()) {
@@ -182,23 +297,25 @@ impl Block for Cpu {
                                 data[6] + // softirq
                                 data[7]; // steal
 
-                let prev_total = self.prev_idles[cpu_i] + self.prev_non_idles[cpu_i];
-                let total = idle + non_idle;
-
-                // This check is needed because the new values may be reset, for
-                // example after hibernation.
-
-                let (total_delta, idle_delta) =
-                    if prev_total < total && self.prev_idles[cpu_i] <= idle {
-                        (total - prev_total, idle - self.prev_idles[cpu_i])
-                    } else {
-                        (1, 1)
+                if self.idles.len() <= cpu_i {
+                    self.idles.push(CounterWindow::new(self.average_over));
+                    self.totals.push(CounterWindow::new(self.average_over));
+                }
+                self.idles[cpu_i].push(now, idle);
+                self.totals[cpu_i].push(now, idle + non_idle);
+
+                // The counters may be reset, for example after hibernation, which starts
+                // their windows over. Until both have the same span again, nothing is known.
+                cpu_utilizations[cpu_i] =
+                    match (self.idles[cpu_i].delta(), self.totals[cpu_i].delta()) {
+                        (Some((idle_delta, idle_time)), Some((total_delta, total_time)))
+                            if idle_time == total_time && idle_delta < total_delta =>
+                        {
+                            (total_delta - idle_delta) as f64 / total_delta as f64
+                        }
+                        _ => 0.0,
                     };
 
-                cpu_utilizations[cpu_i] = (total_delta - idle_delta) as f64 / total_delta as f64;
-
-                self.prev_idles[cpu_i] = idle;
-                self.prev_non_idles[cpu_i] = non_idle;
                 cpu_i += 1;
                 if cpu_i >= MAX_CPUS {
                     break;
@@ -208,12 +325,33 @@ impl Block for Cpu {
 
         let avg_utilization = (100.0 * cpu_utilizations[0]) as u64;
 
-        self.output.set_state(match avg_utilization {
-            x if x > self.minimum_critical => State::Critical,
-            x if x > self.minimum_warning => State::Warning,
-            x if x > self.minimum_info => State::Info,
+        // Only an increase of the counters since the last update means we are throttling
+        // right now, they also count every throttling event since boot.
+        let mut throttling = false;
+        if self.warn_on_throttle {
+            match read_throttle_count() {
+                Some(count) => {
+                    throttling = self.prev_throttle_count.map_or(false, |prev| count > prev);
+                    self.prev_throttle_count = Some(count);
+                }
+                // not exposed on this platform
+                None => self.warn_on_throttle = false,
+            }
+        }
+
+        let sample = Sample {
+            value: avg_utilization as f64,
+            previous: self.prev_utilization,
+        };
+        self.prev_utilization = Some(sample.value);
+        let state = match sample {
+            _ if throttling => State::Critical,
+            s if self.minimum_critical.reached(s) => State::Critical,
+            s if self.minimum_warning.reached(s) => State::Warning,
+            s if self.minimum_info.reached(s) => State::Info,
             _ => State::Idle,
-        });
+        };
+        self.output.set_state(state);
 
         let mut barchart = String::new();
 
@@ -231,11 +369,22 @@ impl Block for Cpu {
         }
         let values = map!("{frequency}" => format_frequency(&cpu_freqs, n_cpu, self.per_core),
                           "{barchart}" => barchart,
-                          "{utilization}" => format_utilization(&cpu_utilizations, cpu_i, self.per_core),
-                          "{utilizationbar}" => format_percent_bar(avg_utilization as f32));
-
-        self.output
-            .set_text(self.format.render_static_str(&values)?);
+                          "{utilization}" => format_utilization(&cpu_utilizations, cpu_i, self.per_core, self.rounding),
+                          "{utilizationbar}" => format_percent_bar(avg_utilization as f32),
+                          "{utilizationgauge}" => format_percent_gauge(avg_utilization as f32, &self.gauge));
+
+        let mut text = self.format.render_static_str(&values)?;
+        // Scanning every process is only worth it when something is hogging the CPU
+        if self.show_top_process && state == State::Critical {
+            if let Some(name) = self.top_process() {
+                text = format!("{} {}", text, name);
+            }
+        }
+        self.output.set_text(text);
+        self.values = values
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect();
 
         Ok(Some(self.update_interval.into()))
     }
@@ -244,23 +393,85 @@ impl Block for Cpu {
         vec![&self.output]
     }
 
+    fn mirror_values(&self) -> HashMap<String, String> {
+        self.values.clone()
+    }
+
     fn id(&self) -> &str {
         &self.id
     }
 }
 
+/// Sums the core and package thermal throttle counters of all CPUs, or `None` if
+/// there are none.
+fn read_throttle_count() -> Option<u64> {
+    let mut total = None;
+    for entry in fs::read_dir("/sys/devices/system/cpu").ok()?.flatten() {
+        let throttle_dir = entry.path().join("thermal_throttle");
+        for counter in &["core_throttle_count", "package_throttle_count"] {
+            if let Some(count) = fs::read_to_string(throttle_dir.join(counter))
+                .ok()
+                .and_then(|s| s.trim().parse::<u64>().ok())
+            {
+                total = Some(total.unwrap_or(0) + count);
+            }
+        }
+    }
+    total
+}
+
+/// Reads the name and the user and system CPU time, in clock ticks, of every process.
+fn read_process_times() -> HashMap<u32, (String, u64)> {
+    let mut processes = HashMap::new();
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return processes,
+    };
+    for entry in entries.flatten() {
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        // Processes may exit while we are scanning
+        let stat = match fs::read_to_string(entry.path().join("stat")) {
+            Ok(stat) => stat,
+            Err(_) => continue,
+        };
+        // The name is in parentheses and may contain spaces and parentheses itself
+        let (start, end) = match (stat.find('('), stat.rfind(')')) {
+            (Some(start), Some(end)) if start < end => (start, end),
+            _ => continue,
+        };
+        let name = stat[start + 1..end].to_owned();
+        // utime and stime are the 14th and 15th fields, counting the pid and name
+        let fields: Vec<&str> = stat[end + 1..].split_whitespace().collect();
+        let time = match (fields.get(11), fields.get(12)) {
+            (Some(utime), Some(stime)) => {
+                utime.parse::<u64>().unwrap_or(0) + stime.parse::<u64>().unwrap_or(0)
+            }
+            _ => continue,
+        };
+        processes.insert(pid, (name, time));
+    }
+    processes
+}
+
 #[inline]
-fn format_utilization(values: &[f64], count: usize, per_core: bool) -> String {
+fn format_utilization(values: &[f64], count: usize, per_core: bool, rounding: Rounding) -> String {
     if per_core {
         values
             .iter()
             .take(count)
             .skip(1) // The first value is a global one.
-            .map(|v| format!("{:02.0}%", 100.0 * v))
+            .map(|v| format!("{:0>2}%", rounding.format(100.0 * v, 0)))
             .collect::<Vec<String>>()
             .join(" ")
     } else {
-        format!("{:02.0}", 100.0 * values[0])
+        format!("{:0>2}", rounding.format(100.0 * values[0], 0))
     }
 }
 
@@ -5,7 +5,9 @@ use std::time::{Duration, Instant};
 use std::vec;
 
 use crossbeam_channel::Sender;
+use regex::Regex;
 use serde_derive::Deserialize;
+use serde_json::value::Value;
 use uuid::Uuid;
 
 use crate::blocks::{Block, ConfigBlock, Update};
@@ -27,6 +29,20 @@ pub struct Custom {
     cycle: Option<Peekable<Cycle<vec::IntoIter<String>>>>,
     tx_update_request: Sender<Task>,
     pub json: bool,
+    markup: bool,
+    thresholds: Thresholds,
+    warning_regex: Option<Regex>,
+    critical_regex: Option<Regex>,
+    update_on_click: bool,
+}
+
+/// Percentages above which the state is set to info, warning and critical, for JSON output
+/// giving a `percentage` without a `state`
+#[derive(Clone, Copy, Debug)]
+struct Thresholds {
+    info: f64,
+    warning: f64,
+    critical: f64,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -51,6 +67,32 @@ pub struct CustomConfig {
     /// Parse command output if it contains valid bar JSON
     #[serde(default = "CustomConfig::default_json")]
     pub json: bool,
+
+    /// Render the command output as pango markup rather than plain text
+    #[serde(default = "CustomConfig::default_markup")]
+    pub markup: bool,
+
+    /// Regex which sets the state to warning when it matches the text
+    pub warning_regex: Option<String>,
+
+    /// Regex which sets the state to critical when it matches the text
+    pub critical_regex: Option<String>,
+
+    /// Run the command again when the block is clicked
+    #[serde(default = "CustomConfig::default_update_on_click")]
+    pub update_on_click: bool,
+
+    /// Percentage above which the state is set to info, for JSON output
+    #[serde(default = "CustomConfig::default_info")]
+    pub info: f64,
+
+    /// Percentage above which the state is set to warning, for JSON output
+    #[serde(default = "CustomConfig::default_warning")]
+    pub warning: f64,
+
+    /// Percentage above which the state is set to critical, for JSON output
+    #[serde(default = "CustomConfig::default_critical")]
+    pub critical: f64,
 }
 
 impl CustomConfig {
@@ -61,12 +103,41 @@ impl CustomConfig {
     fn default_json() -> bool {
         false
     }
+
+    fn default_markup() -> bool {
+        true
+    }
+
+    fn default_update_on_click() -> bool {
+        false
+    }
+
+    fn default_info() -> f64 {
+        30.
+    }
+
+    fn default_warning() -> f64 {
+        60.
+    }
+
+    fn default_critical() -> f64 {
+        90.
+    }
 }
 
 impl ConfigBlock for Custom {
     type Config = CustomConfig;
 
     fn new(block_config: Self::Config, config: Config, tx: Sender<Task>) -> Result<Self> {
+        let regex = |pattern: &Option<String>, name: &str| -> Result<Option<Regex>> {
+            pattern
+                .as_ref()
+                .map(|pattern| {
+                    Regex::new(pattern)
+                        .configuration_error(&format!("invalid regex for '{}' in custom", name))
+                })
+                .transpose()
+        };
         let mut custom = Custom {
             id: Uuid::new_v4().to_simple().to_string(),
             update_interval: block_config.interval,
@@ -76,6 +147,15 @@ impl ConfigBlock for Custom {
             cycle: None,
             tx_update_request: tx,
             json: block_config.json,
+            markup: block_config.markup,
+            thresholds: Thresholds {
+                info: block_config.info,
+                warning: block_config.warning,
+                critical: block_config.critical,
+            },
+            warning_regex: regex(&block_config.warning_regex, "warning_regex")?,
+            critical_regex: regex(&block_config.critical_regex, "critical_regex")?,
+            update_on_click: block_config.update_on_click,
         };
         custom.output = ButtonWidget::new(config, &custom.id);
 
@@ -96,21 +176,98 @@ impl ConfigBlock for Custom {
     }
 }
 
-fn default_icon() -> String {
-    String::from("")
-}
-
-fn default_state() -> State {
-    State::Idle
-}
-
-#[derive(Deserialize)]
 struct Output {
-    #[serde(default = "default_icon")]
     icon: String,
-    #[serde(default = "default_state")]
     state: State,
     text: String,
+    short_text: Option<String>,
+}
+
+fn output_error(field: &str, message: &str, value: &Value) -> Error {
+    BlockError(
+        "custom".to_string(),
+        format!(
+            "Invalid JSON output: `{}` {}, got {}",
+            field, message, value
+        ),
+    )
+}
+
+/// Reads a field which may be any JSON scalar as a string. Missing and null fields are `None`.
+fn scalar_field(object: &Value, field: &str) -> Result<Option<String>> {
+    match object.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(Value::Number(n)) => Ok(Some(n.to_string())),
+        Some(Value::Bool(b)) => Ok(Some(b.to_string())),
+        Some(value) => Err(output_error(
+            field,
+            "must be a string, number or boolean",
+            value,
+        )),
+    }
+}
+
+impl Output {
+    /// Parses the JSON output of the command. See blocks.md for the schema.
+    fn parse(raw_output: &str, thresholds: Thresholds) -> Result<Self> {
+        let object: Value = serde_json::from_str(raw_output)
+            .map_err(|e| BlockError("custom".to_string(), format!("Error parsing JSON: {}", e)))?;
+        if !object.is_object() {
+            return Err(BlockError(
+                "custom".to_string(),
+                format!("Invalid JSON output: expected an object, got {}", object),
+            ));
+        }
+
+        let text = scalar_field(&object, "text")?
+            .block_error("custom", "Invalid JSON output: `text` is missing")?;
+        let short_text = scalar_field(&object, "short_text")?;
+        let icon = scalar_field(&object, "icon")?.unwrap_or_default();
+
+        let state = match object.get("state") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(s)) => Some(match s.to_lowercase().as_str() {
+                "idle" => State::Idle,
+                "info" => State::Info,
+                "good" => State::Good,
+                "warning" => State::Warning,
+                "critical" => State::Critical,
+                _ => {
+                    return Err(output_error(
+                        "state",
+                        "must be one of idle, info, good, warning or critical",
+                        &object["state"],
+                    ))
+                }
+            }),
+            Some(value) => return Err(output_error("state", "must be a string", value)),
+        };
+
+        let percentage = match object.get("percentage") {
+            None | Some(Value::Null) => None,
+            Some(value) => Some(
+                value
+                    .as_f64()
+                    .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+                    .ok_or_else(|| output_error("percentage", "must be a number", value))?,
+            ),
+        };
+
+        let state = state.unwrap_or_else(|| match percentage {
+            Some(p) if p > thresholds.critical => State::Critical,
+            Some(p) if p > thresholds.warning => State::Warning,
+            Some(p) if p > thresholds.info => State::Info,
+            _ => State::Idle,
+        });
+
+        Ok(Output {
+            icon,
+            state,
+            text,
+            short_text,
+        })
+    }
 }
 
 impl Block for Custom {
@@ -122,27 +279,62 @@ impl Block for Custom {
             .or_else(|| self.command.clone())
             .unwrap_or_else(|| "".to_owned());
 
-        let raw_output = Command::new(env::var("SHELL").unwrap_or_else(|_| "sh".to_owned()))
-            .args(&["-c", &command_str])
-            .output()
-            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_owned())
-            .unwrap_or_else(|e| e.to_string());
-
-        if self.json {
-            let output: Output = match serde_json::from_str(&*raw_output) {
-                Err(e) => {
-                    return Err(BlockError(
-                        "custom".to_string(),
-                        format!("Error parsing JSON: {}", e),
-                    ));
+        let (raw_output, failed) =
+            match Command::new(env::var("SHELL").unwrap_or_else(|_| "sh".to_owned()))
+                .args(&["-c", &command_str])
+                .output()
+            {
+                Ok(o) if o.status.success() => {
+                    (String::from_utf8_lossy(&o.stdout).trim().to_owned(), false)
+                }
+                Ok(o) => {
+                    let stderr = String::from_utf8_lossy(&o.stderr).trim().to_owned();
+                    eprintln!("custom command '{}' failed: {}", command_str, stderr);
+                    let stdout = String::from_utf8_lossy(&o.stdout).trim().to_owned();
+                    (if stdout.is_empty() { stderr } else { stdout }, true)
                 }
-                Ok(s) => s,
+                Err(e) => (e.to_string(), true),
             };
-            self.output.set_icon(&output.icon);
-            self.output.set_state(output.state);
-            self.output.set_text(output.text);
+
+        let (text, short_text, state) = if self.json && !failed {
+            let output = Output::parse(&raw_output, self.thresholds)?;
+            if output.icon.is_empty() {
+                self.output.clear_icon();
+            } else {
+                self.output.set_icon(&output.icon);
+            }
+            (output.text, output.short_text, output.state)
         } else {
-            self.output.set_text(raw_output);
+            (raw_output, None, State::Idle)
+        };
+        let state = match text {
+            _ if failed => State::Critical,
+            ref text
+                if self
+                    .critical_regex
+                    .as_ref()
+                    .map_or(false, |re| re.is_match(text)) =>
+            {
+                State::Critical
+            }
+            ref text
+                if self
+                    .warning_regex
+                    .as_ref()
+                    .map_or(false, |re| re.is_match(text)) =>
+            {
+                State::Warning
+            }
+            _ => state,
+        };
+        self.output.set_state(state);
+        // Without a short text of its own the whole text is kept when the bar is short of space
+        self.output
+            .set_short_text(short_text.unwrap_or_else(|| text.clone()));
+        if self.markup {
+            self.output.set_text_raw(text);
+        } else {
+            self.output.set_text(text);
         }
 
         Ok(Some(self.update_interval.clone()))
@@ -161,7 +353,7 @@ impl Block for Custom {
             return Ok(());
         }
 
-        let mut update = false;
+        let mut update = self.update_on_click;
 
         if let Some(ref on_click) = self.on_click {
             spawn_child_async("sh", &["-c", on_click]).ok();
@@ -187,3 +379,77 @@ impl Block for Custom {
         &self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::custom::{Output, Thresholds};
+    use crate::widget::State;
+
+    const THRESHOLDS: Thresholds = Thresholds {
+        info: 30.0,
+        warning: 60.0,
+        critical: 90.0,
+    };
+
+    fn parse(line: &str) -> Output {
+        Output::parse(line, THRESHOLDS).unwrap()
+    }
+
+    #[test]
+    fn test_parse_scalars() {
+        let output = parse(r#"{"text": "up", "icon": "net_up", "state": "Good"}"#);
+        assert_eq!(output.text, "up");
+        assert_eq!(output.icon, "net_up");
+        assert_eq!(output.state, State::Good);
+        assert_eq!(parse(r#"{"text": 42}"#).text, "42");
+        assert_eq!(parse(r#"{"text": 1.5}"#).text, "1.5");
+        assert_eq!(parse(r#"{"text": true}"#).text, "true");
+        assert_eq!(
+            parse(r#"{"text": "x", "short_text": 7}"#)
+                .short_text
+                .unwrap(),
+            "7"
+        );
+        assert_eq!(parse(r#"{"text": "x", "icon": null}"#).icon, "");
+    }
+
+    #[test]
+    fn test_parse_percentage() {
+        assert_eq!(
+            parse(r#"{"text": "x", "percentage": 10}"#).state,
+            State::Idle
+        );
+        assert_eq!(
+            parse(r#"{"text": "x", "percentage": 45}"#).state,
+            State::Info
+        );
+        assert_eq!(
+            parse(r#"{"text": "x", "percentage": "75"}"#).state,
+            State::Warning
+        );
+        assert_eq!(
+            parse(r#"{"text": "x", "percentage": 95.5}"#).state,
+            State::Critical
+        );
+        // An explicit state wins over the percentage
+        assert_eq!(
+            parse(r#"{"text": "x", "percentage": 95, "state": "idle"}"#).state,
+            State::Idle
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for line in &[
+            "not json",
+            r#"["text"]"#,
+            r#"{"icon": "net_up"}"#,
+            r#"{"text": ["x"]}"#,
+            r#"{"text": "x", "state": "bad"}"#,
+            r#"{"text": "x", "state": 1}"#,
+            r#"{"text": "x", "percentage": "many"}"#,
+        ] {
+            assert!(Output::parse(line, THRESHOLDS).is_err(), "{}", line);
+        }
+    }
+}
@@ -20,12 +20,31 @@ pub struct CustomDBus {
     id: String,
     text: TextWidget,
     status: Arc<Mutex<String>>,
+    scroll: bool,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct CustomDBusConfig {
     pub name: String,
+
+    /// Longer text is cut to this many columns
+    #[serde(default = "CustomDBusConfig::default_max_width")]
+    pub max_width: Option<usize>,
+
+    /// Scroll longer text instead of cutting it
+    #[serde(default = "CustomDBusConfig::default_scroll")]
+    pub scroll: bool,
+}
+
+impl CustomDBusConfig {
+    fn default_max_width() -> Option<usize> {
+        None
+    }
+
+    fn default_scroll() -> bool {
+        false
+    }
 }
 
 impl ConfigBlock for CustomDBus {
@@ -34,6 +53,7 @@ impl ConfigBlock for CustomDBus {
     fn new(block_config: Self::Config, config: Config, send: Sender<Task>) -> Result<Self> {
         let id: String = Uuid::new_v4().to_simple().to_string();
         let id_copy = id.clone();
+        let (max_width, scroll) = (block_config.max_width, block_config.scroll);
 
         let status_original = Arc::new(Mutex::new(String::from("??")));
         let status = status_original.clone();
@@ -90,8 +110,12 @@ impl ConfigBlock for CustomDBus {
 
         Ok(CustomDBus {
             id: id_copy,
-            text: TextWidget::new(config).with_text("CustomDBus"),
+            text: TextWidget::new(config)
+                .with_max_width(max_width)
+                .with_scroll(scroll)
+                .with_text("CustomDBus"),
             status,
+            scroll: scroll && max_width.is_some(),
         })
     }
 }
@@ -109,7 +133,12 @@ impl Block for CustomDBus {
             .block_error("custom_dbus", "failed to acquire lock")?)
         .clone();
         self.text.set_text(status);
-        Ok(None)
+        if self.scroll {
+            // Each update moves the text along
+            Ok(Some(Update::Every(Duration::from_secs(1))))
+        } else {
+            Ok(None)
+        }
     }
 
     // Returns the view of the block, comprised of widgets.
@@ -12,7 +12,7 @@ use crate::config::Config;
 use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::scheduler::Task;
-use crate::util::format_percent_bar;
+use crate::util::{format_percent_bar, Rounding, UnitBase};
 use crate::widget::{I3BarWidget, State};
 use crate::widgets::text::TextWidget;
 
@@ -28,15 +28,36 @@ pub enum Unit {
 }
 
 impl Unit {
+    /// The base and power of 1000 or 1024 of the unit, or `None` for `Percent`.
+    fn scale(self) -> Option<(UnitBase, i32)> {
+        match self {
+            Unit::MB => Some((UnitBase::Decimal, 2)),
+            Unit::GB => Some((UnitBase::Decimal, 3)),
+            Unit::TB => Some((UnitBase::Decimal, 4)),
+            Unit::MiB => Some((UnitBase::Binary, 2)),
+            Unit::GiB => Some((UnitBase::Binary, 3)),
+            Unit::TiB => Some((UnitBase::Binary, 4)),
+            Unit::Percent => None,
+        }
+    }
+
+    /// The unit of the same magnitude in `base`, e.g. GiB for GB in base 1024.
+    fn with_base(self, base: UnitBase) -> Unit {
+        match (self.scale(), base) {
+            (Some((_, 2)), UnitBase::Decimal) => Unit::MB,
+            (Some((_, 3)), UnitBase::Decimal) => Unit::GB,
+            (Some((_, 4)), UnitBase::Decimal) => Unit::TB,
+            (Some((_, 2)), UnitBase::Binary) => Unit::MiB,
+            (Some((_, 3)), UnitBase::Binary) => Unit::GiB,
+            (Some((_, 4)), UnitBase::Binary) => Unit::TiB,
+            _ => self,
+        }
+    }
+
     fn bytes_in_unit(unit: Unit, bytes: u64) -> f64 {
-        match unit {
-            Unit::MB => bytes as f64 / 1000. / 1000.,
-            Unit::GB => bytes as f64 / 1000. / 1000. / 1000.,
-            Unit::TB => bytes as f64 / 1000. / 1000. / 1000. / 1000.,
-            Unit::MiB => bytes as f64 / 1024. / 1024.,
-            Unit::GiB => bytes as f64 / 1024. / 1024. / 1024.,
-            Unit::TiB => bytes as f64 / 1024. / 1024. / 1024. / 1024.,
-            Unit::Percent => bytes as f64,
+        match unit.scale() {
+            Some((base, power)) => base.bytes_in(bytes as f64, power),
+            None => bytes as f64,
         }
     }
 }
@@ -62,6 +83,7 @@ pub struct DiskSpace {
     alert: f64,
     show_percentage: bool,
     show_bar: bool,
+    rounding: Rounding,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -81,7 +103,11 @@ pub struct DiskSpaceConfig {
 
     /// Unit that is used to display disk space. Options are MB, MiB, GB, GiB, TB and TiB
     #[serde(default = "DiskSpaceConfig::default_unit")]
-    pub unit: Unit,
+    pub unit: Option<Unit>,
+
+    /// Whether sizes and thresholds are in powers of 1000 or 1024, overriding that of `unit`
+    #[serde(default = "DiskSpaceConfig::default_unit_base")]
+    pub unit_base: Option<UnitBase>,
 
     /// Update interval in seconds
     #[serde(
@@ -90,11 +116,11 @@ pub struct DiskSpaceConfig {
     )]
     pub interval: Duration,
 
-    /// Diskspace warning in GiB (yellow)
+    /// Diskspace warning in GB or GiB, following the base of the unit (yellow)
     #[serde(default = "DiskSpaceConfig::default_warning")]
     pub warning: f64,
 
-    /// Diskspace alert in GiB (red)
+    /// Diskspace alert in GB or GiB, following the base of the unit (red)
     #[serde(default = "DiskSpaceConfig::default_alert")]
     pub alert: f64,
 
@@ -105,6 +131,10 @@ pub struct DiskSpaceConfig {
     /// Show percentage
     #[serde(default = "DiskSpaceConfig::default_show_bar")]
     pub show_bar: bool,
+
+    /// How the sizes and percentage are rounded
+    #[serde(default)]
+    pub rounding: Rounding,
 }
 
 impl DiskSpaceConfig {
@@ -120,8 +150,12 @@ impl DiskSpaceConfig {
         InfoType::Available
     }
 
-    fn default_unit() -> Unit {
-        Unit::GB
+    fn default_unit() -> Option<Unit> {
+        None
+    }
+
+    fn default_unit_base() -> Option<UnitBase> {
+        None
     }
 
     fn default_interval() -> Duration {
@@ -147,10 +181,10 @@ impl DiskSpaceConfig {
 
 impl DiskSpace {
     fn compute_state(&self, bytes: u64, warning: f64, alert: f64) -> State {
-        let value = if self.unit == Unit::Percent {
-            bytes as f64
-        } else {
-            Unit::bytes_in_unit(Unit::GB, bytes)
+        // Thresholds are in GB or GiB, like the sizes shown
+        let value = match self.unit.scale() {
+            Some((base, _)) => base.bytes_in(bytes as f64, 3),
+            None => bytes as f64,
         };
         match self.unit {
             Unit::Percent => match self.info_type {
@@ -187,6 +221,13 @@ impl ConfigBlock for DiskSpace {
         config: Config,
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
+        // The base given on its own applies to the default magnitude, GB or GiB
+        let unit = match (block_config.unit, block_config.unit_base) {
+            (Some(unit), Some(base)) => unit.with_base(base),
+            (Some(unit), None) => unit,
+            (None, base) => Unit::GiB.with_base(base.unwrap_or_default()),
+        };
+
         Ok(DiskSpace {
             id: Uuid::new_v4().to_simple().to_string(),
             update_interval: block_config.interval,
@@ -194,11 +235,12 @@ impl ConfigBlock for DiskSpace {
             alias: block_config.alias,
             path: block_config.path,
             info_type: block_config.info_type,
-            unit: block_config.unit,
+            unit,
             warning: block_config.warning,
             alert: block_config.alert,
             show_percentage: block_config.show_percentage,
             show_bar: block_config.show_bar,
+            rounding: block_config.rounding,
         })
     }
 }
@@ -228,7 +270,11 @@ impl Block for DiskSpace {
                 let converted_used = Unit::bytes_in_unit(self.unit, result);
                 let converted_total = Unit::bytes_in_unit(self.unit, total);
 
-                converted_str = format!("{0:.2}/{1:.2}", converted_used, converted_total);
+                converted_str = format!(
+                    "{}/{}",
+                    self.rounding.format(converted_used, 2),
+                    self.rounding.format(converted_total, 2)
+                );
             }
             InfoType::Used => {
                 result = used;
@@ -237,18 +283,19 @@ impl Block for DiskSpace {
         }
 
         let percentage = (result as f32) / (total as f32) * 100f32;
+        let percentage_str = self.rounding.format(f64::from(percentage), 2);
         if converted_str.is_empty() {
-            converted_str = format!("{0:.2}", converted);
+            converted_str = self.rounding.format(converted, 2);
         }
 
         if self.unit == Unit::Percent {
             self.disk_space
-                .set_text(format!("{0} {1:.2}%", self.alias, percentage));
+                .set_text(format!("{0} {1}%", self.alias, percentage_str));
             result = percentage as u64;
         } else if self.show_percentage {
             self.disk_space.set_text(format!(
-                "{0} {1} ({2:.2}%) {3:?}",
-                self.alias, converted_str, percentage, self.unit
+                "{0} {1} ({2}%) {3:?}",
+                self.alias, converted_str, percentage_str, self.unit
             ));
         } else if self.show_bar {
             self.disk_space.set_text(format!(
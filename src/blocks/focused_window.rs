@@ -12,6 +12,7 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::Config;
 use crate::errors::*;
 use crate::scheduler::Task;
+use crate::util::truncate_width;
 use crate::widget::I3BarWidget;
 use crate::widgets::text::TextWidget;
 
@@ -29,13 +30,14 @@ pub struct FocusedWindow {
     marks: Arc<Mutex<String>>,
     show_marks: MarksType,
     max_width: usize,
+    ellipsis: String,
     id: String,
 }
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct FocusedWindowConfig {
-    /// Truncates titles if longer than max-width
+    /// Truncates titles if longer than max-width, in columns
     #[serde(default = "FocusedWindowConfig::default_max_width")]
     pub max_width: usize,
 
@@ -182,8 +184,9 @@ impl ConfigBlock for FocusedWindow {
 
         Ok(FocusedWindow {
             id,
-            text: TextWidget::new(config),
             max_width: block_config.max_width,
+            ellipsis: config.ellipsis.clone(),
+            text: TextWidget::new(config),
             show_marks: block_config.show_marks,
             title,
             marks,
@@ -198,13 +201,13 @@ impl Block for FocusedWindow {
             .lock()
             .block_error("focused_window", "failed to acquire lock")?)
         .clone();
-        marks_string = marks_string.chars().take(self.max_width).collect();
+        marks_string = truncate_width(&marks_string, self.max_width, &self.ellipsis);
         let mut title_string = (*self
             .title
             .lock()
             .block_error("focused_window", "failed to acquire lock")?)
         .clone();
-        title_string = title_string.chars().take(self.max_width).collect();
+        title_string = truncate_width(&title_string, self.max_width, &self.ellipsis);
         let out_str = match self.show_marks {
             MarksType::None => title_string,
             _ => {
@@ -1,15 +1,25 @@
-use std::time::Duration;
+use std::cmp;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use crossbeam_channel::Sender;
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 
+use crate::alert::{AlertPolicy, AlertPolicyConfig};
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::Config;
-use crate::de::deserialize_duration;
+use crate::de::{deserialize_duration, deserialize_one_or_many};
 use crate::errors::*;
 use crate::widgets::text::TextWidget;
 use crate::widget::{I3BarWidget, State};
 use crate::input::I3BarEvent;
 use crate::scheduler::Task;
+use crate::threshold::Threshold;
+use crate::util::{check_min_interval, FormatTemplate};
 
 use serde::Deserialize;
 use uuid::Uuid;
@@ -18,28 +28,71 @@ pub struct GmailCount {
     text: TextWidget,
     id: String,
     update_interval: Duration,
-    auth_base64: String,
-    threshold_warning: usize,
-    threshold_critical: usize,
+    accounts: Vec<String>,
+    feed_url: String,
+    per_account: bool,
+    format: FormatTemplate,
+    alert: AlertPolicy,
+    newmails: f64,
+    hide_when_empty: bool,
+    /// Whether the count was 0 at the last update, and the block is hidden for it
+    hidden: bool,
+    /// The counts of the last fetch, until they are shown
+    fetched: Arc<Mutex<Option<Vec<Result<f64>>>>>,
+    /// When the fetch in progress started, if there is one
+    fetching: Option<Instant>,
+    /// Whether counts were shown since the last scheduled update, which then waits for the next fetch
+    shown: bool,
+    /// Whether no account could be read the last time
+    failed: bool,
+    tx_update_request: Sender<Task>,
 }
 
+/// How long a fetch may take before the block shows that it is stuck.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
 #[derive(Deserialize, Debug, Default, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct GmailCountConfig {
     /// Update interval in seconds
     #[serde(default = "GmailCountConfig::default_interval", deserialize_with = "deserialize_duration")]
     pub interval: Duration,
-    pub auth_base64: String,
+    /// The credentials of one account, or a list of them to sum up their counts
+    #[serde(default, deserialize_with = "deserialize_one_or_many")]
+    pub auth_base64: Vec<String>,
+    /// Shell command printing the credentials, instead of `auth_base64`
+    #[serde(default)]
+    pub auth_cmd: Option<String>,
+    /// File holding the credentials, instead of `auth_base64`
+    #[serde(default)]
+    pub auth_file: Option<PathBuf>,
+    /// Count the unread mail with this label rather than in the inbox
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Also show the count of each account
+    #[serde(default)]
+    pub per_account: bool,
+    /// Format override, with the placeholders {count} and {account}, the counts of each account
+    #[serde(default = "GmailCountConfig::default_format")]
+    pub format: String,
+    /// Hide the block, icon included, while there is no unread mail
+    #[serde(default)]
+    pub hide_when_empty: bool,
     #[serde(default = "GmailCountConfig::default_threshold_warning")]
     pub threshold_warning: usize,
     #[serde(default = "GmailCountConfig::default_threshold_critical")]
     pub threshold_critical: usize,
+    #[serde(default)]
+    pub alert: AlertPolicyConfig,
 }
 
 impl GmailCountConfig {
     fn default_interval() -> Duration {
         Duration::from_secs(60)
     }
+    fn default_format() -> String {
+        "{count}".to_owned()
+    }
     fn default_threshold_warning() -> usize {
         1 as usize
     }
@@ -48,58 +101,288 @@ impl GmailCountConfig {
     }
 }
 
+/// The credentials from whichever of `auth_base64`, `auth_cmd` and `auth_file` is set. They
+/// are kept out of any error message.
+fn resolve_accounts(block_config: &GmailCountConfig) -> Result<Vec<String>> {
+    let given = [!block_config.auth_base64.is_empty(),
+                 block_config.auth_cmd.is_some(),
+                 block_config.auth_file.is_some()];
+    if given.iter().filter(|&&given| given).count() != 1 {
+        return Err(ConfigurationError(
+            "gmailcount".to_string(),
+            ("exactly one of 'auth_base64', 'auth_cmd' and 'auth_file' has to be set".to_string(),
+             "invalid credentials".to_string()),
+        ));
+    }
+
+    let secret = if let Some(ref cmd) = block_config.auth_cmd {
+        let output = Command::new("sh")
+            .args(&["-c", cmd])
+            .output()
+            .block_error("gmailcount", "failed to run 'auth_cmd'")?;
+        if !output.status.success() {
+            return Err(BlockError("gmailcount".to_owned(), "'auth_cmd' failed".to_owned()));
+        }
+        String::from_utf8(output.stdout).block_error("gmailcount", "'auth_cmd' printed invalid UTF-8")?
+    } else if let Some(ref path) = block_config.auth_file {
+        fs::read_to_string(path).block_error(
+            "gmailcount", &format!("failed to read 'auth_file' {}", path.display()))?
+    } else {
+        return block_config.auth_base64.iter().map(|secret| checked_base64(secret)).collect();
+    };
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(BlockError("gmailcount".to_owned(), "the credentials are empty".to_owned()));
+    }
+    Ok(vec![checked_base64(secret)?])
+}
+
+/// Fails unless `secret` is base64, which is also all that may go into curl's config.
+fn checked_base64(secret: &str) -> Result<String> {
+    if secret.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"+/=".contains(&byte)) {
+        Ok(secret.to_owned())
+    } else {
+        Err(BlockError("gmailcount".to_owned(), "the credentials are not base64".to_owned()))
+    }
+}
+
+const FEED_URL: &str = "https://mail.google.com/mail/feed/atom";
+
+/// The feed of `label`, or of the inbox. The label is percent-encoded, but control characters
+/// are refused, as Gmail has no labels with them anyway.
+fn feed_url(label: Option<&str>) -> Result<String> {
+    let label = match label {
+        Some(label) => label,
+        None => return Ok(FEED_URL.to_owned()),
+    };
+    if label.trim().is_empty() || label.chars().any(char::is_control) {
+        return Err(ConfigurationError(
+            "gmailcount".to_string(),
+            (format!("invalid 'label' {:?}", label), "invalid label".to_string()),
+        ));
+    }
+    let mut url = format!("{}/", FEED_URL);
+    for byte in label.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => url.push(byte as char),
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    Ok(url)
+}
+
 impl ConfigBlock for GmailCount {
     type Config = GmailCountConfig;
 
-    fn new(block_config: Self::Config, config: Config, _tx_update_request: Sender<Task>) -> Result<Self> {
+    fn new(block_config: Self::Config, config: Config, tx_update_request: Sender<Task>) -> Result<Self> {
+        // Every update is a request to Gmail
+        check_min_interval("gmailcount", block_config.interval, Duration::from_secs(5))?;
+        let feed_url = feed_url(block_config.label.as_deref())?;
+        let accounts = resolve_accounts(&block_config)?;
         Ok(GmailCount {
             id: Uuid::new_v4().to_simple().to_string(),
             update_interval: block_config.interval,
             text: TextWidget::new(config.clone())
                 .with_icon("mail")
                 .with_text(""),
-            auth_base64: block_config.auth_base64,
-            threshold_warning: block_config.threshold_warning,
-            threshold_critical: block_config.threshold_critical,
+            accounts,
+            feed_url,
+            per_account: block_config.per_account,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("gmailcount", "Invalid format specified for gmailcount")?,
+            newmails: 0.0,
+            hide_when_empty: block_config.hide_when_empty,
+            hidden: false,
+            fetched: Arc::new(Mutex::new(None)),
+            fetching: None,
+            shown: false,
+            failed: false,
+            tx_update_request,
+            alert: AlertPolicy::new(block_config.alert,
+                                    vec![(State::Critical, Threshold::at_least(block_config.threshold_critical as f64)),
+                                         (State::Warning, Threshold::at_least(block_config.threshold_warning as f64))],
+                                    State::Idle),
         })
     }
 }
 
+/// Fetches the unread feed, failing on network errors and on any status other than 2xx.
+fn fetch_feed(url: &str, auth_base64: &str) -> Result<String> {
+    let mut child = Command::new("curl")
+        .args(&["--silent", "--show-error", "--max-time", "10",
+                // The credentials are read from stdin, where other users can't see them
+                "--config", "-",
+                // The status goes last, after the body
+                "--write-out", "\n%{http_code}",
+                url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .block_error("gmailcount", "failed to run curl, is it installed?")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The credentials are checked to be base64, which needs no escaping in curl's config
+        writeln!(stdin, "header = \"Authorization: Basic {}\"", auth_base64)
+            .block_error("gmailcount", "failed to pass the credentials to curl")?;
+    }
+    let output = child.wait_with_output()
+        .block_error("gmailcount", "failed to wait for curl")?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(BlockError("gmailcount".to_owned(),
+                              format!("failed to fetch the feed: {}", error.trim())));
+    }
+    let data = String::from_utf8(output.stdout).block_error("gmailcount", "the feed is not UTF-8")?;
+    let (body, status) = match data.rfind('\n') {
+        Some(idx) => (&data[..idx], &data[idx+1..]),
+        None => ("", &data[..]),
+    };
+    match status.trim().parse::<u16>() {
+        Ok(200..=299) => Ok(body.to_owned()),
+        Ok(401) => Err(BlockError("gmailcount".to_owned(), "the feed refused 'auth_base64'".to_owned())),
+        _ => Err(BlockError("gmailcount".to_owned(), format!("the feed returned status {}", status.trim()))),
+    }
+}
+
+/// The text between the first `<tag>` and the `</tag>` after it, `None` unless both are there.
+fn tag_content<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    // Both are found at char boundaries, so slicing can't split a character
+    let start = xml.find(&open)? + open.len();
+    let len = xml[start..].find(&close)?;
+    Some(&xml[start..start+len])
+}
+
+/// The unread count in the `<fullcount>` tag of an Atom feed.
+fn parse_count(feed: &str) -> Result<f64> {
+    let count = tag_content(feed, "fullcount")
+        .block_error("gmailcount", "no count in the feed")?;
+    count.trim().parse::<usize>()
+        .map(|newmails| newmails as f64)
+        .block_error("gmailcount", "invalid count in the feed")
+}
+
+impl GmailCount {
+    /// Fetches the feeds in a thread of its own, so a slow network doesn't hold up the other
+    /// blocks. The block is updated again to show the counts once they are in.
+    fn start_fetch(&mut self) -> Result<()> {
+        let accounts = self.accounts.clone();
+        let url = self.feed_url.clone();
+        let fetched = self.fetched.clone();
+        let tx = self.tx_update_request.clone();
+        let id = self.id.clone();
+        thread::Builder::new()
+            .name("gmailcount".into())
+            .spawn(move || {
+                let counts = accounts.iter()
+                    .map(|auth_base64| fetch_feed(&url, auth_base64).and_then(|feed| parse_count(&feed)))
+                    .collect();
+                *fetched.lock().unwrap() = Some(counts);
+                tx.send(Task { id, update_time: Instant::now() }).ok();
+            })
+            .block_error("gmailcount", "failed to start the fetch thread")?;
+        self.fetching = Some(Instant::now());
+        Ok(())
+    }
+
+    fn show(&mut self, counts: Vec<Result<f64>>) -> Result<()> {
+        // Failed accounts show up as "?"
+        let breakdown: Vec<String> = counts.iter()
+            .map(|count| count.as_ref().map_or("?".to_owned(), |count| count.to_string()))
+            .collect();
+        let failed = counts.iter().filter(|count| count.is_err()).count();
+        // Only fail if no account could be read, the others are still worth showing
+        let total = if failed == counts.len() {
+            counts.into_iter().find_map(|count| count.err()).map_or(Ok(0.0), Err)
+        } else {
+            Ok(counts.iter().filter_map(|count| count.as_ref().ok()).sum())
+        };
+        let (mut state, newmails) = match self.alert.reading(total) {
+            Ok(reading) => reading,
+            Err(e) => {
+                // Most likely the network, which may well be back soon
+                eprintln!("{}", e);
+                self.failed = true;
+                self.hidden = false;
+                self.text.set_state(State::Critical);
+                self.text.set_text("?".to_owned());
+                return Ok(());
+            }
+        };
+        self.failed = false;
+        if failed > 0 && (state == State::Idle || state == State::Info || state == State::Good) {
+            state = State::Warning;
+        }
+        self.text.set_state(state);
+        self.text.set_icon(if newmails > 0.0 { "mail_unread" } else { "mail" });
+        self.newmails = newmails;
+        // Accounts which failed are still worth showing
+        self.hidden = self.hide_when_empty && newmails == 0.0 && failed == 0;
+        let count = format!("{}", newmails.round());
+        let values = map!("{count}" => count.clone(),
+                          "{account}" => breakdown.join("/"));
+        let mut text = self.format.render_static_str(&values)?;
+        if self.per_account && self.accounts.len() > 1 {
+            self.text.set_short_text(count);
+            text = format!("{} ({})", text, breakdown.join("/"));
+        }
+        self.text.set_text(text);
+        Ok(())
+    }
+}
+
 impl Block for GmailCount {
     fn update(&mut self) -> Result<Option<Update>> {
-        if let Ok(output) = Command::new("curl")
-            .args(&["-H", &["Authorization: Basic", &self.auth_base64].join(" "),
-                    "https://mail.google.com/mail/feed/atom"])
-            .output() {
-                if output.status.success() {
-                    if let Ok(data) = String::from_utf8(output.stdout) {
-                        if let Some(idx_start) = data.find("<fullcount>") {
-                            if let Some(idx_end) = data.find("</fullcount>") {
-                                if let Ok(newmails) = data[idx_start+11..idx_end].parse::<usize>() {
-                                    let state = {
-                                        if newmails >= self.threshold_critical {
-                                            State::Critical
-                                        } else if newmails >= self.threshold_warning {
-                                            State::Warning
-                                        } else {
-                                            State::Idle
-                                        }
-                                    };
-                                    self.text.set_state(state);
-                                    self.text.set_text(format!("{}", newmails));
-                                }
-                            }
-                        }
-                    }
-                }
+        let counts = self.fetched.lock().block_error("gmailcount", "failed to acquire lock")?.take();
+        match (counts, self.fetching) {
+            (Some(counts), _) => {
+                self.fetching = None;
+                self.shown = true;
+                self.show(counts)?;
+            }
+            // The counts came in on their own, what is returned now is the wait for the next fetch
+            (None, None) if self.shown => self.shown = false,
+            (None, None) => self.start_fetch()?,
+            (None, Some(started)) if started.elapsed() >= FETCH_TIMEOUT => {
+                // The counts are still shown once the fetch is done
+                eprintln!("gmailcount: fetching the feed takes longer than {:?}", FETCH_TIMEOUT);
+                self.failed = true;
+                self.hidden = false;
+                self.text.set_state(State::Warning);
+                self.text.set_text("?".to_owned());
             }
-        Ok(Some(Update::Every(self.update_interval)))
+            (None, Some(_)) => {}
+        }
+        // What is returned when the fetch thread asks for an update is dropped, so a running
+        // fetch is checked on again in case it gets stuck
+        let poll = cmp::min(FETCH_TIMEOUT, self.update_interval);
+        Ok(Some(if self.fetching.is_some() && self.failed {
+            // Not `Every`, which would start the backoff over
+            Update::RetryBackoff { base: poll, max: poll }
+        } else if self.fetching.is_some() {
+            Update::Every(poll)
+        } else if self.failed {
+            Update::RetryBackoff {
+                base: Duration::from_secs(5),
+                max: self.update_interval,
+            }
+        } else {
+            Update::Every(self.update_interval)
+        }))
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.hidden {
+            return vec![];
+        }
         vec![&self.text]
     }
 
+    fn mirror_values(&self) -> HashMap<String, String> {
+        map!("{count}".to_owned() => self.newmails.round().to_string())
+    }
+
     fn click(&mut self, _: &I3BarEvent) -> Result<()> {
         Ok(())
     }
@@ -108,3 +391,45 @@ impl Block for GmailCount {
         &self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::gmailcount::{checked_base64, feed_url, parse_count};
+
+    #[test]
+    fn test_parse_count() {
+        let feed = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed version="0.3" xmlns="http://purl.org/atom/ns#">
+<title>Gmail - Inbox for someone@gmail.com</title>
+<fullcount>3</fullcount>
+<entry><title>fullcount</title></entry>
+</feed>"#;
+        assert_eq!(parse_count(feed).unwrap(), 3.0);
+        assert!(parse_count("<feed></feed>").is_err());
+        assert!(parse_count("<fullcount>many</fullcount>").is_err());
+        assert!(parse_count("<fullcount>-1</fullcount>").is_err());
+        // Reversed tags, a stray close tag before the count doesn't matter
+        assert!(parse_count("</fullcount>3<fullcount>").is_err());
+        assert_eq!(parse_count("</fullcount><fullcount>3</fullcount>").unwrap(), 3.0);
+        // Cut off inside the count or the close tag
+        assert!(parse_count("<fullcount>12").is_err());
+        assert!(parse_count("<fullcount>12</fullc").is_err());
+        assert!(parse_count("<fullco").is_err());
+    }
+
+    #[test]
+    fn test_checked_base64() {
+        assert_eq!(checked_base64("dXNlcjpwYXNz+/==").unwrap(), "dXNlcjpwYXNz+/==");
+        assert!(checked_base64("dXNlcjpw\"\nurl = evil").is_err());
+        assert!(checked_base64("user:pass").is_err());
+    }
+
+    #[test]
+    fn test_feed_url() {
+        assert_eq!(feed_url(None).unwrap(), "https://mail.google.com/mail/feed/atom");
+        assert_eq!(feed_url(Some("urgent")).unwrap(), "https://mail.google.com/mail/feed/atom/urgent");
+        assert_eq!(feed_url(Some("to do/ä")).unwrap(), "https://mail.google.com/mail/feed/atom/to%20do%2F%C3%A4");
+        assert!(feed_url(Some("")).is_err());
+        assert!(feed_url(Some("urgent\n")).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use uuid::Uuid;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::Config;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::idle::{IdleBackend, IdleSource, SystemIdle};
+use crate::scheduler::Task;
+use crate::util::{check_min_interval, FormatTemplate};
+use crate::widget::I3BarWidget;
+use crate::widgets::text::TextWidget;
+
+/// Shows how long there was no input, for scripts and Mirror blocks to pick up.
+pub struct Idle {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    update_interval: Duration,
+    idle_source: Box<dyn IdleSource>,
+    /// The idle time at the last update, in seconds
+    idle: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IdleConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "IdleConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override, with the placeholders {idle}, in seconds, and {minutes}
+    #[serde(default = "IdleConfig::default_format")]
+    pub format: String,
+
+    /// X screen whose input counts, the default screen if not set
+    #[serde(default)]
+    pub screen: Option<usize>,
+
+    /// Where the idle time is read from, detected from the environment if not given
+    #[serde(default)]
+    pub force_backend: Option<IdleBackend>,
+}
+
+impl IdleConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn default_format() -> String {
+        "{idle}s".to_owned()
+    }
+}
+
+impl ConfigBlock for Idle {
+    type Config = IdleConfig;
+
+    fn new(block_config: Self::Config, config: Config, _tx: Sender<Task>) -> Result<Self> {
+        check_min_interval("idle", block_config.interval, Duration::from_secs(1))?;
+        Ok(Idle {
+            id: Uuid::new_v4().to_simple().to_string(),
+            text: TextWidget::new(config),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("idle", "Invalid format specified for idle")?,
+            update_interval: block_config.interval,
+            idle_source: Box::new(SystemIdle::new(
+                "idle",
+                block_config.force_backend,
+                block_config.screen,
+            )?),
+            idle: 0,
+        })
+    }
+}
+
+impl Block for Idle {
+    fn update(&mut self) -> Result<Option<Update>> {
+        self.idle = self.idle_source.idle()? / 1000;
+        let values = map!(
+            "{idle}" => self.idle.to_string(),
+            "{minutes}" => (self.idle / 60).to_string()
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+        Ok(Some(Update::Every(self.update_interval)))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn mirror_values(&self) -> HashMap<String, String> {
+        map!("{idle}".to_owned() => self.idle.to_string())
+    }
+
+    fn shutdown(&mut self) {
+        self.idle_source.close();
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::idle::Idle;
+    use crate::blocks::Block;
+    use crate::config::Config;
+    use crate::errors::*;
+    use crate::idle::IdleSource;
+    use crate::util::FormatTemplate;
+    use crate::widgets::text::TextWidget;
+    use std::time::Duration;
+
+    struct MockIdle(u64);
+
+    impl IdleSource for MockIdle {
+        fn idle(&self) -> Result<u64> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn test_idle_update() {
+        let mut block = Idle {
+            id: "idle".to_owned(),
+            text: TextWidget::new(Config::default()),
+            format: FormatTemplate::from_string("{minutes}m").unwrap(),
+            update_interval: Duration::from_secs(1),
+            idle_source: Box::new(MockIdle(125_400)),
+            idle: 0,
+        };
+        block.update().unwrap();
+        assert_eq!(block.mirror_values()["{idle}"], "125");
+    }
+}
@@ -0,0 +1,243 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, TimeZone};
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use serde_json::value::Value;
+use uuid::Uuid;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::Config;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widget::{I3BarWidget, State};
+use crate::widgets::button::ButtonWidget;
+
+/// The most recent entry logged by the unit at the configured priority or above.
+struct Entry {
+    time: DateTime<Local>,
+    message: String,
+}
+
+pub struct Journal {
+    id: String,
+    text: ButtonWidget,
+    format: FormatTemplate,
+    time_format: String,
+    window: Duration,
+    update_interval: Duration,
+    last_entry: Arc<Mutex<Option<Entry>>>,
+    /// Entries up to this time have been acknowledged by clicking the block
+    acknowledged: Option<DateTime<Local>>,
+    journalctl: Child,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct JournalConfig {
+    /// The systemd unit to watch
+    pub unit: String,
+
+    /// Watch a unit of the user's service manager instead of the system one
+    #[serde(default = "JournalConfig::default_user")]
+    pub user: bool,
+
+    /// Lowest priority, as a name or number, of the entries to show
+    #[serde(default = "JournalConfig::default_priority")]
+    pub priority: String,
+
+    /// How long an entry is shown for
+    #[serde(
+        default = "JournalConfig::default_window",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub window: Duration,
+
+    /// How often to check whether the entry shown has left the window, in seconds
+    #[serde(
+        default = "JournalConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "JournalConfig::default_format")]
+    pub format: String,
+
+    /// Format of the `{time}` placeholder
+    #[serde(default = "JournalConfig::default_time_format")]
+    pub time_format: String,
+}
+
+impl JournalConfig {
+    fn default_user() -> bool {
+        false
+    }
+
+    fn default_priority() -> String {
+        "err".to_owned()
+    }
+
+    fn default_window() -> Duration {
+        Duration::from_secs(3600)
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{unit} {time}".to_owned()
+    }
+
+    fn default_time_format() -> String {
+        "%H:%M".to_owned()
+    }
+}
+
+/// Reads an entry from a line of `journalctl --output=json`.
+fn parse_entry(line: &str) -> Option<Entry> {
+    let entry: Value = serde_json::from_str(line).ok()?;
+    let micros: i64 = entry.get("__REALTIME_TIMESTAMP")?.as_str()?.parse().ok()?;
+    // Messages which aren't valid UTF-8 are sent as an array of bytes
+    let message = match entry.get("MESSAGE") {
+        Some(Value::String(message)) => message.clone(),
+        _ => String::new(),
+    };
+    Some(Entry {
+        time: Local.timestamp(
+            micros.div_euclid(1_000_000),
+            (micros.rem_euclid(1_000_000) * 1000) as u32,
+        ),
+        message,
+    })
+}
+
+impl ConfigBlock for Journal {
+    type Config = JournalConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().to_simple().to_string();
+
+        let unit_arg = if block_config.user {
+            format!("--user-unit={}", block_config.unit)
+        } else {
+            format!("--unit={}", block_config.unit)
+        };
+        // Start with the latest entry, so a recent error is shown right away
+        let mut journalctl = Command::new("journalctl")
+            .args(&["--follow", "--lines=1", "--output=json"])
+            .arg(unit_arg)
+            .arg(format!("--priority={}", block_config.priority))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .block_error("journal", "failed to run journalctl")?;
+        let stdout = journalctl
+            .stdout
+            .take()
+            .block_error("journal", "failed to read from journalctl")?;
+
+        let last_entry = Arc::new(Mutex::new(None));
+        let last_entry_thread = last_entry.clone();
+        let id_thread = id.clone();
+        thread::Builder::new()
+            .name("journal".into())
+            .spawn(move || {
+                for line in BufReader::new(stdout).lines() {
+                    let entry = match line.ok().as_deref().map(parse_entry) {
+                        Some(Some(entry)) => entry,
+                        Some(None) => continue,
+                        None => break,
+                    };
+                    *last_entry_thread.lock().unwrap() = Some(entry);
+                    tx.send(Task {
+                        id: id_thread.clone(),
+                        update_time: Instant::now(),
+                    })
+                    .unwrap();
+                }
+            })
+            .block_error("journal", "failed to start the journal thread")?;
+
+        Ok(Journal {
+            text: ButtonWidget::new(config, &id),
+            id,
+            format: FormatTemplate::from_string(
+                &block_config.format.replace("{unit}", &block_config.unit),
+            )
+            .block_error("journal", "Invalid format specified for journal")?,
+            time_format: block_config.time_format,
+            window: block_config.window,
+            update_interval: block_config.interval,
+            last_entry,
+            acknowledged: None,
+            journalctl,
+        })
+    }
+}
+
+impl Block for Journal {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let last_entry = self
+            .last_entry
+            .lock()
+            .block_error("journal", "failed to acquire lock")?;
+
+        // Entries older than the window, or acknowledged by a click, don't count
+        let now = Local::now();
+        let window =
+            chrono::Duration::from_std(self.window).block_error("journal", "invalid window")?;
+        let shown = last_entry.as_ref().filter(|entry| {
+            entry.time + window > now && self.acknowledged.map_or(true, |ack| entry.time > ack)
+        });
+
+        match shown {
+            Some(entry) => {
+                let values = map!(
+                    "{time}" => entry.time.format(&self.time_format).to_string(),
+                    "{message}" => entry.message.clone()
+                );
+                self.text.set_text(self.format.render_static_str(&values)?);
+                self.text.set_state(State::Critical);
+            }
+            None => {
+                self.text.set_text("ok".to_owned());
+                self.text.set_state(State::Good);
+            }
+        }
+
+        // New entries are sent by journalctl, this only makes old ones go away
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let Some(ref name) = e.name {
+            if name.as_str() == self.id && e.button == MouseButton::Left {
+                self.acknowledged = Some(Local::now());
+                self.update()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {
+        self.journalctl.kill().ok();
+        self.journalctl.wait().ok();
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
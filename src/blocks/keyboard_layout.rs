@@ -4,7 +4,9 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
+#[cfg(feature = "dbus")]
 use dbus::ffidisp::stdintf::org_freedesktop_dbus::Properties;
+#[cfg(feature = "dbus")]
 use dbus::{
     ffidisp::{MsgHandlerResult, MsgHandlerType},
     Message,
@@ -17,13 +19,14 @@ use uuid::Uuid;
 
 use crate::blocks::Update;
 use crate::blocks::{Block, ConfigBlock};
-use crate::config::Config;
+use crate::config::{Config, LogicalDirection, Scrolling};
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::util::FormatTemplate;
 use crate::widget::I3BarWidget;
-use crate::widgets::text::TextWidget;
+use crate::widgets::button::ButtonWidget;
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "lowercase")]
@@ -51,6 +54,19 @@ pub trait KeyboardLayoutMonitor {
     /// Monitor layout changes and send updates via the `update_request`
     /// channel. By default, this method does nothing.
     fn monitor(&self, _id: String, _update_request: Sender<Task>) {}
+
+    /// Switch to the given layout, as named by `keyboard_layout`.
+    fn set_keyboard_layout(&self, layout: &str) -> Result<()>;
+}
+
+/// Index of `layout` in the layouts configured in X, which is how kbdd refers to them.
+#[cfg(feature = "dbus")]
+fn setxkbmap_layout_index(layout: &str) -> Result<u32> {
+    setxkbmap_layouts()?
+        .split(',')
+        .position(|l| l.split(':').next() == Some(layout))
+        .map(|idx| idx as u32)
+        .block_error("keyboard_layout", "Layout is not configured in X.")
 }
 
 pub struct SetXkbMap;
@@ -101,12 +117,22 @@ impl KeyboardLayoutMonitor for SetXkbMap {
     fn must_poll(&self) -> bool {
         true
     }
+
+    fn set_keyboard_layout(&self, layout: &str) -> Result<()> {
+        Command::new("setxkbmap")
+            .args(&["-layout", layout])
+            .status()
+            .block_error("keyboard_layout", "Failed to execute setxkbmap.")
+            .map(|_| ())
+    }
 }
 
+#[cfg(feature = "dbus")]
 pub struct LocaleBus {
     con: dbus::ffidisp::Connection,
 }
 
+#[cfg(feature = "dbus")]
 impl LocaleBus {
     pub fn new() -> Result<Self> {
         let con = dbus::ffidisp::Connection::get_private(dbus::ffidisp::BusType::System)
@@ -116,6 +142,7 @@ impl LocaleBus {
     }
 }
 
+#[cfg(feature = "dbus")]
 impl KeyboardLayoutMonitor for LocaleBus {
     fn keyboard_layout(&self) -> Result<String> {
         let layout: String = self
@@ -131,6 +158,34 @@ impl KeyboardLayoutMonitor for LocaleBus {
         false
     }
 
+    fn set_keyboard_layout(&self, layout: &str) -> Result<()> {
+        // Keep the model and options, but not the variant, which belongs to the old layout,
+        // and apply the layout to the console too
+        let locale =
+            self.con
+                .with_path("org.freedesktop.locale1", "/org/freedesktop/locale1", 1000);
+        let model: String = locale
+            .get("org.freedesktop.locale1", "X11Model")
+            .block_error("locale", "Failed to get X11Model property.")?;
+        let options: String = locale
+            .get("org.freedesktop.locale1", "X11Options")
+            .block_error("locale", "Failed to get X11Options property.")?;
+        let msg = Message::new_method_call(
+            "org.freedesktop.locale1",
+            "/org/freedesktop/locale1",
+            "org.freedesktop.locale1",
+            "SetX11Keyboard",
+        )
+        .block_error("locale", "Failed to create D-Bus message")?
+        .append3(layout, model, "")
+        .append3(options, true, false);
+
+        self.con
+            .send_with_reply_and_block(msg, 1000)
+            .block_error("locale", "Failed to set the X11 keyboard layout")
+            .map(|_| ())
+    }
+
     /// Monitor Locale property changes in a separate thread and send updates
     /// via the `update_request` channel.
     fn monitor(&self, id: String, update_request: Sender<Task>) {
@@ -170,11 +225,13 @@ impl KeyboardLayoutMonitor for LocaleBus {
 // KbdDaemonBus - use this option if you have kbdd running (https://github.com/qnikst/kbdd,
 // also available in AUR and Debian) running, which enables per window keyboard layout,
 // really handy for dual-language typists who often change window focus
+#[cfg(feature = "dbus")]
 pub struct KbdDaemonBus {
     // extracted from kbdd dbus message
     kbdd_layout_id: Arc<Mutex<u32>>,
 }
 
+#[cfg(feature = "dbus")]
 impl KbdDaemonBus {
     pub fn new() -> Result<Self> {
         Command::new("setxkbmap")
@@ -215,6 +272,7 @@ impl KbdDaemonBus {
     }
 }
 
+#[cfg(feature = "dbus")]
 impl KeyboardLayoutMonitor for KbdDaemonBus {
     fn keyboard_layout(&self) -> Result<String> {
         let layouts_str = setxkbmap_layouts()?;
@@ -242,6 +300,24 @@ impl KeyboardLayoutMonitor for KbdDaemonBus {
         false
     }
 
+    fn set_keyboard_layout(&self, layout: &str) -> Result<()> {
+        let c = dbus::ffidisp::Connection::get_private(dbus::ffidisp::BusType::Session)
+            .block_error("kbddaemonbus", "can't connect to dbus")?;
+
+        let send_msg = Message::new_method_call(
+            "ru.gentoo.KbddService",
+            "/ru/gentoo/KbddService",
+            "ru.gentoo.kbdd",
+            "set_layout",
+        )
+        .block_error("kbddaemonbus", "Create set-layout message failure")?
+        .append1(setxkbmap_layout_index(layout)?);
+
+        c.send_with_reply_and_block(send_msg, 5000)
+            .block_error("kbddaemonbus", "Is kbdd running?")
+            .map(|_| ())
+    }
+
     // Monitor KbdDaemon 'layoutChanged' property in a separate thread and send updates
     // via the `update_request` channel.
     fn monitor(&self, id: String, update_request: Sender<Task>) {
@@ -279,8 +355,10 @@ impl KeyboardLayoutMonitor for KbdDaemonBus {
     }
 }
 
+#[cfg(feature = "dbus")]
 struct KbddMessageHandler(Arc<Mutex<u32>>);
 
+#[cfg(feature = "dbus")]
 impl dbus::ffidisp::MsgHandler for KbddMessageHandler {
     fn handler_type(&self) -> MsgHandlerType {
         dbus::ffidisp::MsgHandlerType::MsgType(dbus::MessageType::Signal)
@@ -302,6 +380,7 @@ impl dbus::ffidisp::MsgHandler for KbddMessageHandler {
 }
 
 pub struct Sway {
+    sway_kb_identifier: String,
     sway_kb_layout: Arc<Mutex<String>>,
 }
 
@@ -318,6 +397,7 @@ impl Sway {
             .block_error("sway", "Failed to get xkb_active_layout_name.")?;
 
         Ok(Sway {
+            sway_kb_identifier,
             sway_kb_layout: Arc::new(Mutex::new(layout)),
         })
     }
@@ -333,6 +413,31 @@ impl KeyboardLayoutMonitor for Sway {
         false
     }
 
+    fn set_keyboard_layout(&self, layout: &str) -> Result<()> {
+        let mut con = Connection::new().block_error("sway", "Failed to connect to sway.")?;
+        let idx = con
+            .get_inputs()
+            .block_error("sway", "Failed to get inputs.")?
+            .into_iter()
+            .find(|input| input.identifier == self.sway_kb_identifier)
+            .and_then(|input| input.xkb_layout_names.iter().position(|l| l == layout))
+            .block_error("sway", "Layout is not configured for the keyboard.")?;
+
+        let outcomes = con
+            .run_command(format!(
+                "input \"{}\" xkb_switch_layout {}",
+                self.sway_kb_identifier, idx
+            ))
+            .block_error("sway", "Failed to switch layout.")?;
+        match outcomes.into_iter().find(|outcome| !outcome.success) {
+            Some(outcome) => Err(BlockError(
+                "sway".to_string(),
+                outcome.error.unwrap_or_default(),
+            )),
+            None => Ok(()),
+        }
+    }
+
     /// Monitor layout changes in a separate thread and send updates
     /// via the `update_request` channel.
     fn monitor(&self, id: String, update_request: Sender<Task>) {
@@ -395,6 +500,9 @@ pub struct KeyboardLayoutConfig {
     interval: Duration,
 
     sway_kb_identifier: String,
+
+    /// Layouts to switch between by clicking and scrolling, in that order
+    layouts: Vec<String>,
 }
 
 impl KeyboardLayoutConfig {
@@ -409,10 +517,12 @@ impl KeyboardLayoutConfig {
 
 pub struct KeyboardLayout {
     id: String,
-    output: TextWidget,
+    output: ButtonWidget,
     monitor: Box<dyn KeyboardLayoutMonitor>,
     update_interval: Option<Duration>,
     format: FormatTemplate,
+    layouts: Vec<String>,
+    scrolling: Scrolling,
 }
 
 impl ConfigBlock for KeyboardLayout {
@@ -422,16 +532,25 @@ impl ConfigBlock for KeyboardLayout {
         let id: String = Uuid::new_v4().to_simple().to_string();
         let monitor: Box<dyn KeyboardLayoutMonitor> = match block_config.driver {
             KeyboardLayoutDriver::SetXkbMap => Box::new(SetXkbMap::new()?),
+            #[cfg(feature = "dbus")]
             KeyboardLayoutDriver::LocaleBus => {
                 let monitor = LocaleBus::new()?;
                 monitor.monitor(id.clone(), send);
                 Box::new(monitor)
             }
+            #[cfg(feature = "dbus")]
             KeyboardLayoutDriver::KbddBus => {
                 let monitor = KbdDaemonBus::new()?;
                 monitor.monitor(id.clone(), send);
                 Box::new(monitor)
             }
+            #[cfg(not(feature = "dbus"))]
+            KeyboardLayoutDriver::LocaleBus | KeyboardLayoutDriver::KbddBus => {
+                return Err(BlockError(
+                    "keyboard_layout".to_string(),
+                    "The localebus and kbddbus drivers need the dbus feature".to_string(),
+                ));
+            }
             KeyboardLayoutDriver::Sway => {
                 let monitor = Sway::new(block_config.sway_kb_identifier)?;
                 monitor.monitor(id.clone(), send);
@@ -444,10 +563,12 @@ impl ConfigBlock for KeyboardLayout {
             None
         };
         Ok(KeyboardLayout {
+            output: ButtonWidget::new(config.clone(), &id),
             id,
-            output: TextWidget::new(config),
             monitor,
             update_interval,
+            layouts: block_config.layouts,
+            scrolling: config.scrolling,
             format: FormatTemplate::from_string(&block_config.format).block_error(
                 "keyboard_layout",
                 "Invalid format specified for keyboard_layout",
@@ -473,4 +594,29 @@ impl Block for KeyboardLayout {
     fn view(&self) -> Vec<&dyn I3BarWidget> {
         vec![&self.output]
     }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.name.as_deref() != Some(self.id.as_str()) || self.layouts.is_empty() {
+            return Ok(());
+        }
+
+        // Clicking and scrolling up go forwards through the list, wrapping around at the end
+        let forward = match self.scrolling.to_logical_direction(event.button) {
+            Some(LogicalDirection::Up) => true,
+            Some(LogicalDirection::Down) => false,
+            None if event.button == MouseButton::Left => true,
+            None => return Ok(()),
+        };
+        let current = self.monitor.keyboard_layout()?;
+        let len = self.layouts.len();
+        let next = match self.layouts.iter().position(|l| *l == current) {
+            Some(idx) if forward => (idx + 1) % len,
+            Some(idx) => (idx + len - 1) % len,
+            None => 0,
+        };
+
+        self.monitor.set_keyboard_layout(&self.layouts[next])?;
+        self.update()?;
+        Ok(())
+    }
 }
@@ -13,7 +13,8 @@ use crate::config::Config;
 use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::scheduler::Task;
-use crate::util::FormatTemplate;
+use crate::threshold::{Sample, Threshold};
+use crate::util::{FormatTemplate, Scaling};
 use crate::widget::{I3BarWidget, State};
 use crate::widgets::text::TextWidget;
 
@@ -23,9 +24,11 @@ pub struct Load {
     format: FormatTemplate,
     id: String,
     update_interval: Duration,
-    minimum_info: f32,
-    minimum_warning: f32,
-    minimum_critical: f32,
+    minimum_info: Threshold,
+    minimum_warning: Threshold,
+    minimum_critical: Threshold,
+    prev_load: Option<f64>,
+    scaling: Option<Scaling>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -41,15 +44,19 @@ pub struct LoadConfig {
 
     /// Minimum load, where state is set to info
     #[serde(default = "LoadConfig::default_info")]
-    pub info: f32,
+    pub info: Threshold,
 
     /// Minimum load, where state is set to warning
     #[serde(default = "LoadConfig::default_warning")]
-    pub warning: f32,
+    pub warning: Threshold,
 
     /// Minimum load, where state is set to critical
     #[serde(default = "LoadConfig::default_critical")]
-    pub critical: f32,
+    pub critical: Threshold,
+
+    /// Transform applied to the displayed load averages, which the thresholds then apply to
+    #[serde(default)]
+    pub scaling: Option<Scaling>,
 }
 
 impl LoadConfig {
@@ -61,16 +68,16 @@ impl LoadConfig {
         Duration::from_secs(5)
     }
 
-    fn default_info() -> f32 {
-        0.3
+    fn default_info() -> Threshold {
+        Threshold::Above(0.3)
     }
 
-    fn default_warning() -> f32 {
-        0.6
+    fn default_warning() -> Threshold {
+        Threshold::Above(0.6)
     }
 
-    fn default_critical() -> f32 {
-        0.9
+    fn default_critical() -> Threshold {
+        Threshold::Above(0.9)
     }
 }
 
@@ -110,6 +117,11 @@ impl ConfigBlock for Load {
             minimum_info: block_config.info,
             minimum_warning: block_config.warning,
             minimum_critical: block_config.critical,
+            prev_load: None,
+            scaling: match block_config.scaling {
+                Some(scaling) => Some(scaling.validated("load")?),
+                None => None,
+            },
             format: FormatTemplate::from_string(&block_config.format)
                 .block_error("load", "Invalid format specified for load")?,
             text,
@@ -117,6 +129,17 @@ impl ConfigBlock for Load {
     }
 }
 
+impl Load {
+    /// What the thresholds are compared against: the 1m load as displayed if it is scaled,
+    /// the load per core otherwise.
+    fn threshold_value(&self, load_1m: f32) -> f64 {
+        match self.scaling {
+            Some(scaling) => scaling.apply(f64::from(load_1m)),
+            None => f64::from(load_1m / self.logical_cores as f32),
+        }
+    }
+}
+
 impl Block for Load {
     fn update(&mut self) -> Result<Option<Update>> {
         let mut f = OpenOptions::new()
@@ -132,19 +155,30 @@ impl Block for Load {
 
         let split: Vec<&str> = (&loadavg).split(' ').collect();
 
-        let values = map!("{1m}" => split[0],
-                          "{5m}" => split[1],
-                          "{15m}" => split[2]);
-
-        let used_perc = values["{1m}"]
-            .parse::<f32>()
-            .block_error("load", "failed to parse float percentage")?
-            / self.logical_cores as f32;
+        let mut averages = [0f32; 3];
+        for (average, raw) in averages.iter_mut().zip(split.iter()) {
+            *average = raw
+                .parse::<f32>()
+                .block_error("load", "failed to parse float percentage")?;
+        }
 
-        self.text.set_state(match used_perc {
-            x if x > self.minimum_critical => State::Critical,
-            x if x > self.minimum_warning => State::Warning,
-            x if x > self.minimum_info => State::Info,
+        let scaled = |average: f32| {
+            let average = f64::from(average);
+            format!("{:.2}", self.scaling.map_or(average, |s| s.apply(average)))
+        };
+        let values = map!("{1m}" => scaled(averages[0]),
+                          "{5m}" => scaled(averages[1]),
+                          "{15m}" => scaled(averages[2]));
+
+        let sample = Sample {
+            value: self.threshold_value(averages[0]),
+            previous: self.prev_load,
+        };
+        self.prev_load = Some(sample.value);
+        self.text.set_state(match sample {
+            s if self.minimum_critical.reached(s) => State::Critical,
+            s if self.minimum_warning.reached(s) => State::Warning,
+            s if self.minimum_info.reached(s) => State::Info,
             _ => State::Idle,
         });
 
@@ -161,3 +195,39 @@ impl Block for Load {
         &self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::load::Load;
+    use crate::config::Config;
+    use crate::threshold::Threshold;
+    use crate::util::{FormatTemplate, Scaling};
+    use crate::widgets::text::TextWidget;
+    use std::time::Duration;
+
+    fn load(scaling: Option<Scaling>) -> Load {
+        Load {
+            text: TextWidget::new(Config::default()),
+            logical_cores: 4,
+            format: FormatTemplate::from_string("{1m}").unwrap(),
+            id: "load".to_owned(),
+            update_interval: Duration::from_secs(5),
+            minimum_info: Threshold::Above(0.3),
+            minimum_warning: Threshold::Above(0.6),
+            minimum_critical: Threshold::Above(0.9),
+            prev_load: None,
+            scaling,
+        }
+    }
+
+    #[test]
+    fn test_threshold_value() {
+        assert_eq!(load(None).threshold_value(2.0), 0.5);
+        let percent = Scaling {
+            scale: 100.0,
+            divisor: 4.0,
+            offset: 0.0,
+        };
+        assert_eq!(load(Some(percent)).threshold_value(2.0), 50.0);
+    }
+}
@@ -1,6 +1,5 @@
 use crossbeam_channel::Sender;
 use serde_derive::Deserialize;
-use std::fmt;
 use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
 use std::str::FromStr;
@@ -27,51 +26,24 @@ pub enum Memtype {
 
 #[derive(Clone, Copy)]
 enum Unit {
-    MiB(u64),
-    GiB(f32),
     KiB(u64),
 }
 
-impl fmt::Display for Unit {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Unit::MiB(n) => n.fmt(f),
-            Unit::KiB(n) => n.fmt(f),
-            Unit::GiB(n) => n.fmt(f),
-        }
-    }
-}
-
 impl Unit {
     fn n(&self) -> u64 {
-        match self.kib() {
-            Unit::KiB(n) => n,
-            _ => 0,
-        }
-    }
-
-    fn gib(&self) -> Unit {
         match *self {
-            Unit::KiB(n) => Unit::GiB((n as f32) / 1024f32.powi(2)),
-            Unit::MiB(n) => Unit::GiB((n as f32) / 1024f32),
-            Unit::GiB(n) => Unit::GiB(n),
+            Unit::KiB(n) => n,
         }
     }
 
-    fn mib(&self) -> Unit {
-        match *self {
-            Unit::KiB(n) => Unit::MiB(n / 1024),
-            Unit::MiB(n) => Unit::MiB(n),
-            Unit::GiB(n) => Unit::MiB((n * 1024f32) as u64),
-        }
+    /// Whole MB or MiB.
+    fn mega(&self, base: UnitBase) -> u64 {
+        base.bytes_in(self.n() as f64 * 1024.0, 2) as u64
     }
 
-    fn kib(&self) -> Unit {
-        match *self {
-            Unit::KiB(n) => Unit::KiB(n),
-            Unit::MiB(n) => Unit::KiB(n * 1024),
-            Unit::GiB(n) => Unit::KiB((n * 1024f32.powi(2)) as u64),
-        }
+    /// GB or GiB.
+    fn giga(&self, base: UnitBase) -> f64 {
+        base.bytes_in(self.n() as f64 * 1024.0, 3)
     }
 
     fn percent(&self, reference: Unit) -> f32 {
@@ -165,6 +137,8 @@ pub struct Memory {
     tx_update_request: Sender<Task>,
     warning: (f64, f64),
     critical: (f64, f64),
+    rounding: Rounding,
+    unit_base: UnitBase,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -211,6 +185,14 @@ pub struct MemoryConfig {
     /// Percentage of swap usage, where state is set to critical
     #[serde(default = "MemoryConfig::default_critical_swap")]
     pub critical_swap: f64,
+
+    /// How the GiB values and percentages are rounded
+    #[serde(default)]
+    pub rounding: Rounding,
+
+    /// Whether the MiB and GiB values are in powers of 1000 or 1024
+    #[serde(default)]
+    pub unit_base: UnitBase,
 }
 
 impl MemoryConfig {
@@ -257,6 +239,8 @@ impl MemoryConfig {
 
 impl Memory {
     fn format_insert_values(&mut self, mem_state: Memstate) -> Result<String> {
+        let rounding = self.rounding;
+        let base = self.unit_base;
         let mem_total = Unit::KiB(mem_state.mem_total());
         let mem_free = Unit::KiB(mem_state.mem_free());
         let swap_total = Unit::KiB(mem_state.swap_total());
@@ -269,49 +253,51 @@ impl Memory {
         let mem_avail = Unit::KiB(mem_total.n() - mem_used.n());
 
         let values = map!(
-            "{MTg}" => format!("{:.1}", mem_total.gib()),
-            "{MTm}" => format!("{}", mem_total.mib()),
-            "{MFg}" => format!("{:.1}", mem_free.gib()),
-            "{MFm}" => format!("{}", mem_free.mib()),
-            "{MFp}" => format!("{:.2}", mem_free.percent(mem_total)),
-            "{MFpi}" => format!("{:02}", mem_free.percent(mem_total) as i32),
+            "{Um}" => base.label(2).to_owned(),
+            "{Ug}" => base.label(3).to_owned(),
+            "{MTg}" => rounding.format(mem_total.giga(base), 1),
+            "{MTm}" => format!("{}", mem_total.mega(base)),
+            "{MFg}" => rounding.format(mem_free.giga(base), 1),
+            "{MFm}" => format!("{}", mem_free.mega(base)),
+            "{MFp}" => rounding.format(f64::from(mem_free.percent(mem_total)), 2),
+            "{MFpi}" => format!("{:0>2}", rounding.format(f64::from(mem_free.percent(mem_total)), 0)),
             "{MFpb}" => format_percent_bar(mem_free.percent(mem_total)),
-            "{MUg}" => format!("{:.1}", mem_total_used.gib()),
-            "{MUm}" => format!("{}", mem_total_used.mib()),
-            "{MUp}" => format!("{:.2}", mem_total_used.percent(mem_total)),
-            "{MUpi}" => format!("{:02}", mem_total_used.percent(mem_total) as i32),
+            "{MUg}" => rounding.format(mem_total_used.giga(base), 1),
+            "{MUm}" => format!("{}", mem_total_used.mega(base)),
+            "{MUp}" => rounding.format(f64::from(mem_total_used.percent(mem_total)), 2),
+            "{MUpi}" => format!("{:0>2}", rounding.format(f64::from(mem_total_used.percent(mem_total)), 0)),
             "{MUpb}" => format_percent_bar(mem_total_used.percent(mem_total)),
-            "{Mug}" => format!("{:.1}", mem_used.gib()),
-            "{Mum}" => format!("{}", mem_used.mib()),
-            "{Mup}" => format!("{:.2}", mem_used.percent(mem_total)),
-            "{Mupi}" => format!("{:02}", mem_used.percent(mem_total) as i32),
+            "{Mug}" => rounding.format(mem_used.giga(base), 1),
+            "{Mum}" => format!("{}", mem_used.mega(base)),
+            "{Mup}" => rounding.format(f64::from(mem_used.percent(mem_total)), 2),
+            "{Mupi}" => format!("{:0>2}", rounding.format(f64::from(mem_used.percent(mem_total)), 0)),
             "{Mupb}" => format_percent_bar(mem_used.percent(mem_total)),
-            "{MAg}" => format!("{:.1}", mem_avail.gib()),
-            "{MAm}" => format!("{}", mem_avail.mib()),
-            "{MAp}" => format!("{:.2}", mem_avail.percent(mem_total)),
-            "{MApi}" => format!("{:02}", mem_avail.percent(mem_total) as i32),
+            "{MAg}" => rounding.format(mem_avail.giga(base), 1),
+            "{MAm}" => format!("{}", mem_avail.mega(base)),
+            "{MAp}" => rounding.format(f64::from(mem_avail.percent(mem_total)), 2),
+            "{MApi}" => format!("{:0>2}", rounding.format(f64::from(mem_avail.percent(mem_total)), 0)),
             "{MApb}" => format_percent_bar(mem_avail.percent(mem_total)),
-            "{STg}" => format!("{:.1}", swap_total.gib()),
-            "{STm}" => format!("{}", swap_total.mib()),
-            "{SFg}" => format!("{:.1}", swap_free.gib()),
-            "{SFm}" => format!("{}", swap_free.mib()),
-            "{SFp}" => format!("{:.2}", swap_free.percent(swap_total)),
-            "{SFpi}" => format!("{:02}", swap_free.percent(swap_total) as i32),
+            "{STg}" => rounding.format(swap_total.giga(base), 1),
+            "{STm}" => format!("{}", swap_total.mega(base)),
+            "{SFg}" => rounding.format(swap_free.giga(base), 1),
+            "{SFm}" => format!("{}", swap_free.mega(base)),
+            "{SFp}" => rounding.format(f64::from(swap_free.percent(swap_total)), 2),
+            "{SFpi}" => format!("{:0>2}", rounding.format(f64::from(swap_free.percent(swap_total)), 0)),
             "{SFpb}" => format_percent_bar(swap_free.percent(swap_total)),
-            "{SUg}" => format!("{:.1}", swap_used.gib()),
-            "{SUm}" => format!("{}", swap_used.mib()),
-            "{SUp}" => format!("{:.2}", swap_used.percent(swap_total)),
-            "{SUpi}" => format!("{:02}", swap_used.percent(swap_total) as i32),
+            "{SUg}" => rounding.format(swap_used.giga(base), 1),
+            "{SUm}" => format!("{}", swap_used.mega(base)),
+            "{SUp}" => rounding.format(f64::from(swap_used.percent(swap_total)), 2),
+            "{SUpi}" => format!("{:0>2}", rounding.format(f64::from(swap_used.percent(swap_total)), 0)),
             "{SUpb}" => format_percent_bar(swap_used.percent(swap_total)),
-            "{Bg}" => format!("{:.1}", buffers.gib()),
-            "{Bm}" => format!("{}", buffers.mib()),
-            "{Bp}" => format!("{:.2}", buffers.percent(mem_total)),
-            "{Bpi}" => format!("{:02}", buffers.percent(mem_total) as i32),
+            "{Bg}" => rounding.format(buffers.giga(base), 1),
+            "{Bm}" => format!("{}", buffers.mega(base)),
+            "{Bp}" => rounding.format(f64::from(buffers.percent(mem_total)), 2),
+            "{Bpi}" => format!("{:0>2}", rounding.format(f64::from(buffers.percent(mem_total)), 0)),
             "{Bpb}" => format_percent_bar(buffers.percent(mem_total)),
-            "{Cg}" => format!("{:.1}", cached.gib()),
-            "{Cm}" => format!("{}", cached.mib()),
-            "{Cp}" => format!("{:.2}", cached.percent(mem_total)),
-            "{Cpi}" => format!("{:02}", cached.percent(mem_total) as i32),
+            "{Cg}" => rounding.format(cached.giga(base), 1),
+            "{Cm}" => format!("{}", cached.mega(base)),
+            "{Cp}" => rounding.format(f64::from(cached.percent(mem_total)), 2),
+            "{Cpi}" => format!("{:0>2}", rounding.format(f64::from(cached.percent(mem_total)), 0)),
             "{Cpb}" => format_percent_bar(cached.percent(mem_total)));
 
         match self.memtype {
@@ -381,6 +367,8 @@ impl ConfigBlock for Memory {
             tx_update_request: tx,
             warning: (block_config.warning_mem, block_config.warning_swap),
             critical: (block_config.critical_mem, block_config.critical_swap),
+            rounding: block_config.rounding,
+            unit_base: block_config.unit_base,
         })
     }
 }
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use uuid::Uuid;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::Config;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widget::I3BarWidget;
+use crate::widgets::text::TextWidget;
+
+/// The values published by blocks with an `id`, for mirror blocks to show. Every block
+/// publishes its text as `{text}` after each update, next to its own `mirror_values`. A block
+/// showing an error publishes just the message, as both `{text}` and `{error}`.
+#[derive(Debug, Clone, Default)]
+pub struct MirrorRegistry {
+    inner: Arc<Mutex<Registry>>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    /// The last values of every block with an id, empty until its first update
+    values: HashMap<String, HashMap<String, String>>,
+    /// The mirror blocks of every id, which are updated when its values change
+    mirrors: HashMap<String, Vec<(String, Sender<Task>)>>,
+}
+
+impl MirrorRegistry {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Registry> {
+        // Nothing is done while holding the lock which could panic
+        self.inner.lock().expect("mirror registry mutex poisoned")
+    }
+
+    /// Makes `id` known before it publishes anything, so mirrors can tell it exists.
+    pub fn register(&self, id: &str) {
+        self.lock().values.entry(id.to_owned()).or_default();
+    }
+
+    /// Stores the values of the block `id`, updating its mirrors if they changed.
+    pub fn publish(&self, id: &str, values: HashMap<String, String>) {
+        let mut registry = self.lock();
+        if registry.values.get(id) == Some(&values) {
+            return;
+        }
+        registry.values.insert(id.to_owned(), values);
+        for (mirror, update_request) in registry.mirrors.get(id).into_iter().flatten() {
+            update_request
+                .send(Task {
+                    id: mirror.clone(),
+                    update_time: Instant::now(),
+                })
+                .ok();
+        }
+    }
+
+    /// Updates the block `mirror` whenever the values of `source` change.
+    pub fn subscribe(&self, source: &str, mirror: &str, update_request: Sender<Task>) {
+        self.lock()
+            .mirrors
+            .entry(source.to_owned())
+            .or_default()
+            .push((mirror.to_owned(), update_request));
+    }
+
+    /// The last values of `source`, empty before its first update, or `None` if no block
+    /// has that id.
+    pub fn values(&self, source: &str) -> Option<HashMap<String, String>> {
+        self.lock().values.get(source).cloned()
+    }
+}
+
+pub struct Mirror {
+    id: String,
+    text: TextWidget,
+    source: String,
+    format: FormatTemplate,
+    mirrors: MirrorRegistry,
+    scroll: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MirrorConfig {
+    /// The `id` of the block to mirror
+    pub source: String,
+
+    /// Format override, taking the placeholders published by the source block
+    #[serde(default = "MirrorConfig::default_format")]
+    pub format: String,
+
+    /// Longer text is cut to this many columns
+    #[serde(default)]
+    pub max_width: Option<usize>,
+
+    /// Scroll longer text instead of cutting it
+    #[serde(default)]
+    pub scroll: bool,
+}
+
+impl MirrorConfig {
+    fn default_format() -> String {
+        "{text}".to_owned()
+    }
+}
+
+impl ConfigBlock for Mirror {
+    type Config = MirrorConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let mirrors = config.mirrors.clone();
+        mirrors.subscribe(&block_config.source, &id, tx);
+
+        Ok(Mirror {
+            id,
+            text: TextWidget::new(config)
+                .with_max_width(block_config.max_width)
+                .with_scroll(block_config.scroll),
+            source: block_config.source,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("mirror", "Invalid format specified for mirror")?,
+            mirrors,
+            scroll: block_config.scroll && block_config.max_width.is_some(),
+        })
+    }
+}
+
+impl Block for Mirror {
+    fn update(&mut self) -> Result<Option<Update>> {
+        // Updates are requested by the source block when its values change, what is returned
+        // only matters for scheduled updates, which move scrolled text along
+        let next = if self.scroll {
+            Some(Update::Every(Duration::from_secs(1)))
+        } else {
+            None
+        };
+        let values = self
+            .mirrors
+            .values(&self.source)
+            .block_error("mirror", &format!("no block has id {}", self.source))?;
+        if values.is_empty() {
+            // The source has not been updated yet
+            return Ok(next);
+        }
+        let values: HashMap<&str, &String> = values.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        self.text.set_text(self.format.render_static_str(&values)?);
+        Ok(next)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -11,6 +11,7 @@ use dbus::{
     Message,
 };
 use serde_derive::Deserialize;
+use unicode_width::UnicodeWidthStr;
 use uuid::Uuid;
 
 use crate::blocks::Update;
@@ -21,6 +22,7 @@ use crate::errors::*;
 use crate::input::I3BarEvent;
 use crate::scheduler::Task;
 use crate::subprocess::spawn_child_async;
+use crate::util::truncate_width;
 use crate::widget::{I3BarWidget, State};
 use crate::widgets::button::ButtonWidget;
 use crate::widgets::rotatingtext::RotatingTextWidget;
@@ -40,6 +42,7 @@ pub struct Music {
     auto_discover: bool,
     smart_trim: bool,
     max_width: usize,
+    ellipsis: String,
     separator: String,
 }
 
@@ -181,6 +184,7 @@ impl ConfigBlock for Music {
             };
         }
 
+        let ellipsis = config.ellipsis.clone();
         Ok(Music {
             id: id_copy,
             current_song: RotatingTextWidget::new(
@@ -214,6 +218,7 @@ impl ConfigBlock for Music {
             smart_trim: block_config.smart_trim,
             max_width: block_config.max_width,
             separator: block_config.separator,
+            ellipsis,
         })
     }
 }
@@ -255,43 +260,36 @@ impl Block for Music {
                             .set_text(format!("{}{}{}", title, self.separator, artist));
                     } else if title.is_empty() {
                         // Only display artist, truncated appropriately
-                        self.current_song.set_text({
-                            match artist.char_indices().nth(self.max_width) {
-                                None => artist.to_string(),
-                                Some((i, _)) => {
-                                    artist.truncate(i);
-                                    artist.to_string()
-                                }
-                            }
-                        });
+                        self.current_song.set_text(truncate_width(
+                            &artist,
+                            self.max_width,
+                            &self.ellipsis,
+                        ));
                     } else if artist.is_empty() {
                         // Only display title, truncated appropriately
-                        self.current_song.set_text({
-                            match title.char_indices().nth(self.max_width) {
-                                None => title.to_string(),
-                                Some((i, _)) => {
-                                    title.truncate(i);
-                                    title.to_string()
-                                }
-                            }
-                        });
+                        self.current_song.set_text(truncate_width(
+                            &title,
+                            self.max_width,
+                            &self.ellipsis,
+                        ));
                     } else {
                         // Below code is by https://github.com/jgbyrne
                         let text = format!("{}{}{}", title, self.separator, artist);
-                        let textlen = title.chars().count() + artist.chars().count() + 3;
+                        let separator_len = self.separator.width();
+                        let textlen = title.width() + artist.width() + separator_len;
                         if textlen > self.max_width {
-                            // overshoot: # of chars we need to trim
-                            // substance: # of chars available for trimming
+                            // overshoot: # of columns we need to trim
+                            // substance: # of columns available for trimming
                             let overshoot = (textlen - self.max_width) as f32;
-                            let substance = (textlen - 3) as f32;
+                            let substance = (textlen - separator_len) as f32;
 
-                            // Calculate number of chars to trim from title
-                            let tlen = title.chars().count();
+                            // Calculate number of columns to trim from title
+                            let tlen = title.width();
                             let tblm = tlen as f32 / substance;
                             let mut tnum = (overshoot * tblm).ceil() as usize;
 
-                            // Calculate number of chars to trim from artist
-                            let alen = artist.chars().count();
+                            // Calculate number of columns to trim from artist
+                            let alen = artist.width();
                             let ablm = alen as f32 / substance;
                             let mut anum = (overshoot * ablm).ceil() as usize;
 
@@ -306,31 +304,20 @@ impl Block for Music {
                                 anum += tnum;
                             }
 
-                            // Calculate how many chars to keep from title and artist
-                            let mut ttrc = tlen - tnum;
+                            // Calculate how many columns to keep from title and artist
+                            let mut ttrc = tlen.saturating_sub(tnum);
                             if ttrc < 1 || ttrc > 5000 {
                                 ttrc = 1
                             }
 
-                            let mut atrc = alen - anum;
+                            let mut atrc = alen.saturating_sub(anum);
                             if atrc < 1 || atrc > 5000 {
                                 atrc = 1
                             }
 
                             // Truncate artist and title to appropriate lengths
-                            let tidx = title
-                                .char_indices()
-                                .nth(ttrc)
-                                .unwrap_or((title.len(), 'a'))
-                                .0;
-                            title.truncate(tidx);
-
-                            let aidx = artist
-                                .char_indices()
-                                .nth(atrc)
-                                .unwrap_or((artist.len(), 'a'))
-                                .0;
-                            artist.truncate(aidx);
+                            title = truncate_width(&title, ttrc, &self.ellipsis);
+                            artist = truncate_width(&artist, atrc, &self.ellipsis);
 
                             // Produce final formatted string
                             self.current_song
@@ -2,11 +2,14 @@ use std::fmt;
 use std::fs::read_to_string;
 use std::fs::OpenOptions;
 use std::io::prelude::*;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
+use nix::ifaddrs::getifaddrs;
+use nix::sys::socket::SockAddr;
 use serde_derive::Deserialize;
 use uuid::Uuid;
 
@@ -18,11 +21,22 @@ use crate::errors::*;
 use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::subprocess::spawn_child_async;
-use crate::util::{escape_pango_text, format_percent_bar, format_speed};
-use crate::widget::I3BarWidget;
+use crate::util::{escape_markup, format_percent_bar, format_speed, truncate_width, CounterWindow};
+use crate::widget::{I3BarWidget, State};
 use crate::widgets::button::ButtonWidget;
 use crate::widgets::graph::GraphWidget;
 
+/// Whether an address is usable beyond the link, like the scope "global" of `ip address`.
+fn is_global(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => !(ip.is_loopback() || ip.is_link_local() || ip.is_unspecified()),
+        IpAddr::V6(ip) => {
+            // fe80::/10 is link-local
+            !(ip.is_loopback() || ip.is_unspecified() || ip.segments()[0] & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
 pub struct NetworkDevice {
     device: String,
     device_path: PathBuf,
@@ -259,63 +273,30 @@ impl NetworkDevice {
         Ok(Some(result))
     }
 
-    /// Queries the inet IP of this device (using `ip`).
-    pub fn ip_addr(&self) -> Result<Option<String>> {
+    /// The first global address of the given family assigned to this device.
+    fn global_addr(&self, ipv6: bool) -> Result<Option<String>> {
         if !self.is_up()? {
             return Ok(None);
         }
-        let mut ip_output = Command::new("sh")
-            .args(&[
-                "-c",
-                &format!(
-                    "ip -oneline -family inet address show {} | sed -rn -e \"s/.*inet ([\\.0-9/]+).*/\\1/; G; s/\\n/ /;h\" -e \"$ P;\"",
-                    self.device
-                ),
-            ])
-            .output()
-            .block_error("net", "Failed to execute IP address query.")?
-            .stdout;
-
-        if ip_output.is_empty() {
-            Ok(None)
-        } else {
-            ip_output.pop(); // Remove trailing newline.
-            let ip = String::from_utf8(ip_output)
-                .block_error("net", "Non-UTF8 IP address.")?
-                .trim()
-                .to_string();
-            Ok(Some(ip))
-        }
+        let addrs = getifaddrs().block_error("net", "Failed to read the interface addresses.")?;
+        Ok(addrs
+            .filter(|ifaddr| ifaddr.interface_name == self.device)
+            .filter_map(|ifaddr| match ifaddr.address {
+                Some(SockAddr::Inet(addr)) => Some(addr.ip().to_std()),
+                _ => None,
+            })
+            .find(|ip| ip.is_ipv6() == ipv6 && is_global(ip))
+            .map(|ip| ip.to_string()))
+    }
+
+    /// Queries the first global IPv4 address of this device.
+    pub fn ip_addr(&self) -> Result<Option<String>> {
+        self.global_addr(false)
     }
 
-    /// Queries the inet IPv6 of this device (using `ip`).
+    /// Queries the first global IPv6 address of this device.
     pub fn ipv6_addr(&self) -> Result<Option<String>> {
-        if !self.is_up()? {
-            return Ok(None);
-        }
-
-        let mut ip_output = Command::new("sh")
-            .args(&[
-                "-c",
-                &format!(
-                    "ip -oneline -family inet6 address show {} | sed -e 's/^.*inet6 \\([^ ]\\+\\).*/\\1/'",
-                    self.device
-                ),
-            ])
-            .output()
-            .block_error("net", "Failed to execute IPv6 address query.")?
-            .stdout;
-
-        if ip_output.is_empty() {
-            Ok(None)
-        } else {
-            ip_output.pop(); // Remove trailing newline.
-            let ip = String::from_utf8(ip_output)
-                .block_error("net", "Non-UTF8 IP address.")?
-                .trim()
-                .to_string();
-            Ok(Some(ip))
-        }
+        self.global_addr(true)
     }
 
     /// Queries the bitrate of this device (using `iwlist`)
@@ -359,6 +340,9 @@ pub struct Net {
     network: ButtonWidget,
     ssid: Option<ButtonWidget>,
     max_ssid_width: usize,
+    ellipsis: String,
+    last_ssid: Option<String>,
+    show_disconnected: bool,
     signal_strength: Option<ButtonWidget>,
     signal_strength_bar: bool,
     ip_addr: Option<ButtonWidget>,
@@ -374,8 +358,8 @@ pub struct Net {
     auto_device: bool,
     tx_buff: Vec<u64>,
     rx_buff: Vec<u64>,
-    tx_bytes: u64,
-    rx_bytes: u64,
+    tx_bytes: CounterWindow,
+    rx_bytes: CounterWindow,
     use_bits: bool,
     speed_min_unit: Unit,
     speed_digits: usize,
@@ -460,6 +444,10 @@ pub struct NetConfig {
     #[serde(default = "NetConfig::default_hide_missing")]
     pub hide_missing: bool,
 
+    /// Whether to keep showing the last known SSID (dimmed) while disconnected.
+    #[serde(default = "NetConfig::default_show_disconnected")]
+    pub show_disconnected: bool,
+
     /// Whether to show the upload throughput indicator of active networks.
     #[serde(default = "NetConfig::default_speed_up")]
     pub speed_up: bool,
@@ -488,6 +476,13 @@ pub struct NetConfig {
     #[serde(default = "NetConfig::default_graph_down")]
     pub graph_down: bool,
 
+    /// Time over which throughputs are averaged, at least one update interval.
+    #[serde(
+        default = "NetConfig::default_average_over",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub average_over: Duration,
+
     #[serde(default = "NetConfig::default_on_click")]
     pub on_click: Option<String>,
 }
@@ -516,6 +511,10 @@ impl NetConfig {
         false
     }
 
+    fn default_show_disconnected() -> bool {
+        false
+    }
+
     fn default_max_ssid_width() -> usize {
         21
     }
@@ -572,6 +571,10 @@ impl NetConfig {
         3
     }
 
+    fn default_average_over() -> Duration {
+        Duration::from_secs(0)
+    }
+
     fn default_on_click() -> Option<String> {
         None
     }
@@ -586,8 +589,11 @@ impl ConfigBlock for Net {
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
         let device = NetworkDevice::from_device(block_config.device);
-        let init_rx_bytes = device.rx_bytes().unwrap_or(0);
-        let init_tx_bytes = device.tx_bytes().unwrap_or(0);
+        let now = Instant::now();
+        let mut rx_bytes = CounterWindow::new(block_config.average_over);
+        rx_bytes.push(now, device.rx_bytes().unwrap_or(0));
+        let mut tx_bytes = CounterWindow::new(block_config.average_over);
+        tx_bytes.push(now, device.tx_bytes().unwrap_or(0));
         let wireless = device.is_wireless();
         let vpn = device.is_vpn();
         let id = Uuid::new_v4().to_simple().to_string();
@@ -612,6 +618,9 @@ impl ConfigBlock for Net {
                 None
             },
             max_ssid_width: block_config.max_ssid_width,
+            ellipsis: config.ellipsis.clone(),
+            last_ssid: None,
+            show_disconnected: block_config.show_disconnected,
             signal_strength: if block_config.signal_strength && wireless {
                 Some(ButtonWidget::new(config.clone(), &id))
             } else {
@@ -657,8 +666,8 @@ impl ConfigBlock for Net {
             auto_device: block_config.auto_device,
             rx_buff: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
             tx_buff: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-            rx_bytes: init_rx_bytes,
-            tx_bytes: init_tx_bytes,
+            rx_bytes,
+            tx_bytes,
             active: true,
             hide_inactive: block_config.hide_inactive,
             hide_missing: block_config.hide_missing,
@@ -687,6 +696,8 @@ impl Net {
             let dev = NetConfig::default_device();
             if self.device.device() != dev {
                 self.device = NetworkDevice::from_device(dev);
+                self.tx_bytes.clear();
+                self.rx_bytes.clear();
                 self.network.set_icon(if self.device.is_wireless() {
                     "net_wireless"
                 } else if self.device.is_vpn() {
@@ -712,10 +723,9 @@ impl Net {
         if let Some(ref mut ssid_widget) = self.ssid {
             let ssid = self.device.ssid()?;
             if let Some(s) = ssid {
-                let mut truncated = s;
-                truncated.truncate(self.max_ssid_width);
-                // SSID names can contain chars that need escaping
-                ssid_widget.set_text(escape_pango_text(truncated));
+                let truncated = truncate_width(&s, self.max_ssid_width, &self.ellipsis);
+                ssid_widget.set_text(truncated.clone());
+                self.last_ssid = Some(truncated);
             }
         }
         Ok(())
@@ -736,30 +746,24 @@ impl Net {
     }
 
     fn update_ip_addr(&mut self) -> Result<()> {
+        // Addresses which went away aren't kept
         if let Some(ref mut ip_addr_widget) = self.ip_addr {
             let ip_addr = self.device.ip_addr()?;
-            if let Some(ip) = ip_addr {
-                ip_addr_widget.set_text(ip);
-            }
+            ip_addr_widget.set_text(ip_addr.unwrap_or_default());
         }
         if let Some(ref mut ipv6_addr_widget) = self.ipv6_addr {
             let ipv6_addr = self.device.ipv6_addr()?;
-            if let Some(ip) = ipv6_addr {
-                ipv6_addr_widget.set_text(ip);
-            }
+            ipv6_addr_widget.set_text(ipv6_addr.unwrap_or_default());
         }
         Ok(())
     }
 
     fn update_tx_rx(&mut self) -> Result<()> {
-        // TODO: consider using `as_nanos`
-        let update_interval = (self.update_interval.as_secs() as f64)
         // Update the throughput/graph widgets if they are enabled
-            + (self.update_interval.subsec_nanos() as f64 / 1_000_000_000.0);
+        let now = Instant::now();
         if self.output_tx.is_some() || self.graph_tx.is_some() {
-            let current_tx = self.device.tx_bytes()?;
-            let tx_bytes = ((current_tx - self.tx_bytes) as f64 / update_interval) as u64;
-            self.tx_bytes = current_tx;
+            self.tx_bytes.push(now, self.device.tx_bytes()?);
+            let tx_bytes = self.tx_bytes.rate() as u64;
 
             if let Some(ref mut tx_widget) = self.output_tx {
                 tx_widget.set_text(format_speed(
@@ -777,9 +781,8 @@ impl Net {
             }
         }
         if self.output_rx.is_some() || self.graph_rx.is_some() {
-            let current_rx = self.device.rx_bytes()?;
-            let rx_bytes = ((current_rx - self.rx_bytes) as f64 / update_interval) as u64;
-            self.rx_bytes = current_rx;
+            self.rx_bytes.push(now, self.device.rx_bytes()?);
+            let rx_bytes = self.rx_bytes.rate() as u64;
 
             if let Some(ref mut rx_widget) = self.output_rx {
                 rx_widget.set_text(format_speed(
@@ -810,6 +813,12 @@ impl Block for Net {
         if !exists || !is_up {
             self.active = false;
             self.network.set_text(" ×".to_string());
+            self.network.set_state(State::Warning);
+            if let (Some(ref mut ssid_widget), Some(ref ssid)) = (&mut self.ssid, &self.last_ssid) {
+                // SSID names can contain chars that need escaping
+                ssid_widget
+                    .set_text_raw(format!("<span alpha='50%'>{}</span>", escape_markup(ssid)));
+            }
             if let Some(ref mut tx_widget) = self.output_tx {
                 tx_widget.set_text("×".to_string());
             };
@@ -820,8 +829,16 @@ impl Block for Net {
             return Ok(Some(self.update_interval.into()));
         }
 
+        if !self.active {
+            // Reconnected: start the throughput from a fresh baseline rather than
+            // averaging over the downtime, and refresh SSID/IP right away.
+            self.tx_bytes.clear();
+            self.rx_bytes.clear();
+            self.last_update = Instant::now() - Duration::from_secs(31);
+        }
         self.active = true;
         self.network.set_text("".to_string());
+        self.network.set_state(State::Idle);
 
         // Update SSID and IP address every 30s and the bitrate every 10s
         let now = Instant::now();
@@ -831,9 +848,10 @@ impl Block for Net {
         if now.duration_since(self.last_update).as_secs() > 30 {
             self.update_ssid()?;
             self.update_signal_strength()?;
-            self.update_ip_addr()?;
             self.last_update = now;
         }
+        // Reading the addresses is cheap, so changes are shown right away
+        self.update_ip_addr()?;
 
         self.update_tx_rx()?;
 
@@ -873,7 +891,12 @@ impl Block for Net {
             }
             widgets
         } else if !self.hide_inactive || !self.hide_missing {
-            vec![&self.network]
+            match (&self.ssid, &self.last_ssid) {
+                (Some(ref ssid_widget), Some(_)) if self.show_disconnected => {
+                    vec![&self.network, ssid_widget]
+                }
+                _ => vec![&self.network],
+            }
         } else {
             vec![]
         }
@@ -21,7 +21,7 @@ use crate::config::Config;
 use crate::errors::*;
 use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
-use crate::util::FormatTemplate;
+use crate::util::{truncate_width, FormatTemplate};
 use crate::widget::{I3BarWidget, State};
 use crate::widgets::button::ButtonWidget;
 
@@ -653,11 +653,11 @@ impl Block for NetworkManager {
                                 let ap = if let Ok(ap) = device.active_access_point(&self.dbus_conn)
                                 {
                                     let ssid = match ap.ssid(&self.dbus_conn) {
-                                        Ok(ssid) => {
-                                            let mut truncated = ssid.to_string();
-                                            truncated.truncate(self.max_ssid_width);
-                                            truncated
-                                        }
+                                        Ok(ssid) => truncate_width(
+                                            &ssid,
+                                            self.max_ssid_width,
+                                            &self.config.ellipsis,
+                                        ),
                                         Err(_) => "".to_string(),
                                     };
                                     let strength = match ap.strength(&self.dbus_conn) {
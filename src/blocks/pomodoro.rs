@@ -10,6 +10,7 @@ use crate::blocks::{Block, ConfigBlock};
 use crate::config::Config;
 use crate::errors::*;
 use crate::input::{I3BarEvent, MouseButton};
+use crate::notify::{Notification, Urgency};
 use crate::scheduler::Task;
 use crate::subprocess::spawn_child_async;
 use crate::widget::I3BarWidget;
@@ -71,6 +72,8 @@ pub struct Pomodoro {
     count: usize,
     use_nag: bool,
     nag_path: std::path::PathBuf,
+    /// Replaced at every phase change, so only the latest one is shown
+    notification: Option<Notification>,
 }
 
 impl Pomodoro {
@@ -86,6 +89,23 @@ impl Pomodoro {
         )
         .expect("Failed to start i3-nagbar");
     }
+
+    /// Tells the user a phase is over, in whichever ways are enabled.
+    fn alert(&mut self, message: &str, urgency: Urgency) {
+        if self.use_nag {
+            self.nag(
+                message,
+                if urgency == Urgency::Critical {
+                    "error"
+                } else {
+                    "warning"
+                },
+            );
+        }
+        if let Some(ref mut notification) = self.notification {
+            notification.notify("Pomodoro", message, urgency, "");
+        }
+    }
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -103,6 +123,8 @@ pub struct PomodoroConfig {
     pub use_nag: bool,
     #[serde(default = "PomodoroConfig::default_nag_path")]
     pub nag_path: std::path::PathBuf,
+    #[serde(default = "PomodoroConfig::default_notify")]
+    pub notify: bool,
 }
 
 impl PomodoroConfig {
@@ -129,6 +151,10 @@ impl PomodoroConfig {
     fn default_nag_path() -> std::path::PathBuf {
         std::path::PathBuf::from("i3-nagbar")
     }
+
+    fn default_notify() -> bool {
+        false
+    }
 }
 
 impl ConfigBlock for Pomodoro {
@@ -139,6 +165,11 @@ impl ConfigBlock for Pomodoro {
 
         Ok(Pomodoro {
             id: id.clone(),
+            notification: if block_config.notify {
+                Some(Notification::new(&config))
+            } else {
+                None
+            },
             time: ButtonWidget::new(config, &id).with_icon("pomodoro"),
             state: State::Stopped,
             length: Duration::from_secs(block_config.length * 60), // convert to minutes
@@ -163,18 +194,16 @@ impl Block for Pomodoro {
         match &self.state {
             State::Started(_) => {
                 if self.state.elapsed() >= self.length {
-                    if self.use_nag {
-                        self.nag(&self.message, "error");
-                    }
+                    let message = self.message.clone();
+                    self.alert(&message, Urgency::Critical);
 
                     self.state = State::OnBreak(Instant::now());
                 }
             }
             State::OnBreak(_) => {
                 if self.state.elapsed() >= self.break_length {
-                    if self.use_nag {
-                        self.nag(&self.break_message, "warning");
-                    }
+                    let message = self.break_message.clone();
+                    self.alert(&message, Urgency::Normal);
                     self.state = State::Stopped;
                     self.count += 1;
                 }
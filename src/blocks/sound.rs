@@ -2,9 +2,9 @@
 use {
     crate::pulse::callbacks::ListResult,
     crate::pulse::context::{
-        flags, introspect::ServerInfo, introspect::SinkInfo, subscribe::subscription_masks,
-        subscribe::Facility, subscribe::Operation as SubscribeOperation, Context,
-        State as PulseState,
+        flags, introspect::ServerInfo, introspect::SinkInfo, introspect::SourceInfo,
+        subscribe::subscription_masks, subscribe::Facility,
+        subscribe::Operation as SubscribeOperation, Context, State as PulseState,
     },
     crate::pulse::mainloop::standard::IterateResult,
     crate::pulse::mainloop::standard::Mainloop,
@@ -12,14 +12,13 @@ use {
     crate::pulse::volume::{ChannelVolumes, VOLUME_MAX, VOLUME_NORM},
     crossbeam_channel::unbounded,
     std::cell::RefCell,
-    std::cmp::min,
     std::collections::HashMap,
     std::ops::Deref,
     std::rc::Rc,
     std::sync::Mutex,
 };
 
-use std::cmp::max;
+use std::cmp::{max, min};
 use std::collections::BTreeMap;
 use std::io::Read;
 use std::process::{Command, Stdio};
@@ -27,6 +26,7 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
+#[cfg(feature = "pulseaudio")]
 use lazy_static::lazy_static;
 use serde_derive::Deserialize;
 use uuid::Uuid;
@@ -38,7 +38,7 @@ use crate::errors::*;
 use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::subprocess::spawn_child_async;
-use crate::util::{format_percent_bar, FormatTemplate};
+use crate::util::{default_gauge, format_percent_bar, format_percent_gauge, FormatTemplate};
 use crate::widget::{I3BarWidget, State};
 use crate::widgets::button::ButtonWidget;
 
@@ -48,9 +48,17 @@ trait SoundDevice {
     fn output_name(&self) -> String;
 
     fn get_info(&mut self) -> Result<()>;
-    fn set_volume(&mut self, step: i32) -> Result<()>;
+    fn set_volume(&mut self, step: i32, max_volume: Option<u32>) -> Result<()>;
     fn toggle(&mut self) -> Result<()>;
     fn monitor(&mut self, id: String, tx_update_request: Sender<Task>) -> Result<()>;
+
+    /// Make the entry after the current default output in `sinks` the new default.
+    fn cycle_default(&mut self, _sinks: &[String]) -> Result<()> {
+        Err(BlockError(
+            "sound".into(),
+            "cycling sinks is only supported by the PulseAudio driver".into(),
+        ))
+    }
 }
 
 struct AlsaSoundDevice {
@@ -122,8 +130,11 @@ impl SoundDevice for AlsaSoundDevice {
         Ok(())
     }
 
-    fn set_volume(&mut self, step: i32) -> Result<()> {
-        let volume = max(0, self.volume as i32 + step) as u32;
+    fn set_volume(&mut self, step: i32, max_volume: Option<u32>) -> Result<()> {
+        let mut volume = max(0, self.volume as i32 + step) as u32;
+        if let Some(max_volume) = max_volume {
+            volume = min(volume, max_volume);
+        }
 
         let mut args = Vec::new();
         if self.natural_mapping {
@@ -208,8 +219,16 @@ struct PulseAudioClient {
     sender: Sender<PulseAudioClientRequest>,
 }
 
+#[cfg(feature = "pulseaudio")]
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum DeviceKind {
+    Sink,
+    Source,
+}
+
 #[cfg(feature = "pulseaudio")]
 struct PulseAudioSoundDevice {
+    kind: DeviceKind,
     name: Option<String>,
     volume: Option<ChannelVolumes>,
     volume_avg: u32,
@@ -218,10 +237,12 @@ struct PulseAudioSoundDevice {
 
 #[cfg(feature = "pulseaudio")]
 #[derive(Debug)]
-struct PulseAudioSinkInfo {
+struct PulseAudioDeviceInfo {
+    index: u32,
     volume: ChannelVolumes,
     mute: bool,
-    sink_name: String,
+    name: String,
+    description: Option<String>,
 }
 
 #[cfg(feature = "pulseaudio")]
@@ -230,8 +251,15 @@ enum PulseAudioClientRequest {
     GetDefaultDevice,
     GetSinkInfoByIndex(u32),
     GetSinkInfoByName(String),
+    GetSinkInfoList,
+    GetSourceInfoByIndex(u32),
+    GetSourceInfoByName(String),
+    GetSourceInfoList,
+    SetDefaultSink(String),
     SetSinkVolumeByName(String, ChannelVolumes),
     SetSinkMuteByName(String, bool),
+    SetSourceVolumeByName(String, ChannelVolumes),
+    SetSourceMuteByName(String, bool),
 }
 
 #[cfg(feature = "pulseaudio")]
@@ -240,10 +268,58 @@ lazy_static! {
     static ref PULSEAUDIO_EVENT_LISTENER: Mutex<HashMap<String, Sender<Task>>> =
         Mutex::new(HashMap::new());
     static ref PULSEAUDIO_DEFAULT_SINK: Mutex<String> = Mutex::new("@DEFAULT_SINK@".into());
-    static ref PULSEAUDIO_SINKS: Mutex<HashMap<String, PulseAudioSinkInfo>> =
+    static ref PULSEAUDIO_SINKS: Mutex<HashMap<String, PulseAudioDeviceInfo>> =
+        Mutex::new(HashMap::new());
+    static ref PULSEAUDIO_DEFAULT_SOURCE: Mutex<String> = Mutex::new("@DEFAULT_SOURCE@".into());
+    static ref PULSEAUDIO_SOURCES: Mutex<HashMap<String, PulseAudioDeviceInfo>> =
         Mutex::new(HashMap::new());
 }
 
+#[cfg(feature = "pulseaudio")]
+impl DeviceKind {
+    fn devices(self) -> &'static Mutex<HashMap<String, PulseAudioDeviceInfo>> {
+        match self {
+            DeviceKind::Sink => &*PULSEAUDIO_SINKS,
+            DeviceKind::Source => &*PULSEAUDIO_SOURCES,
+        }
+    }
+
+    fn default_name(self) -> &'static Mutex<String> {
+        match self {
+            DeviceKind::Sink => &*PULSEAUDIO_DEFAULT_SINK,
+            DeviceKind::Source => &*PULSEAUDIO_DEFAULT_SOURCE,
+        }
+    }
+
+    fn get_info_by_name(self, name: String) -> PulseAudioClientRequest {
+        match self {
+            DeviceKind::Sink => PulseAudioClientRequest::GetSinkInfoByName(name),
+            DeviceKind::Source => PulseAudioClientRequest::GetSourceInfoByName(name),
+        }
+    }
+
+    fn get_info_list(self) -> PulseAudioClientRequest {
+        match self {
+            DeviceKind::Sink => PulseAudioClientRequest::GetSinkInfoList,
+            DeviceKind::Source => PulseAudioClientRequest::GetSourceInfoList,
+        }
+    }
+
+    fn set_volume_by_name(self, name: String, volume: ChannelVolumes) -> PulseAudioClientRequest {
+        match self {
+            DeviceKind::Sink => PulseAudioClientRequest::SetSinkVolumeByName(name, volume),
+            DeviceKind::Source => PulseAudioClientRequest::SetSourceVolumeByName(name, volume),
+        }
+    }
+
+    fn set_mute_by_name(self, name: String, mute: bool) -> PulseAudioClientRequest {
+        match self {
+            DeviceKind::Sink => PulseAudioClientRequest::SetSinkMuteByName(name, mute),
+            DeviceKind::Source => PulseAudioClientRequest::SetSourceMuteByName(name, mute),
+        }
+    }
+}
+
 #[cfg(feature = "pulseaudio")]
 impl PulseAudioConnection {
     fn new() -> Result<Self> {
@@ -365,12 +441,45 @@ impl PulseAudioClient {
                                         PulseAudioClient::sink_info_callback,
                                     );
                                 }
+                                PulseAudioClientRequest::GetSinkInfoList => {
+                                    introspector
+                                        .get_sink_info_list(PulseAudioClient::sink_info_callback);
+                                }
+                                PulseAudioClientRequest::GetSourceInfoByIndex(index) => {
+                                    introspector.get_source_info_by_index(
+                                        index,
+                                        PulseAudioClient::source_info_callback,
+                                    );
+                                }
+                                PulseAudioClientRequest::GetSourceInfoByName(name) => {
+                                    introspector.get_source_info_by_name(
+                                        &name,
+                                        PulseAudioClient::source_info_callback,
+                                    );
+                                }
+                                PulseAudioClientRequest::GetSourceInfoList => {
+                                    introspector.get_source_info_list(
+                                        PulseAudioClient::source_info_callback,
+                                    );
+                                }
+                                PulseAudioClientRequest::SetDefaultSink(name) => {
+                                    connection
+                                        .context
+                                        .borrow_mut()
+                                        .set_default_sink(&name, |_| {});
+                                }
                                 PulseAudioClientRequest::SetSinkVolumeByName(name, volumes) => {
                                     introspector.set_sink_volume_by_name(&name, &volumes, None);
                                 }
                                 PulseAudioClientRequest::SetSinkMuteByName(name, mute) => {
                                     introspector.set_sink_mute_by_name(&name, mute, None);
                                 }
+                                PulseAudioClientRequest::SetSourceVolumeByName(name, volumes) => {
+                                    introspector.set_source_volume_by_name(&name, &volumes, None);
+                                }
+                                PulseAudioClientRequest::SetSourceMuteByName(name, mute) => {
+                                    introspector.set_source_mute_by_name(&name, mute, None);
+                                }
                             };
 
                             // send request and receive response
@@ -395,7 +504,9 @@ impl PulseAudioClient {
                     .borrow_mut()
                     .set_subscribe_callback(Some(Box::new(PulseAudioClient::subscribe_callback)));
                 connection.context.borrow_mut().subscribe(
-                    subscription_masks::SERVER | subscription_masks::SINK,
+                    subscription_masks::SERVER
+                        | subscription_masks::SINK
+                        | subscription_masks::SOURCE,
                     |_| {},
                 );
 
@@ -421,6 +532,9 @@ impl PulseAudioClient {
     }
 
     fn server_info_callback(server_info: &ServerInfo) {
+        if let Some(default_source) = server_info.default_source_name.clone() {
+            *PULSEAUDIO_DEFAULT_SOURCE.lock().unwrap() = default_source.into();
+        }
         match server_info.default_sink_name.clone() {
             None => {}
             Some(default_sink) => {
@@ -436,10 +550,12 @@ impl PulseAudioClient {
             ListResult::Item(sink_info) => match sink_info.name.clone() {
                 None => {}
                 Some(name) => {
-                    let info = PulseAudioSinkInfo {
+                    let info = PulseAudioDeviceInfo {
+                        index: sink_info.index,
                         volume: sink_info.volume,
                         mute: sink_info.mute,
-                        sink_name: name.to_string(),
+                        name: name.to_string(),
+                        description: sink_info.description.as_ref().map(|d| d.to_string()),
                     };
                     PULSEAUDIO_SINKS.lock().unwrap().insert(name.into(), info);
                     PulseAudioClient::send_update_event();
@@ -448,9 +564,29 @@ impl PulseAudioClient {
         }
     }
 
+    fn source_info_callback(result: ListResult<&SourceInfo>) {
+        match result {
+            ListResult::End | ListResult::Error => {}
+            ListResult::Item(source_info) => match source_info.name.clone() {
+                None => {}
+                Some(name) => {
+                    let info = PulseAudioDeviceInfo {
+                        index: source_info.index,
+                        volume: source_info.volume,
+                        mute: source_info.mute,
+                        name: name.to_string(),
+                        description: source_info.description.as_ref().map(|d| d.to_string()),
+                    };
+                    PULSEAUDIO_SOURCES.lock().unwrap().insert(name.into(), info);
+                    PulseAudioClient::send_update_event();
+                }
+            },
+        }
+    }
+
     fn subscribe_callback(
         facility: Option<Facility>,
-        _operation: Option<SubscribeOperation>,
+        operation: Option<SubscribeOperation>,
         index: u32,
     ) {
         match facility {
@@ -459,10 +595,30 @@ impl PulseAudioClient {
                 Facility::Server => {
                     let _ = PulseAudioClient::send(PulseAudioClientRequest::GetDefaultDevice);
                 }
+                Facility::Sink if operation == Some(SubscribeOperation::Removed) => {
+                    // e.g. an unplugged headset; forget it so blocks fall back to the default
+                    PULSEAUDIO_SINKS
+                        .lock()
+                        .unwrap()
+                        .retain(|_, info| info.index != index);
+                    PulseAudioClient::send_update_event();
+                }
                 Facility::Sink => {
                     let _ =
                         PulseAudioClient::send(PulseAudioClientRequest::GetSinkInfoByIndex(index));
                 }
+                Facility::Source if operation == Some(SubscribeOperation::Removed) => {
+                    PULSEAUDIO_SOURCES
+                        .lock()
+                        .unwrap()
+                        .retain(|_, info| info.index != index);
+                    PulseAudioClient::send_update_event();
+                }
+                Facility::Source => {
+                    let _ = PulseAudioClient::send(PulseAudioClientRequest::GetSourceInfoByIndex(
+                        index,
+                    ));
+                }
                 _ => {}
             },
         }
@@ -482,25 +638,30 @@ impl PulseAudioClient {
 
 #[cfg(feature = "pulseaudio")]
 impl PulseAudioSoundDevice {
-    fn new() -> Result<Self> {
+    fn new(kind: DeviceKind) -> Result<Self> {
         PulseAudioClient::send(PulseAudioClientRequest::GetDefaultDevice)?;
+        PulseAudioClient::send(kind.get_info_list())?;
 
         let device = PulseAudioSoundDevice {
+            kind,
             name: None,
             volume: None,
             volume_avg: 0,
             muted: false,
         };
 
-        PulseAudioClient::send(PulseAudioClientRequest::GetSinkInfoByName(device.name()))?;
+        PulseAudioClient::send(kind.get_info_by_name(device.name()))?;
 
         Ok(device)
     }
 
-    fn with_name(name: String) -> Result<Self> {
-        PulseAudioClient::send(PulseAudioClientRequest::GetSinkInfoByName(name.clone()))?;
+    fn with_name(kind: DeviceKind, name: String) -> Result<Self> {
+        PulseAudioClient::send(PulseAudioClientRequest::GetDefaultDevice)?;
+        // fetch all devices, `name` may be a description rather than a device name
+        PulseAudioClient::send(kind.get_info_list())?;
 
         Ok(PulseAudioSoundDevice {
+            kind,
             name: Some(name),
             volume: None,
             volume_avg: 0,
@@ -508,10 +669,24 @@ impl PulseAudioSoundDevice {
         })
     }
 
+    /// Resolves the configured device (by name or description) to a device name, falling
+    /// back to the default device if it isn't configured or currently doesn't exist.
     fn name(&self) -> String {
         self.name
-            .clone()
-            .unwrap_or_else(|| PULSEAUDIO_DEFAULT_SINK.lock().unwrap().clone())
+            .as_ref()
+            .and_then(|name| Self::find_device(self.kind, name))
+            .unwrap_or_else(|| self.kind.default_name().lock().unwrap().clone())
+    }
+
+    fn find_device(kind: DeviceKind, name: &str) -> Option<String> {
+        let devices = kind.devices().lock().unwrap();
+        if devices.contains_key(name) {
+            return Some(name.to_string());
+        }
+        devices
+            .values()
+            .find(|info| info.description.as_deref() == Some(name))
+            .map(|info| info.name.clone())
     }
 
     fn volume(&mut self, volume: ChannelVolumes) {
@@ -533,18 +708,18 @@ impl SoundDevice for PulseAudioSoundDevice {
     }
 
     fn get_info(&mut self) -> Result<()> {
-        match PULSEAUDIO_SINKS.lock().unwrap().get(&self.name()) {
+        match self.kind.devices().lock().unwrap().get(&self.name()) {
             None => {}
-            Some(sink_info) => {
-                self.volume(sink_info.volume);
-                self.muted = sink_info.mute;
+            Some(info) => {
+                self.volume(info.volume);
+                self.muted = info.mute;
             }
         }
 
         Ok(())
     }
 
-    fn set_volume(&mut self, step: i32) -> Result<()> {
+    fn set_volume(&mut self, step: i32, max_volume: Option<u32>) -> Result<()> {
         let mut volume = match self.volume {
             Some(volume) => volume,
             None => return Err(BlockError("sound".into(), "volume unknown".into())),
@@ -552,26 +727,34 @@ impl SoundDevice for PulseAudioSoundDevice {
 
         // apply step to volumes
         let step = (step as f32 * VOLUME_NORM.0 as f32 / 100.0).round() as i32;
+        let max_volume = match max_volume {
+            Some(max_volume) => min(
+                (max_volume as f32 * VOLUME_NORM.0 as f32 / 100.0).round() as u32,
+                VOLUME_MAX.0,
+            ),
+            None => VOLUME_MAX.0,
+        };
         for vol in volume.get_mut().iter_mut() {
-            vol.0 = min(max(0, vol.0 as i32 + step) as u32, VOLUME_MAX.0);
+            vol.0 = min(max(0, vol.0 as i32 + step) as u32, max_volume);
         }
 
         // update volumes
         self.volume(volume);
-        PulseAudioClient::send(PulseAudioClientRequest::SetSinkVolumeByName(
-            self.name(),
-            volume,
-        ))?;
+        // keep the cached device info in sync so the next redraw doesn't show the old volume
+        if let Some(info) = self.kind.devices().lock().unwrap().get_mut(&self.name()) {
+            info.volume = volume;
+        }
+        PulseAudioClient::send(self.kind.set_volume_by_name(self.name(), volume))?;
 
         Ok(())
     }
 
     fn toggle(&mut self) -> Result<()> {
         self.muted = !self.muted;
-        PulseAudioClient::send(PulseAudioClientRequest::SetSinkMuteByName(
-            self.name(),
-            self.muted,
-        ))?;
+        if let Some(info) = self.kind.devices().lock().unwrap().get_mut(&self.name()) {
+            info.mute = self.muted;
+        }
+        PulseAudioClient::send(self.kind.set_mute_by_name(self.name(), self.muted))?;
 
         Ok(())
     }
@@ -583,6 +766,42 @@ impl SoundDevice for PulseAudioSoundDevice {
             .insert(id, tx_update_request);
         Ok(())
     }
+
+    fn cycle_default(&mut self, sinks: &[String]) -> Result<()> {
+        if self.kind != DeviceKind::Sink {
+            return Err(BlockError(
+                "sound".into(),
+                "only sinks can be cycled".into(),
+            ));
+        }
+        // skip configured sinks which aren't currently available
+        let available: Vec<String> = sinks
+            .iter()
+            .filter_map(|s| Self::find_device(DeviceKind::Sink, s))
+            .collect();
+        if available.is_empty() {
+            return Ok(());
+        }
+        let default = PULSEAUDIO_DEFAULT_SINK.lock().unwrap().clone();
+        let next = match available.iter().position(|s| *s == default) {
+            Some(i) => &available[(i + 1) % available.len()],
+            None => &available[0],
+        };
+        PulseAudioClient::send(PulseAudioClientRequest::SetDefaultSink(next.clone()))
+    }
+}
+
+#[cfg(feature = "pulseaudio")]
+fn pulseaudio_source() -> Result<Box<dyn SoundDevice>> {
+    Ok(Box::new(PulseAudioSoundDevice::new(DeviceKind::Source)?))
+}
+
+#[cfg(not(feature = "pulseaudio"))]
+fn pulseaudio_source() -> Result<Box<dyn SoundDevice>> {
+    Err(BlockError(
+        "sound".into(),
+        "PulseAudio feature disabled".into(),
+    ))
 }
 
 // TODO: Use the alsa control bindings to implement push updates
@@ -590,13 +809,17 @@ pub struct Sound {
     text: ButtonWidget,
     id: String,
     device: Box<dyn SoundDevice>,
+    source: Option<(Box<dyn SoundDevice>, ButtonWidget)>,
     step_width: u32,
+    max_volume: Option<u32>,
+    cycle_sinks: Option<Vec<String>>,
     format: FormatTemplate,
     config: Config,
     on_click: Option<String>,
     show_volume_when_muted: bool,
     bar: bool,
     mappings: Option<BTreeMap<String, String>>,
+    gauge: Vec<String>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -606,9 +829,9 @@ pub struct SoundConfig {
     #[serde(default = "SoundDriver::default")]
     pub driver: SoundDriver,
 
-    /// PulseAudio device name, or
+    /// PulseAudio sink name or description, or
     /// ALSA control name as listed in the output of `amixer -D yourdevice scontrols` (default is "Master")
-    #[serde(default = "SoundConfig::default_name")]
+    #[serde(default = "SoundConfig::default_name", alias = "sink")]
     pub name: Option<String>,
 
     /// ALSA device name, usually in the form "hw:#" where # is the number of the card desired (default is "default")
@@ -623,17 +846,34 @@ pub struct SoundConfig {
     #[serde(default = "SoundConfig::default_step_width")]
     pub step_width: u32,
 
+    /// Upper limit for the volume when scrolling, in percent. Set above 100 to allow
+    /// overamplification with PulseAudio.
+    #[serde(default = "SoundConfig::default_max_volume")]
+    pub max_volume: Option<u32>,
+
+    /// PulseAudio sinks (names or descriptions) to cycle the default sink through on middle click
+    #[serde(default = "SoundConfig::default_cycle_sinks")]
+    pub cycle_sinks: Option<Vec<String>>,
+
     /// Format string for displaying sound information.
-    /// placeholders: {volume}
+    /// placeholders: {volume}, {output_name} and {gauge}
     #[serde(default = "SoundConfig::default_format")]
     pub format: String,
 
+    /// Glyphs for the {gauge} placeholder, from silent to full volume
+    #[serde(default = "default_gauge")]
+    pub gauge: Vec<String>,
+
     #[serde(default = "SoundConfig::default_on_click")]
     pub on_click: Option<String>,
 
     #[serde(default = "SoundConfig::default_show_volume_when_muted")]
     pub show_volume_when_muted: bool,
 
+    /// Also show whether the default source (microphone) is muted, right click toggles it
+    #[serde(default = "SoundConfig::default_show_source")]
+    pub show_source: bool,
+
     /// Show volume as bar instead of percent
     #[serde(default = "SoundConfig::default_bar")]
     pub bar: bool,
@@ -674,6 +914,14 @@ impl SoundConfig {
         5
     }
 
+    fn default_max_volume() -> Option<u32> {
+        None
+    }
+
+    fn default_cycle_sinks() -> Option<Vec<String>> {
+        None
+    }
+
     fn default_format() -> String {
         "{volume}%".into()
     }
@@ -686,6 +934,10 @@ impl SoundConfig {
         false
     }
 
+    fn default_show_source() -> bool {
+        false
+    }
+
     fn default_bar() -> bool {
         false
     }
@@ -710,7 +962,8 @@ impl Sound {
             output_name
         };
         let values = map!("{volume}" => format!("{:02}", volume),
-                          "{output_name}" => mapped_output_name
+                          "{output_name}" => mapped_output_name,
+                          "{gauge}" => format_percent_gauge(volume as f32, &self.gauge)
         );
         let text = self.format.render_static_str(&values)?;
 
@@ -740,6 +993,17 @@ impl Sound {
             self.text.set_state(State::Idle);
         }
 
+        if let Some((ref mut source, ref mut source_widget)) = self.source {
+            source.get_info()?;
+            if source.muted() {
+                source_widget.set_icon("microphone_muted");
+                source_widget.set_state(State::Warning);
+            } else {
+                source_widget.set_icon("microphone");
+                source_widget.set_state(State::Idle);
+            }
+        }
+
         Ok(())
     }
 }
@@ -757,6 +1021,7 @@ impl ConfigBlock for Sound {
         if step_width > 50 {
             step_width = 50;
         }
+        let name = block_config.name.filter(|name| name != "default");
 
         #[cfg(not(feature = "pulseaudio"))]
         type PulseAudioSoundDevice = AlsaSoundDevice;
@@ -764,9 +1029,9 @@ impl ConfigBlock for Sound {
         // try to create a pulseaudio device if feature is enabled and `driver != "alsa"`
         let pulseaudio_device: Result<PulseAudioSoundDevice> = match block_config.driver {
             #[cfg(feature = "pulseaudio")]
-            SoundDriver::Auto | SoundDriver::PulseAudio => match block_config.name.clone() {
-                None => PulseAudioSoundDevice::new(),
-                Some(name) => PulseAudioSoundDevice::with_name(name),
+            SoundDriver::Auto | SoundDriver::PulseAudio => match name.clone() {
+                None => PulseAudioSoundDevice::new(DeviceKind::Sink),
+                Some(name) => PulseAudioSoundDevice::with_name(DeviceKind::Sink, name),
             },
             _ => Err(BlockError(
                 "sound".into(),
@@ -775,26 +1040,50 @@ impl ConfigBlock for Sound {
         };
 
         // prefer PulseAudio if available and selected, fallback to ALSA
+        let alsa_device = block_config.device.unwrap_or_else(|| "default".into());
+        let pulseaudio = pulseaudio_device.is_ok();
         let device: Box<dyn SoundDevice> = match pulseaudio_device {
             Ok(dev) => Box::new(dev),
             Err(_) => Box::new(AlsaSoundDevice::new(
-                block_config.name.unwrap_or_else(|| "Master".into()),
-                block_config.device.unwrap_or_else(|| "default".into()),
+                name.unwrap_or_else(|| "Master".into()),
+                alsa_device.clone(),
                 block_config.natural_mapping,
             )?),
         };
 
+        // The source is only monitored through the sink: PulseAudio notifies all blocks of
+        // any change and `alsactl monitor` covers all controls of the card.
+        let source = if !block_config.show_source {
+            None
+        } else {
+            let source = if pulseaudio {
+                pulseaudio_source()?
+            } else {
+                Box::new(AlsaSoundDevice::new(
+                    "Capture".into(),
+                    alsa_device,
+                    block_config.natural_mapping,
+                )?)
+            };
+            let widget = ButtonWidget::new(config.clone(), &id).with_icon("microphone");
+            Some((source, widget))
+        };
+
         let mut sound = Self {
             text: ButtonWidget::new(config.clone(), &id).with_icon("volume_empty"),
             id: id.clone(),
             device,
+            source,
             format: FormatTemplate::from_string(&block_config.format)?,
             step_width,
+            max_volume: block_config.max_volume,
+            cycle_sinks: block_config.cycle_sinks,
             config,
             on_click: block_config.on_click,
             show_volume_when_muted: block_config.show_volume_when_muted,
             bar: block_config.bar,
             mappings: block_config.mappings,
+            gauge: block_config.gauge,
         };
 
         sound.device.monitor(id, tx_update_request)?;
@@ -813,25 +1102,42 @@ impl Block for Sound {
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
-        vec![&self.text]
+        match self.source {
+            Some((_, ref source_widget)) => vec![&self.text, source_widget],
+            None => vec![&self.text],
+        }
     }
 
     fn click(&mut self, e: &I3BarEvent) -> Result<()> {
         if let Some(ref name) = e.name {
             if name.as_str() == self.id {
                 match e.button {
-                    MouseButton::Right => self.device.toggle()?,
+                    MouseButton::Right => match self.source {
+                        Some((ref mut source, _)) => source.toggle()?,
+                        None => self.device.toggle()?,
+                    },
+                    MouseButton::Middle => {
+                        if let Some(ref sinks) = self.cycle_sinks {
+                            self.device.cycle_default(sinks)?;
+                        }
+                    }
                     MouseButton::Left => {
                         if let Some(ref cmd) = self.on_click {
                             spawn_child_async("sh", &["-c", cmd])
                                 .block_error("sound", "could not spawn child")?;
+                        } else if self.source.is_some() {
+                            self.device.toggle()?;
                         }
                     }
                     _ => {
                         use LogicalDirection::*;
                         match self.config.scrolling.to_logical_direction(e.button) {
-                            Some(Up) => self.device.set_volume(self.step_width as i32)?,
-                            Some(Down) => self.device.set_volume(-(self.step_width as i32))?,
+                            Some(Up) => self
+                                .device
+                                .set_volume(self.step_width as i32, self.max_volume)?,
+                            Some(Down) => self
+                                .device
+                                .set_volume(-(self.step_width as i32), self.max_volume)?,
                             None => (),
                         }
                     }
@@ -0,0 +1,352 @@
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use serde_derive::Deserialize;
+use uuid::Uuid;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::Config;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::{has_command, FormatTemplate};
+use crate::widget::{I3BarWidget, State};
+use crate::widgets::button::ButtonWidget;
+
+#[derive(Deserialize, Debug, Copy, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum Driver {
+    /// Runs the query with the `sqlite3` command line tool
+    Sqlite,
+    /// Runs the query with the `psql` command line tool
+    Postgres,
+}
+
+impl Driver {
+    /// The command line client the query is run with, there is no database driver linked in.
+    fn command(self) -> &'static str {
+        match self {
+            Driver::Sqlite => "sqlite3",
+            Driver::Postgres => "psql",
+        }
+    }
+}
+
+/// The result of the last query, taken by the next update. Errors are kept as messages, as
+/// they are shown as the block's error.
+type QueryResult = Arc<Mutex<Option<std::result::Result<String, String>>>>;
+
+pub struct Sql {
+    id: String,
+    text: ButtonWidget,
+    format: FormatTemplate,
+    update_interval: Duration,
+    info: Option<f64>,
+    warning: Option<f64>,
+    critical: Option<f64>,
+    result: QueryResult,
+    send: Sender<()>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SqlConfig {
+    /// Which database to query
+    pub driver: Driver,
+
+    /// Path of the database file for SQLite, or a connection string or URI for Postgres
+    pub connection_string: String,
+
+    /// Shell command printing the Postgres password, so it doesn't have to be in the config
+    #[serde(default = "SqlConfig::default_password_command")]
+    pub password_command: Option<String>,
+
+    /// Query returning the value to show
+    pub query: String,
+
+    /// Update interval in seconds
+    #[serde(
+        default = "SqlConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Time after which a query is given up on, in seconds
+    #[serde(
+        default = "SqlConfig::default_timeout",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub timeout: Duration,
+
+    /// Format override
+    #[serde(default = "SqlConfig::default_format")]
+    pub format: String,
+
+    /// Minimum value, above which state is set to info
+    #[serde(default = "SqlConfig::default_threshold")]
+    pub info: Option<f64>,
+
+    /// Minimum value, above which state is set to warning
+    #[serde(default = "SqlConfig::default_threshold")]
+    pub warning: Option<f64>,
+
+    /// Minimum value, above which state is set to critical
+    #[serde(default = "SqlConfig::default_threshold")]
+    pub critical: Option<f64>,
+}
+
+impl SqlConfig {
+    fn default_password_command() -> Option<String> {
+        None
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_timeout() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_format() -> String {
+        "{result}".to_owned()
+    }
+
+    fn default_threshold() -> Option<f64> {
+        None
+    }
+}
+
+/// Reads all of `pipe` in a thread of its own.
+fn read_in_thread<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_string(&mut text).ok();
+        }
+        text
+    })
+}
+
+/// Runs `command`, killing it if it takes longer than `timeout`, and returns its output.
+fn output_with_timeout(command: &mut Command, timeout: Duration) -> Result<String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .block_error("sql", "failed to run the database client")?;
+
+    // Read while waiting, a client writing more than fits in the pipe would never exit
+    let stdout = read_in_thread(child.stdout.take());
+    let stderr = read_in_thread(child.stderr.take());
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .block_error("sql", "failed to wait for the database client")?
+        {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(BlockError("sql".to_owned(), "query timed out".to_owned()));
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if status.success() {
+        Ok(stdout)
+    } else {
+        Err(BlockError("sql".to_owned(), stderr.trim().to_owned()))
+    }
+}
+
+/// Runs the query read-only and returns the first row of the result.
+fn run_query(config: &SqlConfig) -> Result<String> {
+    let mut command = match config.driver {
+        Driver::Sqlite => {
+            let mut command = Command::new(Driver::Sqlite.command());
+            command
+                .args(&["-readonly", "-batch", "-noheader", "-bail"])
+                .arg(&config.connection_string)
+                .arg(&config.query);
+            command
+        }
+        Driver::Postgres => {
+            let mut command = Command::new(Driver::Postgres.command());
+            command
+                .args(&["--no-psqlrc", "--no-align", "--tuples-only", "--quiet"])
+                .args(&["--set", "ON_ERROR_STOP=1", "--command"])
+                .arg(&config.query)
+                .arg(&config.connection_string)
+                .env("PGOPTIONS", "-c default_transaction_read_only=on");
+            if let Some(ref password_command) = config.password_command {
+                let password = output_with_timeout(
+                    Command::new("sh").args(&["-c", password_command]),
+                    config.timeout,
+                )?;
+                command.env("PGPASSWORD", password.trim_end_matches('\n'));
+            }
+            command
+        }
+    };
+
+    let output = output_with_timeout(&mut command, config.timeout)?;
+    Ok(output.lines().next().unwrap_or("").trim().to_owned())
+}
+
+fn make_thread(
+    recv: Receiver<()>,
+    done: Sender<Task>,
+    result: QueryResult,
+    config: SqlConfig,
+    id: String,
+) {
+    thread::Builder::new()
+        .name("sql".into())
+        .spawn(move || {
+            for () in recv {
+                let outcome = run_query(&config).map_err(|e| match e {
+                    BlockError(_, message) => message,
+                    e => e.to_string(),
+                });
+                *result
+                    .lock()
+                    .expect("main thread paniced while holding sql result mutex") = Some(outcome);
+                done.send(Task {
+                    id: id.clone(),
+                    update_time: Instant::now(),
+                })
+                .unwrap();
+            }
+        })
+        .unwrap();
+}
+
+impl ConfigBlock for Sql {
+    type Config = SqlConfig;
+
+    fn new(block_config: Self::Config, config: Config, done: Sender<Task>) -> Result<Self> {
+        let client = block_config.driver.command();
+        if !has_command("sql", client)? {
+            return Err(ConfigurationError(
+                "sql".to_owned(),
+                (
+                    format!("{} not found, it is needed to run the query", client),
+                    format!("{} not found", client),
+                ),
+            ));
+        }
+
+        let id = Uuid::new_v4().to_simple().to_string();
+        let (send, recv): (Sender<()>, Receiver<()>) = unbounded();
+        let result = Arc::new(Mutex::new(None));
+
+        make_thread(recv, done, result.clone(), block_config.clone(), id.clone());
+
+        Ok(Sql {
+            text: ButtonWidget::new(config, &id),
+            id,
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("sql", "Invalid format specified for sql")?,
+            update_interval: block_config.interval,
+            info: block_config.info,
+            warning: block_config.warning,
+            critical: block_config.critical,
+            result,
+            send,
+        })
+    }
+}
+
+impl Sql {
+    fn state(&self, value: f64) -> State {
+        let above = |threshold: Option<f64>| threshold.map_or(false, |t| value > t);
+        if above(self.critical) {
+            State::Critical
+        } else if above(self.warning) {
+            State::Warning
+        } else if above(self.info) {
+            State::Info
+        } else {
+            State::Idle
+        }
+    }
+}
+
+impl Block for Sql {
+    fn update(&mut self) -> Result<Option<Update>> {
+        // Updates either show the result of the last query, or start the next one. The
+        // query thread only asks for a redraw, so every path keeps the block scheduled.
+        let result = self
+            .result
+            .lock()
+            .block_error("sql", "failed to acquire lock")?
+            .take();
+
+        match result {
+            Some(Ok(value)) => {
+                let values = map!("{result}" => value.clone());
+                self.text.set_text(self.format.render_static_str(&values)?);
+                self.text
+                    .set_state(value.parse().map_or(State::Idle, |v| self.state(v)));
+                Ok(Some(self.update_interval.into()))
+            }
+            Some(Err(message)) => Err(BlockError("sql".to_owned(), message)),
+            None => {
+                self.send.send(())?;
+                Ok(Some(self.update_interval.into()))
+            }
+        }
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let Some(ref name) = e.name {
+            if name.as_str() == self.id && e.button == MouseButton::Left {
+                self.send.send(())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::sql::output_with_timeout;
+    use std::process::Command;
+    use std::time::Duration;
+
+    #[test]
+    fn test_output_with_timeout() {
+        // More than fits in a pipe, on both outputs
+        let output = output_with_timeout(
+            Command::new("sh").args(&["-c", "seq 100000; seq 100000 >&2"]),
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert_eq!(output.lines().count(), 100000);
+        assert!(output_with_timeout(
+            Command::new("sh").args(&["-c", "sleep 5"]),
+            Duration::from_millis(100)
+        )
+        .is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use std::process::Command;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
 use serde_derive::Deserialize;
@@ -12,7 +12,7 @@ use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
-use crate::util::FormatTemplate;
+use crate::util::{FormatTemplate, Scaling};
 use crate::widget::{I3BarWidget, State};
 use crate::widgets::button::ButtonWidget;
 
@@ -28,8 +28,17 @@ pub struct Temperature {
     maximum_warning: i64,
     format: FormatTemplate,
     chip: Option<String>,
+    scaling: Scaling,
+    debug_click: bool,
+    /// Where the hottest reading came from, and its raw value
+    raw: String,
+    /// While set, the raw reading is shown instead of the formatted one
+    debug_until: Option<Instant>,
 }
 
+/// How long a right click shows the raw reading when `debug_click` is enabled
+const DEBUG_DURATION: Duration = Duration::from_secs(5);
+
 #[derive(Deserialize, Debug, Default, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct TemperatureConfig {
@@ -67,6 +76,14 @@ pub struct TemperatureConfig {
     /// Chip override
     #[serde(default = "TemperatureConfig::default_chip")]
     pub chip: Option<String>,
+
+    /// Transform applied to each reading, e.g. to correct a sensor offset
+    #[serde(default)]
+    pub scaling: Scaling,
+
+    /// Show the raw value and the sensor of the hottest reading on right click
+    #[serde(default = "TemperatureConfig::default_debug_click")]
+    pub debug_click: bool,
 }
 
 impl TemperatureConfig {
@@ -101,6 +118,10 @@ impl TemperatureConfig {
     fn default_chip() -> Option<String> {
         None
     }
+
+    fn default_debug_click() -> bool {
+        false
+    }
 }
 
 impl ConfigBlock for Temperature {
@@ -125,10 +146,26 @@ impl ConfigBlock for Temperature {
             format: FormatTemplate::from_string(&block_config.format)
                 .block_error("temperature", "Invalid format specified for temperature")?,
             chip: block_config.chip,
+            scaling: block_config.scaling.validated("temperature")?,
+            debug_click: block_config.debug_click,
+            raw: String::new(),
+            debug_until: None,
         })
     }
 }
 
+impl Temperature {
+    fn refresh_text(&mut self) {
+        if self.debug_until.is_some() {
+            self.text.set_text(self.raw.clone());
+        } else if self.collapsed {
+            self.text.set_text(String::new());
+        } else {
+            self.text.set_text(self.output.clone());
+        }
+    }
+}
+
 impl Block for Temperature {
     fn update(&mut self) -> Result<Option<Update>> {
         let mut args = vec!["-u"];
@@ -142,8 +179,23 @@ impl Block for Temperature {
             .unwrap_or_else(|e| e.to_string());
 
         let mut temperatures: Vec<i64> = Vec::new();
+        let mut hottest: Option<(i64, String)> = None;
 
+        // Each chip starts with its name, followed by its features, each one a label line
+        // and the readings for it
+        let mut chip = "";
+        let mut feature = "";
         for line in output.lines() {
+            if line.is_empty() {
+                chip = "";
+            } else if !line.starts_with(' ') {
+                if chip.is_empty() {
+                    chip = line;
+                } else if line.ends_with(':') {
+                    feature = line.trim_end_matches(':');
+                }
+            }
+
             if line.starts_with("  temp") {
                 let rest = &line[6..]
                     .split('_')
@@ -155,7 +207,13 @@ impl Block for Temperature {
                     match rest[2].parse::<i64>() {
                         Ok(t) if t == 0 => Ok(()),
                         Ok(t) if t > -101 && t < 151 => {
+                            let t = self.scaling.apply(t as f64).round() as i64;
                             temperatures.push(t);
+                            if self.debug_click && hottest.as_ref().map_or(true, |h| t > h.0) {
+                                // e.g. "coretemp-isa-0000/Core 0/temp2_input = 45.000"
+                                let reading = line.trim().replacen(':', " =", 1);
+                                hottest = Some((t, format!("{}/{}/{}", chip, feature, reading)));
+                            }
                             Ok(())
                         }
                         Ok(t) => {
@@ -189,9 +247,10 @@ impl Block for Temperature {
                               "{max}" => max);
 
             self.output = self.format.render_static_str(&values)?;
-            if !self.collapsed {
-                self.text.set_text(self.output.clone());
+            if let Some((_, raw)) = hottest {
+                self.raw = raw;
             }
+            self.render()?;
 
             let state = match max {
                 m if m <= self.maximum_good => State::Good,
@@ -207,19 +266,47 @@ impl Block for Temperature {
         Ok(Some(self.update_interval.into()))
     }
 
+    fn render_interval(&self) -> Option<Duration> {
+        // Ends the debug view on time rather than at the next update
+        if self.debug_click {
+            Some(Duration::from_secs(1))
+        } else {
+            None
+        }
+    }
+
+    fn render(&mut self) -> Result<()> {
+        if self
+            .debug_until
+            .map_or(false, |until| until <= Instant::now())
+        {
+            self.debug_until = None;
+        }
+        self.refresh_text();
+        Ok(())
+    }
+
     fn view(&self) -> Vec<&dyn I3BarWidget> {
         vec![&self.text]
     }
 
     fn click(&mut self, e: &I3BarEvent) -> Result<()> {
         if let Some(ref name) = e.name {
-            if name.as_str() == self.id && e.button == MouseButton::Left {
-                self.collapsed = !self.collapsed;
-                if self.collapsed {
-                    self.text.set_text(String::new());
-                } else {
-                    self.text.set_text(self.output.clone());
+            if name.as_str() == self.id {
+                match e.button {
+                    MouseButton::Left => {
+                        self.collapsed = !self.collapsed;
+                        self.debug_until = None;
+                    }
+                    MouseButton::Right if self.debug_click => {
+                        self.debug_until = match self.debug_until {
+                            Some(_) => None,
+                            None => Some(Instant::now() + DEBUG_DURATION),
+                        };
+                    }
+                    _ => {}
                 }
+                self.refresh_text();
             }
         }
 
@@ -3,7 +3,7 @@ use serde_derive::Deserialize;
 use std::collections::HashMap;
 use std::env;
 use std::process::Command;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 use crate::blocks::Update;
@@ -54,14 +54,42 @@ pub enum OpenWeatherMapUnits {
     Imperial,
 }
 
+/// A location given in the block's `locations`, rather than the service's.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeatherLocation {
+    pub city_id: Option<String>,
+    pub place: Option<String>,
+}
+
+/// A location shown by the block, with the weather last fetched for it.
+struct Place {
+    /// `None` for the location configured in the service
+    location: Option<WeatherLocation>,
+    icon: &'static str,
+    weather_keys: HashMap<String, String>,
+    /// When the weather was last fetched successfully
+    updated: Option<Instant>,
+}
+
+/// Delay between the first fetches of several locations, so they don't all hit the API at once
+const STAGGER: Duration = Duration::from_secs(5);
+
 pub struct Weather {
     id: String,
     weather: ButtonWidget,
     format: String,
-    weather_keys: HashMap<String, String>,
     service: WeatherService,
     update_interval: Duration,
+    render_interval: Duration,
     autolocate: bool,
+    places: Vec<Place>,
+    /// The place being shown
+    current: usize,
+    /// The place fetched by the next update
+    next_fetch: usize,
+    /// Whether every place has been fetched once
+    fetched_all: bool,
 }
 
 fn malformed_json_error() -> Error {
@@ -69,7 +97,7 @@ fn malformed_json_error() -> Error {
 }
 
 impl Weather {
-    fn update_weather(&mut self) -> Result<()> {
+    fn update_weather(&mut self, index: usize) -> Result<()> {
         match self.service {
             WeatherService::OpenWeatherMap {
                 api_key: Some(ref api_key),
@@ -78,7 +106,8 @@ impl Weather {
                 ref units,
             } => {
                 // TODO: might be good to allow for different geolocation services to be used, similar to how we have `service` for the weather API
-                let geoip_city = if self.autolocate {
+                let location = self.places[index].location.as_ref();
+                let geoip_city = if self.autolocate && location.is_none() {
                     let geoip_output = match Command::new("sh")
                         .args(&["-c", "curl --max-time 3 --silent 'https://ipapi.co/json/'"])
                         .output()
@@ -109,7 +138,14 @@ impl Weather {
                     None
                 };
 
-                let location_query = if let Some(city) = geoip_city {
+                let location_query = if let Some(location) = location {
+                    match (&location.city_id, &location.place) {
+                        (Some(city_id), _) => format!("id={}", city_id),
+                        (None, Some(place)) => format!("q={}", place),
+                        // Checked when the block is created
+                        (None, None) => unreachable!(),
+                    }
+                } else if let Some(city) = geoip_city {
                     format!("q={}", city)
                 } else if city_id.is_some() {
                     format!("id={}", city_id.as_ref().unwrap())
@@ -163,8 +199,8 @@ impl Weather {
                 // Don't error out on empty responses e.g. for when not
                 // connected to the internet.
                 if output.is_empty() {
-                    self.weather.set_icon("weather_default");
-                    self.weather_keys = HashMap::new();
+                    self.places[index].icon = "weather_default";
+                    self.places[index].weather_keys = HashMap::new();
                     return Ok(());
                 }
 
@@ -260,22 +296,55 @@ impl Weather {
                     }
                 }
 
-                self.weather.set_icon(match raw_weather.as_str() {
+                // Convert wind direction in azimuth degrees to one of 16 compass point names
+                fn convert_wind_compass(direction_opt: Option<f64>) -> String {
+                    const POINTS: [&str; 16] = [
+                        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW",
+                        "W", "WNW", "NW", "NNW",
+                    ];
+                    match direction_opt {
+                        Some(direction) => {
+                            let index = (direction / 22.5).round() as i64;
+                            POINTS[index.rem_euclid(16) as usize].to_string()
+                        }
+                        None => "-".to_string(),
+                    }
+                }
+
+                // Convert wind direction in azimuth degrees to an arrow pointing the way the
+                // wind blows, e.g. a northerly wind (coming from 0°) is shown as "↓"
+                fn convert_wind_arrow(direction_opt: Option<f64>, speed: f64) -> String {
+                    const ARROWS: [&str; 8] = ["↓", "↙", "←", "↖", "↑", "↗", "→", "↘"];
+                    match direction_opt {
+                        Some(direction) if speed > 0.0 => {
+                            let index = (direction / 45.0).round() as i64;
+                            ARROWS[index.rem_euclid(8) as usize].to_string()
+                        }
+                        // Calm, or no direction reported
+                        _ => "·".to_string(),
+                    }
+                }
+
+                let place = &mut self.places[index];
+                place.icon = match raw_weather.as_str() {
                     "Clear" => "weather_sun",
                     "Rain" | "Drizzle" => "weather_rain",
                     "Clouds" | "Fog" | "Mist" => "weather_clouds",
                     "Thunderstorm" => "weather_thunder",
                     "Snow" => "weather_snow",
                     _ => "weather_default",
-                });
+                };
 
-                self.weather_keys = map_to_owned!("{weather}" => raw_weather,
+                place.weather_keys = map_to_owned!("{weather}" => raw_weather,
                                   "{temp}" => format!("{:.0}", raw_temp),
                                   "{humidity}" => format!("{:.0}", raw_humidity),
                                   "{apparent}" => format!("{:.0}",apparent_temp),
                                   "{wind}" => format!("{:.1}", raw_wind_speed),
                                   "{direction}" => convert_wind_direction(raw_wind_direction),
+                                  "{wind_direction}" => convert_wind_arrow(raw_wind_direction, raw_wind_speed),
+                                  "{wind_compass}" => convert_wind_compass(raw_wind_direction),
                                   "{location}" => raw_location);
+                place.updated = Some(Instant::now());
                 Ok(())
             }
             WeatherService::OpenWeatherMap { ref api_key, .. } => {
@@ -308,6 +377,15 @@ pub struct WeatherConfig {
     pub service: WeatherService,
     #[serde(default = "WeatherConfig::default_autolocate")]
     pub autolocate: bool,
+    /// How often the `{updated}` placeholder is refreshed between updates, in seconds
+    #[serde(
+        default = "WeatherConfig::default_render_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub render_interval: Duration,
+    /// Locations to cycle through on click, instead of the one of the service
+    #[serde(default = "WeatherConfig::default_locations")]
+    pub locations: Vec<WeatherLocation>,
 }
 
 impl WeatherConfig {
@@ -322,6 +400,14 @@ impl WeatherConfig {
     fn default_autolocate() -> bool {
         false
     }
+
+    fn default_render_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_locations() -> Vec<WeatherLocation> {
+        Vec::new()
+    }
 }
 
 impl ConfigBlock for Weather {
@@ -333,30 +419,103 @@ impl ConfigBlock for Weather {
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
         let id = Uuid::new_v4().to_simple().to_string();
+        if block_config
+            .locations
+            .iter()
+            .any(|location| location.city_id.is_none() && location.place.is_none())
+        {
+            return Err(ConfigurationError(
+                "weather".to_string(),
+                (
+                    "every entry of 'locations' needs a 'city_id' or a 'place'".to_string(),
+                    "invalid locations".to_string(),
+                ),
+            ));
+        }
+
+        let mut format = block_config.format;
+        let locations: Vec<Option<WeatherLocation>> = if block_config.locations.is_empty() {
+            vec![None]
+        } else {
+            // Say which of the locations is shown, if the format doesn't
+            if block_config.locations.len() > 1 && !format.contains("{location}") {
+                format = format!("{{location}} {}", format);
+            }
+            block_config.locations.into_iter().map(Some).collect()
+        };
+        let places = locations
+            .into_iter()
+            .map(|location| Place {
+                location,
+                icon: "weather_default",
+                weather_keys: HashMap::new(),
+                updated: None,
+            })
+            .collect();
+
         Ok(Weather {
             id: id.clone(),
             weather: ButtonWidget::new(config, &id),
-            format: block_config.format,
-            weather_keys: HashMap::new(),
+            format,
             service: block_config.service,
             update_interval: block_config.interval,
+            render_interval: block_config.render_interval,
             autolocate: block_config.autolocate,
+            places,
+            current: 0,
+            next_fetch: 0,
+            fetched_all: false,
         })
     }
 }
 
 impl Block for Weather {
     fn update(&mut self) -> Result<Option<Update>> {
-        self.update_weather()?;
+        // Each update fetches one place, so the fetches of several are spread over the interval
+        let index = self.next_fetch;
+        self.next_fetch = (index + 1) % self.places.len();
+        if self.next_fetch == 0 {
+            self.fetched_all = true;
+        }
+        self.update_weather(index)?;
+        self.render()?;
+        if self.fetched_all {
+            Ok(Some(
+                (self.update_interval / self.places.len() as u32).into(),
+            ))
+        } else {
+            Ok(Some(STAGGER.into()))
+        }
+    }
+
+    fn render_interval(&self) -> Option<Duration> {
+        // Only the age of the data changes between updates
+        if self.format.contains("{updated}") {
+            Some(self.render_interval)
+        } else {
+            None
+        }
+    }
+
+    fn render(&mut self) -> Result<()> {
+        let place = &self.places[self.current];
+        self.weather.set_icon(place.icon);
         // Display an error/disabled-looking widget when we don't have any
         // weather information, which is likely due to internet connectivity.
-        if self.weather_keys.keys().len() == 0 {
+        if place.weather_keys.keys().len() == 0 {
             self.weather.set_text("×".to_string());
         } else {
+            let mut weather_keys = place.weather_keys.clone();
+            if let Some(updated) = place.updated {
+                weather_keys.insert(
+                    "{updated}".to_owned(),
+                    format!("{}m", updated.elapsed().as_secs() / 60),
+                );
+            }
             let fmt = FormatTemplate::from_string(&self.format)?;
-            self.weather.set_text(fmt.render(&self.weather_keys));
+            self.weather.set_text(fmt.render(&weather_keys));
         }
-        Ok(Some(self.update_interval.into()))
+        Ok(())
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
@@ -366,7 +525,13 @@ impl Block for Weather {
     fn click(&mut self, event: &I3BarEvent) -> Result<()> {
         if event.matches_name(self.id()) {
             if let MouseButton::Left = event.button {
-                self.update()?;
+                if self.places.len() > 1 {
+                    // Shown from what was last fetched, the places are fetched in turn
+                    self.current = (self.current + 1) % self.places.len();
+                    self.render()?;
+                } else {
+                    self.update()?;
+                }
             }
         }
         Ok(())
@@ -0,0 +1,242 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use regex::Regex;
+use serde_derive::Deserialize;
+use swayipc::reply::Node;
+use swayipc::{Connection, EventType};
+use uuid::Uuid;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::Config;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::threshold::{Sample, Threshold};
+use crate::util::FormatTemplate;
+use crate::widget::{I3BarWidget, State};
+use crate::widgets::text::TextWidget;
+
+/// Which windows are counted. Every part that is given has to match.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CriterionConfig {
+    /// Regex one of the window's marks has to match
+    pub mark: Option<String>,
+    /// Regex the window's class, or app_id under sway, has to match
+    pub class: Option<String>,
+    /// Regex the window's title has to match
+    pub title: Option<String>,
+    /// Whether the window has to be urgent, or not
+    pub urgent: Option<bool>,
+}
+
+struct Criterion {
+    mark: Option<Regex>,
+    class: Option<Regex>,
+    title: Option<Regex>,
+    urgent: Option<bool>,
+}
+
+impl Criterion {
+    fn new(config: &CriterionConfig) -> Result<Self> {
+        let regex = |pattern: &Option<String>, name: &str| -> Result<Option<Regex>> {
+            pattern
+                .as_ref()
+                .map(|pattern| {
+                    Regex::new(pattern).configuration_error(&format!(
+                        "invalid regex for 'criterion.{}' in window_count",
+                        name
+                    ))
+                })
+                .transpose()
+        };
+        Ok(Criterion {
+            mark: regex(&config.mark, "mark")?,
+            class: regex(&config.class, "class")?,
+            title: regex(&config.title, "title")?,
+            urgent: config.urgent,
+        })
+    }
+
+    fn matches(&self, window: &Node) -> bool {
+        let class = window
+            .window_properties
+            .as_ref()
+            .map(|properties| properties.class.as_str())
+            .or_else(|| window.app_id.as_deref())
+            .unwrap_or("");
+        self.mark
+            .as_ref()
+            .map_or(true, |mark| window.marks.iter().any(|m| mark.is_match(m)))
+            && self.class.as_ref().map_or(true, |re| re.is_match(class))
+            && self
+                .title
+                .as_ref()
+                .map_or(true, |re| re.is_match(window.name.as_deref().unwrap_or("")))
+            && self.urgent.map_or(true, |urgent| window.urgent == urgent)
+    }
+
+    /// Counts the windows below `node`, floating ones included, which match.
+    fn count(&self, node: &Node) -> usize {
+        let is_window = node.nodes.is_empty()
+            && node.floating_nodes.is_empty()
+            && (node.window.is_some() || node.app_id.is_some());
+        let own = if is_window && self.matches(node) {
+            1
+        } else {
+            0
+        };
+        own + node
+            .nodes
+            .iter()
+            .chain(node.floating_nodes.iter())
+            .map(|child| self.count(child))
+            .sum::<usize>()
+    }
+}
+
+fn count_windows(criterion: &Criterion) -> Result<usize> {
+    let tree = Connection::new()
+        .and_then(|mut con| con.get_tree())
+        .block_error("window_count", "failed to get the window tree over IPC")?;
+    Ok(criterion.count(&tree))
+}
+
+pub struct WindowCount {
+    id: String,
+    text: TextWidget,
+    format: FormatTemplate,
+    count: Arc<Mutex<usize>>,
+    info: Threshold,
+    warning: Threshold,
+    critical: Threshold,
+    previous: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WindowCountConfig {
+    /// Which windows to count
+    #[serde(default)]
+    pub criterion: CriterionConfig,
+
+    /// Format override
+    #[serde(default = "WindowCountConfig::default_format")]
+    pub format: String,
+
+    /// Count above which state is set to info
+    #[serde(default = "WindowCountConfig::default_info")]
+    pub info: Threshold,
+
+    /// Count above which state is set to warning
+    #[serde(default = "WindowCountConfig::default_warning")]
+    pub warning: Threshold,
+
+    /// Count above which state is set to critical
+    #[serde(default = "WindowCountConfig::default_critical")]
+    pub critical: Threshold,
+}
+
+impl WindowCountConfig {
+    fn default_format() -> String {
+        "{count}".to_owned()
+    }
+
+    fn default_info() -> Threshold {
+        Threshold::Above(0.0)
+    }
+
+    fn default_warning() -> Threshold {
+        Threshold::Above(5.0)
+    }
+
+    fn default_critical() -> Threshold {
+        Threshold::Above(10.0)
+    }
+}
+
+impl ConfigBlock for WindowCount {
+    type Config = WindowCountConfig;
+
+    fn new(block_config: Self::Config, config: Config, tx: Sender<Task>) -> Result<Self> {
+        let id = Uuid::new_v4().to_simple().to_string();
+        let criterion = Criterion::new(&block_config.criterion)?;
+        let count = Arc::new(Mutex::new(count_windows(&criterion)?));
+
+        let thread_count = count.clone();
+        let thread_id = id.clone();
+        thread::Builder::new()
+            .name("window_count".into())
+            .spawn(move || {
+                let events = Connection::new()
+                    .and_then(|con| con.subscribe(&[EventType::Window, EventType::Workspace]))
+                    .expect("failed to subscribe to window events");
+                // The events don't say enough about the other windows, so count them again
+                for event in events {
+                    if event.is_err() {
+                        break;
+                    }
+                    if let Ok(new_count) = count_windows(&criterion) {
+                        let mut count = thread_count.lock().unwrap();
+                        if *count != new_count {
+                            *count = new_count;
+                            tx.send(Task {
+                                id: thread_id.clone(),
+                                update_time: Instant::now(),
+                            })
+                            .unwrap();
+                        }
+                    }
+                }
+            })
+            .block_error("window_count", "failed to start the event thread")?;
+
+        Ok(WindowCount {
+            id,
+            text: TextWidget::new(config),
+            format: FormatTemplate::from_string(&block_config.format)
+                .block_error("window_count", "Invalid format specified for window_count")?,
+            count,
+            info: block_config.info,
+            warning: block_config.warning,
+            critical: block_config.critical,
+            previous: None,
+        })
+    }
+}
+
+impl Block for WindowCount {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let count = *self
+            .count
+            .lock()
+            .block_error("window_count", "failed to acquire lock")?;
+        let values = map!("{count}" => count.to_string());
+        self.text.set_text(self.format.render_static_str(&values)?);
+
+        let sample = Sample {
+            value: count as f64,
+            previous: self.previous,
+        };
+        self.previous = Some(sample.value);
+        self.text.set_state(match sample {
+            s if self.critical.reached(s) => State::Critical,
+            s if self.warning.reached(s) => State::Warning,
+            s if self.info.reached(s) => State::Info,
+            _ => State::Idle,
+        });
+
+        // Updated by the event thread when the count changes
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -3,13 +3,17 @@ use std::marker::PhantomData;
 use std::ops::Deref;
 use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 
 use serde::de::{Deserialize, Deserializer, Error};
 use serde_derive::Deserialize;
 use toml::value;
 
+use crate::blocks::mirror::MirrorRegistry;
+use crate::blocks::BLOCK_NAMES;
 use crate::de::*;
 use crate::input::MouseButton;
+use crate::stats::Stats;
 use crate::themes::{Theme, ThemeConfig};
 use crate::util::deserialize_file;
 use crate::{errors, icons};
@@ -27,16 +31,75 @@ pub struct Config {
     /// motion which is undesired for sliders. Use "natural" to invert this.
     #[serde(default = "Scrolling::default", rename = "scrolling")]
     pub scrolling: Scrolling,
+    /// Whether icons are placed before ("left") or after ("right") the text.
+    #[serde(default = "IconPosition::default")]
+    pub icon_position: IconPosition,
+    /// Font family for the widgets of a block, set through the block's `font` option.
+    #[serde(skip)]
+    pub font: Option<String>,
+    /// How long a block's widgets stay in a warning or critical state at least, set through
+    /// the block's `min_alert_duration` option.
+    #[serde(skip)]
+    pub min_alert_duration: Option<Duration>,
+    /// Values of the blocks with an `id`, shared by all blocks for `mirror` blocks to show.
+    #[serde(skip)]
+    pub mirrors: MirrorRegistry,
+    /// Timings and errors of the blocks, recorded with `--stats`.
+    #[serde(skip)]
+    pub stats: Stats,
+    /// Whether a failing block stops the bar, set with `--exit-on-error`, rather than being
+    /// shown in an error state.
+    #[serde(skip)]
+    pub exit_on_error: bool,
+    /// Terminal command used by `on_click_terminal`, with `{command}` marking where the
+    /// command goes.
+    #[serde(default)]
+    pub terminal: Option<String>,
+    /// Command used by `copy_on_middle_click`, reading the text to copy from stdin.
+    #[serde(default)]
+    pub copy_command: Option<String>,
+    /// Appended to text which blocks cut short to fit their `max_width`.
+    #[serde(default = "default_ellipsis")]
+    pub ellipsis: String,
+    /// Whether blocks may send desktop notifications.
+    #[serde(default = "default_notifications")]
+    pub notifications: bool,
+    /// Update all blocks right away when the bar is shown again after being hidden.
+    #[serde(default)]
+    pub refresh_on_reveal: bool,
+    /// Ids of blocks, in the order they are shown in.
+    #[serde(default)]
+    pub order: Vec<String>,
     #[serde(rename = "block", deserialize_with = "deserialize_blocks")]
     pub blocks: Vec<(String, value::Value)>,
 }
 
+fn default_ellipsis() -> String {
+    "…".to_owned()
+}
+
+fn default_notifications() -> bool {
+    true
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
             icons: icons::default(),
             theme: Theme::default(),
             scrolling: Scrolling::default(),
+            icon_position: IconPosition::default(),
+            font: None,
+            min_alert_duration: None,
+            mirrors: MirrorRegistry::default(),
+            stats: Stats::default(),
+            exit_on_error: false,
+            terminal: None,
+            copy_command: None,
+            ellipsis: default_ellipsis(),
+            notifications: default_notifications(),
+            refresh_on_reveal: false,
+            order: Vec::new(),
             blocks: Vec::new(),
         }
     }
@@ -51,11 +114,130 @@ impl From<LegacyConfig> for Config {
                 .and_then(|s| Theme::from_name(s.as_str()))
                 .unwrap_or_default(),
             scrolling: legacy_config.scrolling,
+            icon_position: legacy_config.icon_position,
+            font: None,
+            min_alert_duration: None,
+            mirrors: MirrorRegistry::default(),
+            stats: Stats::default(),
+            exit_on_error: false,
+            terminal: None,
+            copy_command: None,
+            ellipsis: default_ellipsis(),
+            notifications: default_notifications(),
+            refresh_on_reveal: false,
+            order: legacy_config.order,
             blocks: legacy_config.blocks,
         }
     }
 }
 
+impl Config {
+    /// Reads the optional `id` of every block's configuration and arranges the blocks
+    /// listed in `order` in that order. They fill the places the listed blocks have in the
+    /// file, so all other blocks keep their position.
+    fn arrange_blocks(&mut self) -> errors::Result<()> {
+        let mut ids: Vec<Option<String>> = Vec::with_capacity(self.blocks.len());
+        for (block_name, block_config) in &mut self.blocks {
+            let id = match block_config.get("id") {
+                Some(value::Value::String(id)) => id.clone(),
+                Some(_) => {
+                    return Err(order_error(format!("id of {} is not a string", block_name)))
+                }
+                None => {
+                    ids.push(None);
+                    continue;
+                }
+            };
+            if ids.iter().any(|x| x.as_ref() == Some(&id)) {
+                return Err(order_error(format!("more than one block has id {}", id)));
+            }
+            ids.push(Some(id));
+        }
+
+        let mut listed = Vec::with_capacity(self.order.len());
+        for id in &self.order {
+            let index = ids
+                .iter()
+                .position(|x| x.as_ref() == Some(id))
+                .ok_or_else(|| order_error(format!("no block has id {}", id)))?;
+            if listed.contains(&index) {
+                return Err(order_error(format!("{} is listed more than once", id)));
+            }
+            listed.push(index);
+        }
+
+        let mut places = listed.clone();
+        places.sort_unstable();
+        let mut arrangement: Vec<usize> = (0..self.blocks.len()).collect();
+        for (&place, &index) in places.iter().zip(&listed) {
+            arrangement[place] = index;
+        }
+        let mut blocks: Vec<_> = self.blocks.drain(..).map(Some).collect();
+        self.blocks = arrangement
+            .into_iter()
+            .map(|index| blocks[index].take().unwrap())
+            .collect();
+        Ok(())
+    }
+
+    /// Checks that every block has a known type, so typos are reported with the position of
+    /// the block instead of when the bar starts creating blocks.
+    fn check_block_names(&self) -> errors::Result<()> {
+        for (index, (name, _)) in self.blocks.iter().enumerate() {
+            if BLOCK_NAMES.contains(&name.as_str()) {
+                continue;
+            }
+            let suggestion = BLOCK_NAMES
+                .iter()
+                .map(|known| (edit_distance(name, known), known))
+                .filter(|(distance, _)| *distance <= 2)
+                .min()
+                .map(|(_, known)| format!(" Did you mean \"{}\"?", known))
+                .unwrap_or_default();
+            return Err(errors::ConfigurationError(
+                format!(
+                    "unknown block type \"{}\" in [[block]] number {}.{}",
+                    name,
+                    index + 1,
+                    suggestion
+                ),
+                (
+                    format!("known block types are {}", BLOCK_NAMES.join(", ")),
+                    "unknown block type".to_string(),
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The number of characters to insert, delete or replace to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+fn order_error(message: String) -> errors::Error {
+    errors::ConfigurationError(
+        "order".to_string(),
+        (message, "invalid block order".to_string()),
+    )
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct LegacyConfig {
     #[serde(default = "icons::default", deserialize_with = "deserialize_icons")]
@@ -69,6 +251,12 @@ pub struct LegacyConfig {
     /// motion which is undesired for sliders. Use "natural" to invert this.
     #[serde(default = "Scrolling::default", rename = "scrolling")]
     pub scrolling: Scrolling,
+    /// Whether icons are placed before ("left") or after ("right") the text.
+    #[serde(default = "IconPosition::default")]
+    pub icon_position: IconPosition,
+    /// Ids of blocks, in the order they are shown in.
+    #[serde(default)]
+    pub order: Vec<String>,
     #[serde(rename = "block", deserialize_with = "deserialize_blocks")]
     pub blocks: Vec<(String, value::Value)>,
 }
@@ -79,6 +267,8 @@ impl Default for LegacyConfig {
             icons: icons::default(),
             theme: None,
             scrolling: Scrolling::default(),
+            icon_position: IconPosition::default(),
+            order: Vec::new(),
             blocks: Vec::new(),
         }
     }
@@ -116,6 +306,30 @@ impl Default for Scrolling {
     }
 }
 
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IconPosition {
+    Left,
+    Right,
+}
+
+impl IconPosition {
+    /// Builds a widget's `full_text` from its icon and text. Icons carry their own
+    /// padding, so only the text needs a space on the side facing the block edge.
+    pub fn full_text(self, icon: Option<&str>, text: &str) -> String {
+        match (self, icon) {
+            (IconPosition::Right, Some(icon)) if !text.is_empty() => format!(" {}{}", text, icon),
+            _ => format!("{}{} ", icon.unwrap_or(" "), text),
+        }
+    }
+}
+
+impl Default for IconPosition {
+    fn default() -> Self {
+        IconPosition::Left
+    }
+}
+
 fn deserialize_blocks<'de, D>(deserializer: D) -> Result<Vec<(String, value::Value)>, D::Error>
 where
     D: Deserializer<'de>,
@@ -155,15 +369,19 @@ where
 // this function may belong somewhere else...
 pub fn load_config(config_path: &Path) -> errors::Result<Config> {
     let config: errors::Result<Config> = deserialize_file(config_path.to_str().unwrap());
-    config.or_else(|_| {
+    let mut config = config.or_else(|_| {
         let legacy_config: errors::Result<LegacyConfig> =
             deserialize_file(config_path.to_str().unwrap());
         legacy_config.map(|legacy| legacy.into())
-    })
+    })?;
+    // Before arranging them, so the position matches the file
+    config.check_block_names()?;
+    config.arrange_blocks()?;
+    Ok(config)
 }
 #[cfg(test)]
 mod tests {
-    use crate::config::load_config;
+    use crate::config::{load_config, IconPosition};
     use assert_fs::prelude::{FileWriteStr, PathChild};
     use assert_fs::TempDir;
 
@@ -209,4 +427,71 @@ mod tests {
         let config = load_config(config_file_path.path());
         config.unwrap();
     }
+
+    #[test]
+    fn test_load_config_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file_path = temp_dir.child("status.toml");
+        config_file_path
+            .write_str(
+                concat!(
+                    "order = [\"b\", \"a\"]\n",
+                    "[[block]]\n",
+                    "block = \"load\"\n",
+                    "id = \"a\"\n",
+                    "[[block]]\n",
+                    "block = \"time\"\n",
+                    "[[block]]\n",
+                    "block = \"cpu\"\n",
+                    "id = \"b\"\n",
+                    "[[block]]\n",
+                    "block = \"memory\"\n",
+                    "id = \"c\"\n",
+                )
+                .as_ref(),
+            )
+            .unwrap();
+        let config = load_config(config_file_path.path()).unwrap();
+        let names: Vec<_> = config
+            .blocks
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(names, ["cpu", "time", "load", "memory"]);
+        let ids: Vec<_> = config
+            .blocks
+            .iter()
+            .map(|(_, block_config)| block_config.get("id").and_then(|id| id.as_str()))
+            .collect();
+        assert_eq!(ids, [Some("b"), None, Some("a"), Some("c")]);
+
+        config_file_path
+            .write_str("order = [\"x\"]\n[[block]]\nblock = \"load\"\nid = \"a\"\n")
+            .unwrap();
+        assert!(load_config(config_file_path.path()).is_err());
+    }
+
+    #[test]
+    fn test_load_config_unknown_block() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file_path = temp_dir.child("status.toml");
+        config_file_path
+            .write_str("[[block]]\nblock = \"time\"\n[[block]]\nblock = \"memroy\"\n")
+            .unwrap();
+        let error = load_config(config_file_path.path()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Configuration error: unknown block type \"memroy\" in [[block]] number 2. \
+             Did you mean \"memory\"?"
+        );
+    }
+
+    #[test]
+    fn test_icon_position_full_text() {
+        assert_eq!(IconPosition::Left.full_text(Some(" M "), "5"), " M 5 ");
+        assert_eq!(IconPosition::Right.full_text(Some(" M "), "5"), " 5 M ");
+        assert_eq!(IconPosition::Right.full_text(Some(" M "), ""), " M  ");
+        assert_eq!(IconPosition::Left.full_text(None, "5"), " 5 ");
+        assert_eq!(IconPosition::Right.full_text(None, "5"), " 5 ");
+    }
 }
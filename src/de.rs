@@ -98,6 +98,61 @@ where
     deserialize_duration(deserializer).map(Some)
 }
 
+/// A color written as `#rrggbb` or `#rrggbbaa`.
+pub fn deserialize_opt_color<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let color = String::deserialize(deserializer)?;
+    let valid = (color.len() == 7 || color.len() == 9)
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if valid {
+        Ok(Some(color))
+    } else {
+        Err(de::Error::custom(format!(
+            "invalid color \"{}\", expected #rrggbb or #rrggbbaa",
+            color
+        )))
+    }
+}
+
+/// A string, or a list of strings.
+pub fn deserialize_one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct OneOrManyWrapper;
+
+    impl<'de> de::Visitor<'de> for OneOrManyWrapper {
+        type Value = Vec<String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string or a list of strings")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(vec![value.to_owned()])
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: de::SeqAccess<'de>,
+        {
+            let mut values = Vec::new();
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(values)
+        }
+    }
+
+    deserializer.deserialize_any(OneOrManyWrapper)
+}
+
 pub struct MapType<T, V>(pub PhantomData<T>, pub PhantomData<V>);
 
 macro_rules! map_type {
@@ -257,7 +312,9 @@ where
 mod tests {
     use crate::blocks::Update;
     use crate::blocks::Update::{Every, Once};
-    use crate::de::{deserialize_duration, deserialize_update};
+    use crate::de::{
+        deserialize_duration, deserialize_one_or_many, deserialize_opt_color, deserialize_update,
+    };
     use serde_derive::Deserialize;
     use std::time::Duration;
 
@@ -299,4 +356,38 @@ mod tests {
         let deserialized: UpdateConfig = toml::from_str(duration_toml).unwrap();
         assert_eq!(Once, deserialized.interval);
     }
+
+    #[derive(Deserialize, Debug, Clone)]
+    #[serde(deny_unknown_fields)]
+    pub struct ColorConfig {
+        #[serde(default, deserialize_with = "deserialize_opt_color")]
+        pub color: Option<String>,
+    }
+
+    #[test]
+    fn test_deserialize_opt_color() {
+        let deserialized: ColorConfig = toml::from_str(r##"color = "#ff0000""##).unwrap();
+        assert_eq!(Some("#ff0000".to_owned()), deserialized.color);
+        let deserialized: ColorConfig = toml::from_str(r##"color = "#FF000080""##).unwrap();
+        assert_eq!(Some("#FF000080".to_owned()), deserialized.color);
+        assert!(toml::from_str::<ColorConfig>(r##"color = "red""##).is_err());
+        assert!(toml::from_str::<ColorConfig>(r##"color = "#ff00""##).is_err());
+        assert!(toml::from_str::<ColorConfig>(r##"color = "#gg0000""##).is_err());
+    }
+
+    #[derive(Deserialize, Debug, Clone)]
+    #[serde(deny_unknown_fields)]
+    pub struct OneOrManyConfig {
+        #[serde(deserialize_with = "deserialize_one_or_many")]
+        pub values: Vec<String>,
+    }
+
+    #[test]
+    fn test_deserialize_one_or_many() {
+        let deserialized: OneOrManyConfig = toml::from_str(r#"values = "a""#).unwrap();
+        assert_eq!(vec!["a"], deserialized.values);
+        let deserialized: OneOrManyConfig = toml::from_str(r#"values = ["a", "b"]"#).unwrap();
+        assert_eq!(vec!["a", "b"], deserialized.values);
+        assert!(toml::from_str::<OneOrManyConfig>("values = 1").is_err());
+    }
 }
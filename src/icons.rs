@@ -38,6 +38,8 @@ lazy_static! {
         "net_wireless" => " WLAN",
         "net_wired" => " ETH",
         "net_vpn" => " VPN",
+        "microphone" => " MIC ",
+        "microphone_muted" => " MIC MUTED ",
         "ping" => " PING ",
         "backlight_empty" => " BRIGHT ",
         "backlight_partial1" => " BRIGHT ",
@@ -53,6 +55,7 @@ lazy_static! {
         "uptime" => " UP ",
         "gpu" => " GPU ",
         "mail" => " ",
+        "mail_unread" => " NEW ",
         "bluetooth" => " BT",
         "headphones" => " HEAD",
         "joystick" => " JOY",
@@ -62,7 +65,12 @@ lazy_static! {
         "pomodoro" => " POMODORO ",
         "github" => " GITHUB ",
         "phone" => " PHONE ",
-        "notification" => " NOTIF "
+        "notification" => " NOTIF ",
+        "layout_splith" => " SPLITH ",
+        "layout_splitv" => " SPLITV ",
+        "layout_tabbed" => " TABBED ",
+        "layout_stacked" => " STACKED ",
+        "error" => " ERR "
     };
 
     // FontAwesome 4
@@ -101,6 +109,8 @@ lazy_static! {
         "net_wired" => " \u{f0ac} ",
         "net_bridge" => " \u{f0e8} ",
         "net_vpn" => " \u{f023} ",
+        "microphone" => " \u{f130} ",
+        "microphone_muted" => " \u{f131} ",
         "net_modem" => " \u{f095} ",
         "ping" => " \u{21ba} ",
         "backlight_empty" => " \u{1f315} ",
@@ -119,6 +129,7 @@ lazy_static! {
         "uptime" => " \u{f017} ",
         "gpu" => " \u{f26c} ",
         "mail" => " \u{f0e0} ",
+        "mail_unread" => " \u{f199} ",
         "bluetooth" => " \u{f294}",
         "headphones" => " \u{f025}",
         "joystick" => " \u{f11b}",
@@ -129,6 +140,11 @@ lazy_static! {
         "github" => " \u{f09b} ",
         "phone" => " \u{f10b} ",
         "notification" => " \u{f0a2} ",
+        "layout_splith" => " \u{f0db} ",
+        "layout_splitv" => " \u{f0c9} ",
+        "layout_tabbed" => " \u{f2d0} ",
+        "layout_stacked" => " \u{f03a} ",
+        "error" => " \u{f071} ",
         "unknown" => " \u{f128} "
     };
 
@@ -168,6 +184,8 @@ lazy_static! {
         "net_wired" => " \u{f6ff} ",
         "net_bridge" => " \u{f0e8} ",
         "net_vpn" => " \u{f023} ",
+        "microphone" => " \u{f130} ",
+        "microphone_muted" => " \u{f131} ",
         "net_modem" => " \u{f095} ",
         "ping" => " \u{f362} ",
         "backlight_empty" => " \u{1f315} ",
@@ -185,6 +203,7 @@ lazy_static! {
         "uptime" => " \u{f2f2} ",
         "gpu" => " \u{f26c} ",
         "mail" => " \u{f0e0} ",
+        "mail_unread" => " \u{f199} ",
         "bluetooth" => " \u{f294}",
         "headphones" => " \u{f025}",
         "joystick" => " \u{f11b}",
@@ -195,6 +214,11 @@ lazy_static! {
         "github" => " \u{f09b} ",
         "notification" => " \u{f0f3} ",
         "phone" => " \u{f3cd} ",
+        "layout_splith" => " \u{f0db} ",
+        "layout_splitv" => " \u{f0c9} ",
+        "layout_tabbed" => " \u{f2d0} ",
+        "layout_stacked" => " \u{f03a} ",
+        "error" => " \u{f071} ",
         "unknown" => " \u{f128} "
     };
 
@@ -231,6 +255,7 @@ lazy_static! {
         "uptime" => " \u{e192} ",
         "gpu" => " \u{e333} ",
         "mail" => " \u{e0be} ",
+        "mail_unread" => " \u{e159} ",
         "bluetooth" => " \u{e1a7}",
         "headphones" => " \u{e60f}",
         "joystick" => " \u{e30f}",
@@ -240,7 +265,12 @@ lazy_static! {
         "pomodoro" => " \u{1f345} ",
         "github" => " \u{e86f} ",
         "phone" => " \u{e324} ",
-        "notification" => " \u{e7f7} "
+        "notification" => " \u{e7f7} ",
+        "layout_splith" => " \u{e8ec} ",
+        "layout_splitv" => " \u{e8f2} ",
+        "layout_tabbed" => " \u{e8d8} ",
+        "layout_stacked" => " \u{e8ef} ",
+        "error" => " \u{e002} "
     };
 }
 
@@ -0,0 +1,239 @@
+//! Reading how long the user has been idle, from the XScreenSaver extension under X11 or the
+//! `ext-idle-notify-v1` protocol under Wayland.
+
+use std::env;
+#[cfg(feature = "x11")]
+use std::{os::raw::c_void, ptr};
+
+use serde::Deserialize;
+#[cfg(feature = "x11")]
+use x11::{
+    xlib::{Window, XDefaultRootWindow, XFree, XRootWindow, XScreenCount},
+    xss::{
+        XScreenSaverAllocInfo, XScreenSaverInfo, XScreenSaverQueryExtension, XScreenSaverQueryInfo,
+    },
+};
+
+use crate::errors::*;
+use crate::wayland_idle::WaylandIdle;
+#[cfg(feature = "x11")]
+use crate::x11_shared::SharedDisplay;
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IdleBackend {
+    /// The XScreenSaver extension
+    X11,
+    /// The ext-idle-notify-v1 protocol
+    Wayland,
+}
+
+/// Where the idle time is read from, so blocks can be tested without a display.
+pub trait IdleSource {
+    /// Milliseconds since the last input.
+    fn idle(&self) -> Result<u64>;
+
+    /// Lets go of the connection, after which the idle time reads 0.
+    fn close(&mut self) {}
+
+    /// Whether the idle time can stop growing while i3lock runs, which the activity block's
+    /// `i3lock_workaround` makes up for. Only XScreenSaver does that.
+    fn may_stall_when_locked(&self) -> bool {
+        false
+    }
+
+    /// Has `wake` called as soon as the user becomes idle or comes back, for backends which are
+    /// told about it. The others have to be asked again.
+    fn set_wake(&mut self, _wake: Box<dyn Fn() + Send>) {}
+}
+
+#[cfg(feature = "x11")]
+pub(crate) struct DeferXFree(*mut c_void);
+#[cfg(feature = "x11")]
+impl DeferXFree {
+    fn free(&mut self) {
+        if !self.0.is_null() {
+            unsafe {
+                XFree(self.0);
+            }
+            self.0 = ptr::null_mut();
+        }
+    }
+}
+#[cfg(feature = "x11")]
+impl Drop for DeferXFree {
+    fn drop(&mut self) {
+        self.free();
+    }
+}
+
+/// The idle time of the session the bar runs in.
+pub enum SystemIdle {
+    #[cfg(feature = "x11")]
+    X11 {
+        block_name: &'static str,
+        /// `None` once closed
+        display: Option<SharedDisplay>,
+        /// The root window of the screen whose input counts
+        root: Window,
+        info: *mut XScreenSaverInfo,
+        _defer_free_info: DeferXFree,
+    },
+    Wayland(WaylandIdle),
+}
+
+impl SystemIdle {
+    /// `None` if the X server doesn't have the XScreenSaver extension.
+    #[cfg(feature = "x11")]
+    fn x11(block_name: &'static str, screen: Option<usize>) -> Result<Option<Self>> {
+        let display = SharedDisplay::open(block_name)?;
+        let supported = display.with(|display| {
+            let (mut event_base, mut error_base) = (0, 0);
+            unsafe { XScreenSaverQueryExtension(display, &mut event_base, &mut error_base) != 0 }
+        });
+        if !supported {
+            return Ok(None);
+        }
+        let root = display.with(|display| match screen {
+            None => Ok(unsafe { XDefaultRootWindow(display) }),
+            Some(screen) => {
+                let count = unsafe { XScreenCount(display) } as usize;
+                if screen < count {
+                    Ok(unsafe { XRootWindow(display, screen as i32) })
+                } else {
+                    Err(ConfigurationError(
+                        block_name.to_string(),
+                        (
+                            format!(
+                                "'screen' is {}, but the display has {} screens",
+                                screen, count
+                            ),
+                            "invalid screen".to_string(),
+                        ),
+                    ))
+                }
+            }
+        })?;
+        let info = unsafe { XScreenSaverAllocInfo() };
+        if info.is_null() {
+            return Err(BlockError(
+                block_name.to_owned(),
+                "failed to allocate the XScreenSaver info".to_owned(),
+            ));
+        }
+        Ok(Some(SystemIdle::X11 {
+            block_name,
+            display: Some(display),
+            root,
+            info,
+            _defer_free_info: DeferXFree(info as *mut c_void),
+        }))
+    }
+
+    /// Connects to the backend given, or the one detected from the environment. `screen`
+    /// selects the X screen whose input counts, the default one if not given. If the X server
+    /// can't tell the idle time, Wayland is tried instead, unless `backend` asks for X11.
+    /// Without the `x11` feature, Wayland is the only backend. Errors are reported for
+    /// `block_name`.
+    #[cfg_attr(not(feature = "x11"), allow(unused_variables))]
+    pub fn new(
+        block_name: &'static str,
+        backend: Option<IdleBackend>,
+        screen: Option<usize>,
+    ) -> Result<Self> {
+        // Under Xwayland, X only sees the input going to X clients
+        let forced = backend.is_some();
+        let backend = backend.unwrap_or_else(|| {
+            if !cfg!(feature = "x11")
+                || env::var_os("WAYLAND_DISPLAY").is_some() && env::var_os("DISPLAY").is_none()
+            {
+                IdleBackend::Wayland
+            } else {
+                IdleBackend::X11
+            }
+        });
+        match backend {
+            #[cfg(feature = "x11")]
+            IdleBackend::X11 => match SystemIdle::x11(block_name, screen)? {
+                Some(x11) => Ok(x11),
+                None if !forced && env::var_os("WAYLAND_DISPLAY").is_some() => {
+                    Ok(SystemIdle::Wayland(WaylandIdle::new(block_name)?))
+                }
+                None => Err(BlockError(
+                    block_name.to_owned(),
+                    "the X server doesn't support the XScreenSaver extension".to_owned(),
+                )),
+            },
+            #[cfg(not(feature = "x11"))]
+            IdleBackend::X11 => Err(ConfigurationError(
+                block_name.to_owned(),
+                (
+                    "the X11 backend isn't available, the \"x11\" feature was left out of the build"
+                        .to_owned(),
+                    "built without x11".to_owned(),
+                ),
+            )),
+            IdleBackend::Wayland => Ok(SystemIdle::Wayland(WaylandIdle::new(block_name)?)),
+        }
+    }
+}
+
+impl IdleSource for SystemIdle {
+    fn idle(&self) -> Result<u64> {
+        match self {
+            #[cfg(feature = "x11")]
+            SystemIdle::X11 { display: None, .. } => Ok(0),
+            #[cfg(feature = "x11")]
+            SystemIdle::X11 {
+                block_name,
+                display: Some(display),
+                root,
+                info,
+                ..
+            } => display.with(|display| {
+                if unsafe { XScreenSaverQueryInfo(display, *root, *info) } == 0 {
+                    // Being never idle would keep the session going forever
+                    Err(BlockError(
+                        block_name.to_string(),
+                        "failed to query the XScreenSaver idle time".to_owned(),
+                    ))
+                } else {
+                    Ok(unsafe { (**info).idle })
+                }
+            }),
+            SystemIdle::Wayland(wayland) => wayland.idle(),
+        }
+    }
+
+    fn may_stall_when_locked(&self) -> bool {
+        match self {
+            #[cfg(feature = "x11")]
+            SystemIdle::X11 { .. } => true,
+            SystemIdle::Wayland(_) => false,
+        }
+    }
+
+    fn set_wake(&mut self, wake: Box<dyn Fn() + Send>) {
+        match self {
+            #[cfg(feature = "x11")]
+            SystemIdle::X11 { .. } => {}
+            SystemIdle::Wayland(wayland) => wayland.set_wake(wake),
+        }
+    }
+
+    #[cfg(feature = "x11")]
+    fn close(&mut self) {
+        if let SystemIdle::X11 {
+            display,
+            info,
+            _defer_free_info: free_info,
+            ..
+        } = self
+        {
+            free_info.free();
+            *info = ptr::null_mut();
+            // The connection closes with the last block using it
+            *display = None;
+        }
+    }
+}
@@ -8,7 +8,10 @@ use crossbeam_channel::Sender;
 use serde::{de, Deserializer};
 use serde_derive::Deserialize;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Deserializes from a name like `"right"` in the configuration, see `deserialize_mousebutton`
+/// for click events.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum MouseButton {
     Left,
     Middle,
@@ -59,6 +62,22 @@ pub fn process_events(sender: Sender<I3BarEvent>) {
         .unwrap();
 }
 
+impl MouseButton {
+    /// The name used for the button in the configuration.
+    pub fn name(self) -> &'static str {
+        match self {
+            MouseButton::Left => "left",
+            MouseButton::Middle => "middle",
+            MouseButton::Right => "right",
+            MouseButton::WheelUp => "wheel_up",
+            MouseButton::WheelDown => "wheel_down",
+            MouseButton::Forward => "forward",
+            MouseButton::Back => "back",
+            MouseButton::Unknown => "unknown",
+        }
+    }
+}
+
 fn deserialize_mousebutton<'de, D>(deserializer: D) -> Result<MouseButton, D::Error>
 where
     D: Deserializer<'de>,
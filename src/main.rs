@@ -1,31 +1,42 @@
 #[macro_use]
 extern crate serde_json;
+#[cfg(feature = "pulseaudio")]
 use libpulse_binding as pulse;
 
 #[macro_use]
 mod de;
 #[macro_use]
 mod util;
+mod alert;
 pub mod blocks;
 mod config;
 mod errors;
 mod icons;
+mod idle;
 mod input;
+mod notify;
 mod scheduler;
+mod signals;
+mod stats;
 mod subprocess;
 mod themes;
+mod threshold;
+mod wayland_idle;
 mod widget;
 mod widgets;
+#[cfg(feature = "x11")]
+mod x11_shared;
 
 #[cfg(feature = "profiling")]
 use cpuprofiler::PROFILER;
 
 use std::collections::HashMap;
 use std::ops::DerefMut;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use clap::{crate_authors, crate_description, crate_version, App, Arg, ArgMatches};
 use crossbeam_channel::{select, Receiver, Sender};
+use nix::sys::signal::Signal;
 
 use crate::blocks::create_block;
 use crate::blocks::Block;
@@ -33,6 +44,8 @@ use crate::config::{load_config, Config};
 use crate::errors::*;
 use crate::input::{process_events, I3BarEvent};
 use crate::scheduler::{Task, UpdateScheduler};
+use crate::signals::process_signals;
+use crate::stats::Stats;
 use crate::widget::{I3BarWidget, State};
 use crate::widgets::text::TextWidget;
 
@@ -60,6 +73,13 @@ fn main() {
                 .long("never-pause")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("stats")
+                .help("Write the timings and errors of every block's updates to this file as JSON, every few seconds")
+                .long("stats")
+                .value_name("FILE")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("one-shot")
                 .help("Print blocks once and exit")
@@ -129,7 +149,13 @@ fn run(matches: &ArgMatches) -> Result<()> {
         Some(config_path) => std::path::PathBuf::from(config_path),
         None => util::xdg_config_home().join("i3status-rust/config.toml"),
     };
-    let config = load_config(&config_path)?;
+    let mut config = load_config(&config_path)?;
+    // Off by default, so blocks aren't timed without a reason
+    let stats_path = matches.value_of("stats").map(std::path::PathBuf::from);
+    if stats_path.is_some() {
+        config.stats = Stats::enabled();
+    }
+    config.exit_on_error = matches.is_present("exit-on-error");
 
     // Update request channel
     let (tx_update_requests, rx_update_requests): (Sender<Task>, Receiver<Task>) =
@@ -210,6 +236,12 @@ fn run(matches: &ArgMatches) -> Result<()> {
 
     let mut scheduler = UpdateScheduler::new(&blocks);
 
+    // Update requests may name a block by the name given in the configuration
+    let names: HashMap<String, String> = blocks
+        .iter()
+        .filter_map(|block| Some((block.name()?.to_owned(), block.id().to_owned())))
+        .collect();
+
     let mut block_map: HashMap<String, &mut dyn Block> = HashMap::new();
 
     for block in &mut blocks {
@@ -221,10 +253,18 @@ fn run(matches: &ArgMatches) -> Result<()> {
         crossbeam_channel::unbounded();
     process_events(tx_clicks);
 
+    // Signals asking us to exit are forwarded here, so blocks can be shut down cleanly, as is
+    // the signal telling us the bar was shown again
+    let (tx_signals, rx_signals) = crossbeam_channel::unbounded();
+    process_signals(tx_signals)?;
+
     // Time to next update channel.
     // Fires immediately for first updates
     let mut ttnu = crossbeam_channel::after(Duration::from_millis(0));
 
+    const STATS_INTERVAL: Duration = Duration::from_secs(5);
+    let mut stats_written: Option<Instant> = None;
+
     let one_shot = matches.is_present("one-shot");
     loop {
         // We use the message passing concept of channel selection
@@ -240,12 +280,30 @@ fn run(matches: &ArgMatches) -> Result<()> {
             // Receive async update requests
             recv(rx_update_requests) -> request => if let Ok(req) = request {
                 // Process immediately and forget
+                let id = names.get(&req.id).unwrap_or(&req.id);
                 block_map
-                    .get_mut(&req.id)
+                    .get_mut(id)
                     .internal_error("scheduler", "could not get required block")?
                     .update()?;
                 util::print_blocks(&order, &block_map, &config)?;
             },
+            // Receive signals
+            recv(rx_signals) -> res => match res {
+                Ok(Signal::SIGCONT) => if config.refresh_on_reveal {
+                    // The bar was shown again, don't keep showing what we had when it was hidden
+                    for block in block_map.values_mut() {
+                        block.update()?;
+                    }
+                    util::print_blocks(&order, &block_map, &config)?;
+                },
+                Ok(_) => {
+                    for block in block_map.values_mut() {
+                        block.shutdown();
+                    }
+                    return Ok(());
+                }
+                Err(_) => {}
+            },
             // Receive update timer events
             recv(ttnu) -> _ => {
                 scheduler.do_scheduled_updates(&mut block_map)?;
@@ -258,6 +316,12 @@ fn run(matches: &ArgMatches) -> Result<()> {
         if let Some(time) = scheduler.time_to_next_update() {
             ttnu = crossbeam_channel::after(time)
         }
+        if let Some(ref path) = stats_path {
+            if stats_written.map_or(true, |written| written.elapsed() >= STATS_INTERVAL) {
+                config.stats.write(path)?;
+                stats_written = Some(Instant::now());
+            }
+        }
         if one_shot {
             break Ok(());
         }
@@ -0,0 +1,113 @@
+#[cfg(feature = "dbus")]
+use std::collections::HashMap;
+
+#[cfg(feature = "dbus")]
+use dbus::arg::Variant;
+#[cfg(feature = "dbus")]
+use dbus::ffidisp::{BusType, Connection};
+
+use crate::config::Config;
+use crate::errors::*;
+use crate::subprocess::spawn_child_async;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Urgency {
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    fn name(self) -> &'static str {
+        match self {
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        }
+    }
+
+    /// The urgency level of the notification spec, where 0 is low
+    #[cfg(feature = "dbus")]
+    fn level(self) -> u8 {
+        match self {
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        }
+    }
+}
+
+/// A desktop notification sent by a block. Sending it again replaces the one shown before
+/// rather than stacking up, as long as the notification daemon is reached over D-Bus.
+/// Without D-Bus, `notify-send` is run instead. Nothing is sent if `notifications` is
+/// disabled in the configuration.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    enabled: bool,
+    /// The id the daemon gave the last notification, 0 before the first
+    #[cfg(feature = "dbus")]
+    id: u32,
+}
+
+impl Notification {
+    pub fn new(config: &Config) -> Self {
+        Notification {
+            enabled: config.notifications,
+            #[cfg(feature = "dbus")]
+            id: 0,
+        }
+    }
+
+    pub fn notify(&mut self, summary: &str, body: &str, urgency: Urgency, icon: &str) {
+        if !self.enabled {
+            return;
+        }
+        #[cfg(feature = "dbus")]
+        match notify_dbus(self.id, summary, body, urgency, icon) {
+            Ok(id) => {
+                self.id = id;
+                return;
+            }
+            Err(e) => eprintln!("{}, falling back to notify-send", e),
+        }
+        if let Err(e) = notify_send(summary, body, urgency, icon) {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+/// Calls org.freedesktop.Notifications.Notify, returning the id of the notification.
+#[cfg(feature = "dbus")]
+fn notify_dbus(
+    replaces: u32,
+    summary: &str,
+    body: &str,
+    urgency: Urgency,
+    icon: &str,
+) -> Result<u32> {
+    let con = Connection::get_private(BusType::Session)
+        .internal_error("notify", "failed to establish D-Bus connection")?;
+    let mut hints = HashMap::new();
+    hints.insert("urgency", Variant(urgency.level()));
+    let msg = dbus::Message::new_method_call(
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+        "Notify",
+    )
+    .internal_error("notify", "failed to create D-Bus message")?
+    .append3("i3status-rs", replaces, icon)
+    .append3(summary, body, Vec::<String>::new())
+    // An expiration timeout of -1 leaves it to the daemon
+    .append2(hints, -1i32);
+    con.send_with_reply_and_block(msg, 1000)
+        .internal_error("notify", "failed to send the notification over D-Bus")?
+        .read1()
+        .internal_error("notify", "failed to read the notification id")
+}
+
+fn notify_send(summary: &str, body: &str, urgency: Urgency, icon: &str) -> Result<()> {
+    let mut args = vec!["--app-name", "i3status-rs", "--urgency", urgency.name()];
+    if !icon.is_empty() {
+        args.extend_from_slice(&["--icon", icon]);
+    }
+    args.extend_from_slice(&["--", summary, body]);
+    spawn_child_async("notify-send", &args).internal_error("notify", "failed to run notify-send")
+}
@@ -42,26 +42,52 @@ impl cmp::Ord for Task {
 
 pub struct UpdateScheduler {
     schedule: BinaryHeap<Task>,
+    /// Renders of the blocks with a render interval, which are kept apart from their updates
+    renders: BinaryHeap<Task>,
+    /// Updates in a row which asked for a retry, by block
+    retries: HashMap<String, u32>,
 }
 
 impl UpdateScheduler {
     pub fn new(blocks: &[Box<dyn Block>]) -> UpdateScheduler {
         let mut schedule = BinaryHeap::new();
 
+        let mut renders = BinaryHeap::new();
+
         let now = Instant::now();
         for block in blocks.iter() {
             schedule.push(Task {
                 id: String::from(block.id()),
                 update_time: now,
             });
+            // The first update renders the block anyway
+            if let Some(interval) = block.render_interval() {
+                renders.push(Task {
+                    id: String::from(block.id()),
+                    update_time: now + interval,
+                });
+            }
+        }
+
+        UpdateScheduler {
+            schedule,
+            renders,
+            retries: HashMap::new(),
         }
+    }
 
-        UpdateScheduler { schedule }
+    /// The time of the next update or render, whichever comes first.
+    fn next_time(&self) -> Option<Instant> {
+        let update = self.schedule.peek().map(|task| task.update_time);
+        let render = self.renders.peek().map(|task| task.update_time);
+        match (update, render) {
+            (Some(update), Some(render)) => Some(cmp::min(update, render)),
+            (update, render) => update.or(render),
+        }
     }
 
     pub fn time_to_next_update(&self) -> Option<Duration> {
-        if let Some(peeked) = self.schedule.peek() {
-            let next_update = peeked.update_time;
+        if let Some(next_update) = self.next_time() {
             let now = Instant::now();
 
             if next_update > now {
@@ -78,19 +104,20 @@ impl UpdateScheduler {
         &mut self,
         block_map: &mut HashMap<String, &mut dyn Block>,
     ) -> Result<()> {
-        let t = self
-            .schedule
-            .pop()
+        let next_time = self
+            .next_time()
             .internal_error("scheduler", "schedule is empty")?;
-        let mut tasks_next = vec![t.clone()];
-
-        while !self.schedule.is_empty()
-            && t.update_time
-                == self
-                    .schedule
-                    .peek()
-                    .internal_error("scheduler", "schedule is empty")?
-                    .update_time
+
+        let now = Instant::now();
+        if next_time > now {
+            thread::sleep(next_time - now);
+        }
+
+        let mut tasks_next = Vec::new();
+        while self
+            .schedule
+            .peek()
+            .map_or(false, |task| task.update_time == next_time)
         {
             tasks_next.push(
                 self.schedule
@@ -98,10 +125,17 @@ impl UpdateScheduler {
                     .internal_error("scheduler", "schedule is empty")?,
             )
         }
-
-        let now = Instant::now();
-        if t.update_time > now {
-            thread::sleep(t.update_time - now);
+        let mut renders_next = Vec::new();
+        while self
+            .renders
+            .peek()
+            .map_or(false, |task| task.update_time == next_time)
+        {
+            renders_next.push(
+                self.renders
+                    .pop()
+                    .internal_error("scheduler", "schedule is empty")?,
+            )
         }
 
         let now = Instant::now();
@@ -113,15 +147,45 @@ impl UpdateScheduler {
                 .update()?
             {
                 match dur {
-                    Update::Every(d) => self.schedule.push(Task {
-                        id: task.id,
-                        update_time: now + d,
-                    }),
-                    Update::Once => {} // do not schedule this task again
+                    Update::Every(d) => {
+                        self.retries.remove(&task.id);
+                        self.schedule.push(Task {
+                            id: task.id,
+                            update_time: now + d,
+                        })
+                    }
+                    Update::Once => {
+                        // do not schedule this task again
+                        self.retries.remove(&task.id);
+                    }
+                    Update::RetryBackoff { base, max } => {
+                        let retries = self.retries.entry(task.id.clone()).or_insert(0);
+                        let delay = base
+                            .checked_mul(2u32.saturating_pow(*retries))
+                            .map_or(max, |delay| cmp::min(delay, max));
+                        *retries = retries.saturating_add(1);
+                        self.schedule.push(Task {
+                            id: task.id,
+                            update_time: now + delay,
+                        })
+                    }
                 }
             }
         }
 
+        for task in renders_next {
+            let block = block_map
+                .get_mut(&task.id)
+                .internal_error("scheduler", "could not get required block")?;
+            block.render()?;
+            if let Some(interval) = block.render_interval() {
+                self.renders.push(Task {
+                    id: task.id,
+                    update_time: now + interval,
+                });
+            }
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,56 @@
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::thread;
+
+use crossbeam_channel::Sender;
+use nix::libc::c_int;
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::unistd;
+
+use crate::errors::*;
+
+/// Write end of the pipe used to get signals out of the handler and into a thread.
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn forward_signal(signal: c_int) {
+    // Only async-signal-safe calls are allowed here, so just write the number down.
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        unistd::write(fd, &[signal as u8]).ok();
+    }
+}
+
+/// Forwards the signals that ask us to exit to the main loop, so that blocks can be shut
+/// down cleanly rather than relying on `Drop` during an abrupt exit. `SIGCONT`, which the bar
+/// sends when it is shown again after pausing us, is forwarded too.
+pub fn process_signals(sender: Sender<Signal>) -> Result<()> {
+    let (read_fd, write_fd) =
+        unistd::pipe().internal_error("signals", "failed to create signal pipe")?;
+    SIGNAL_PIPE.store(write_fd, Ordering::Relaxed);
+
+    let action = SigAction::new(
+        SigHandler::Handler(forward_signal),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    for &signal in &[Signal::SIGTERM, Signal::SIGINT, Signal::SIGCONT] {
+        unsafe { sigaction(signal, &action) }
+            .internal_error("signals", "failed to install signal handler")?;
+    }
+
+    thread::Builder::new()
+        .name("signals".into())
+        .spawn(move || {
+            let mut buf = [0u8; 1];
+            while let Ok(1) = unistd::read(read_fd, &mut buf) {
+                if let Ok(signal) = Signal::try_from(c_int::from(buf[0])) {
+                    if sender.send(signal).is_err() {
+                        break;
+                    }
+                }
+            }
+        })
+        .internal_error("signals", "failed to start signal thread")?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde_json::value::Value;
+
+use crate::errors::*;
+
+/// Timings and error counts of the blocks, kept when `--stats` is given.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// `None` when disabled, so nothing is measured
+    blocks: Option<Arc<Mutex<Vec<BlockStats>>>>,
+}
+
+#[derive(Debug, Default)]
+struct BlockStats {
+    name: String,
+    updates: u64,
+    errors: u64,
+    last_duration: Duration,
+    max_duration: Duration,
+    total_duration: Duration,
+    last_success: Option<DateTime<Local>>,
+    last_error: Option<String>,
+}
+
+/// Records the updates of one block.
+#[derive(Debug, Clone)]
+pub struct BlockStatsHandle {
+    blocks: Arc<Mutex<Vec<BlockStats>>>,
+    index: usize,
+}
+
+impl Stats {
+    pub fn enabled() -> Self {
+        Stats {
+            blocks: Some(Arc::new(Mutex::new(Vec::new()))),
+        }
+    }
+
+    /// Adds a block, in the order they are shown in. `None` if stats are disabled.
+    pub fn register(&self, name: &str) -> Option<BlockStatsHandle> {
+        let blocks = self.blocks.as_ref()?;
+        let mut stats = blocks.lock().expect("stats mutex poisoned");
+        stats.push(BlockStats {
+            name: name.to_owned(),
+            ..Default::default()
+        });
+        Some(BlockStatsHandle {
+            blocks: blocks.clone(),
+            index: stats.len() - 1,
+        })
+    }
+
+    /// Writes the stats of all blocks to `path` as JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let blocks = match self.blocks {
+            Some(ref blocks) => blocks,
+            None => return Ok(()),
+        };
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let stats: Vec<Value> = blocks
+            .lock()
+            .expect("stats mutex poisoned")
+            .iter()
+            .map(|block| {
+                json!({
+                    "block": block.name,
+                    "updates": block.updates,
+                    "errors": block.errors,
+                    "last_update_ms": millis(block.last_duration),
+                    "max_update_ms": millis(block.max_duration),
+                    "mean_update_ms": if block.updates > 0 {
+                        millis(block.total_duration) / block.updates as f64
+                    } else {
+                        0.0
+                    },
+                    "last_success": block.last_success.map(|time| time.to_rfc3339()),
+                    "last_error": block.last_error,
+                })
+            })
+            .collect();
+        let json = serde_json::to_string_pretty(&stats)
+            .internal_error("stats", "failed to serialize stats")?;
+        fs::write(path, json).internal_error("stats", "failed to write stats")
+    }
+}
+
+impl BlockStatsHandle {
+    /// Records an update which took `duration`, with the error if it failed.
+    pub fn record(&self, duration: Duration, error: Option<String>) {
+        let mut blocks = self.blocks.lock().expect("stats mutex poisoned");
+        let block = &mut blocks[self.index];
+        block.updates += 1;
+        block.last_duration = duration;
+        block.max_duration = block.max_duration.max(duration);
+        block.total_duration += duration;
+        match error {
+            Some(error) => {
+                block.errors += 1;
+                block.last_error = Some(error);
+            }
+            None => block.last_success = Some(Local::now()),
+        }
+    }
+}
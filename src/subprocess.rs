@@ -1,4 +1,5 @@
-use std::io;
+use std::env;
+use std::io::{self, Write};
 use std::process::{Command, Stdio};
 use std::thread;
 
@@ -16,3 +17,90 @@ pub fn spawn_child_async(name: &str, args: &[&str]) -> io::Result<()> {
         .unwrap();
     Ok(())
 }
+
+/// Runs `command` with `sh -c` in the background, with `env` added to its environment.
+pub fn spawn_shell_async(command: &str, env: &[(String, String)]) -> io::Result<()> {
+    let mut child = Command::new("sh")
+        .args(&["-c", command])
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()?;
+    thread::Builder::new()
+        .name("subprocess".into())
+        .spawn(move || child.wait())
+        .unwrap();
+    Ok(())
+}
+
+/// Runs `command` in a new terminal window, which stays open until Enter is pressed so the
+/// output can be read.
+///
+/// `template` is the terminal command line, e.g. `"alacritty --class floating -e {command}"`.
+/// `{command}` is replaced by the command to run, or, if it is missing, the command is
+/// appended. Without a template `$TERMINAL -e` is used, falling back to `xterm`.
+pub fn spawn_in_terminal(template: Option<&str>, command: &str) -> io::Result<()> {
+    let script = format!(
+        "{}\nprintf '\\n[exited with %s, press Enter to close]' \"$?\"\nread -r _",
+        command
+    );
+    let shell = ["sh", "-c", &script];
+
+    let default_template;
+    let template = match template {
+        Some(template) => template,
+        None => {
+            let terminal = std::env::var("TERMINAL").unwrap_or_else(|_| "xterm".to_owned());
+            default_template = format!("{} -e {{command}}", terminal);
+            &default_template
+        }
+    };
+
+    // Build the arguments directly instead of going through a shell, so the command
+    // doesn't need another level of quoting.
+    let mut args: Vec<&str> = Vec::new();
+    let mut substituted = false;
+    for word in template.split_whitespace() {
+        if word == "{command}" {
+            args.extend(&shell);
+            substituted = true;
+        } else {
+            args.push(word);
+        }
+    }
+    if !substituted {
+        args.extend(&shell);
+    }
+
+    match args.split_first() {
+        Some((name, args)) => spawn_child_async(name, args),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "empty terminal command",
+        )),
+    }
+}
+
+/// Copies `text` to the clipboard by passing it to `command` on stdin, which is run by `sh`.
+/// Without a command `wl-copy` is used under Wayland and `xclip` otherwise.
+pub fn copy_to_clipboard(command: Option<&str>, text: &str) -> io::Result<()> {
+    let command = match command {
+        Some(command) => command,
+        None if env::var_os("WAYLAND_DISPLAY").is_some() => "wl-copy",
+        None => "xclip -selection clipboard",
+    };
+    let mut child = Command::new("sh")
+        .args(&["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    // xclip keeps running to serve the selection, so don't wait for it here
+    thread::Builder::new()
+        .name("subprocess".into())
+        .spawn(move || child.wait())
+        .unwrap();
+    Ok(())
+}
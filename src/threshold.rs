@@ -0,0 +1,416 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use serde::de::{self, Deserialize, Deserializer};
+
+/// The reading a block checks its thresholds against.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub value: f64,
+    /// The value at the previous update, if there was one
+    pub previous: Option<f64>,
+}
+
+/// A threshold given as a number, which is reached when the value is above it, or as an
+/// expression over the value and how it changed since the previous update.
+#[derive(Debug, Clone)]
+pub enum Threshold {
+    Above(f64),
+    Expression(Expr),
+}
+
+impl Threshold {
+    /// Reached when the value is `threshold` or more.
+    pub fn at_least(threshold: f64) -> Self {
+        Threshold::compare(Op::Ge, threshold)
+    }
+
+    /// Reached when the value is `threshold` or less.
+    pub fn at_most(threshold: f64) -> Self {
+        Threshold::compare(Op::Le, threshold)
+    }
+
+    fn compare(op: Op, threshold: f64) -> Self {
+        Threshold::Expression(Expr::Binary(
+            op,
+            Box::new(Expr::Var(Var::Value)),
+            Box::new(Expr::Num(threshold)),
+        ))
+    }
+
+    pub fn reached(&self, sample: Sample) -> bool {
+        match self {
+            Threshold::Above(threshold) => sample.value > *threshold,
+            Threshold::Expression(expr) => expr.eval(sample) != 0.0,
+        }
+    }
+}
+
+impl Default for Threshold {
+    fn default() -> Self {
+        Threshold::Above(0.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Threshold {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ThresholdVisitor;
+
+        impl<'de> de::Visitor<'de> for ThresholdVisitor {
+            type Value = Threshold;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a number or an expression string")
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Threshold::Above(value as f64))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Threshold::Above(value as f64))
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Threshold::Above(value))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Expr::parse(value)
+                    .map(Threshold::Expression)
+                    .map_err(|e| E::custom(format!("invalid threshold `{}`: {}", value, e)))
+            }
+        }
+
+        deserializer.deserialize_any(ThresholdVisitor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Var {
+    Value,
+    Previous,
+    Delta,
+    Trend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// A parsed threshold expression. Every expression evaluates to a number, with comparisons
+/// and logical operators giving 1 for true and 0 for false.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(f64),
+    Var(Var),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    const OPS: &[&str] = &[
+        "||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "*", "/",
+    ];
+
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<CharIndices> = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            chars.next();
+            tokens.push(if c == '(' { Token::Open } else { Token::Close });
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let number = &input[start..end];
+            tokens.push(Token::Num(
+                number
+                    .parse()
+                    .map_err(|_| format!("invalid number `{}`", number))?,
+            ));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Ident(input[start..end].to_owned()));
+        } else {
+            let op = OPS
+                .iter()
+                .find(|op| input[start..].starts_with(*op))
+                .ok_or_else(|| format!("unexpected `{}`", c))?;
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push(Token::Op(op));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_op(&self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    /// Parses a left associative chain of the operators in the given precedence level.
+    fn binary(
+        &mut self,
+        ops: &[(&'static str, Op)],
+        next: fn(&mut Self) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let names: Vec<&'static str> = ops.iter().map(|(name, _)| *name).collect();
+        let mut lhs = next(self)?;
+        while let Some(name) = self.peek_op(&names) {
+            self.pos += 1;
+            let op = ops.iter().find(|(n, _)| *n == name).unwrap().1;
+            let rhs = next(self)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        self.binary(&[("||", Op::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.binary(&[("&&", Op::And)], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        self.binary(
+            &[
+                ("==", Op::Eq),
+                ("!=", Op::Ne),
+                ("<=", Op::Le),
+                (">=", Op::Ge),
+                ("<", Op::Lt),
+                (">", Op::Gt),
+            ],
+            Self::sum,
+        )
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.binary(&[("+", Op::Add), ("-", Op::Sub)], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        self.binary(&[("*", Op::Mul), ("/", Op::Div)], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.peek_op(&["!", "-"]) {
+            Some(op) => {
+                self.pos += 1;
+                let operand = Box::new(self.unary()?);
+                Ok(if op == "!" {
+                    Expr::Not(operand)
+                } else {
+                    Expr::Neg(operand)
+                })
+            }
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| "unexpected end of expression".to_owned())?;
+        self.pos += 1;
+        match token {
+            Token::Num(n) => Ok(Expr::Num(n)),
+            Token::Ident(name) => match name.as_str() {
+                "value" => Ok(Expr::Var(Var::Value)),
+                "previous" => Ok(Expr::Var(Var::Previous)),
+                "delta" => Ok(Expr::Var(Var::Delta)),
+                "trend" => Ok(Expr::Var(Var::Trend)),
+                "up" | "true" => Ok(Expr::Num(1.0)),
+                "down" => Ok(Expr::Num(-1.0)),
+                "flat" | "false" => Ok(Expr::Num(0.0)),
+                _ => Err(format!("unknown variable `{}`", name)),
+            },
+            Token::Open => {
+                let expr = self.or()?;
+                match self.tokens.get(self.pos) {
+                    Some(Token::Close) => {
+                        self.pos += 1;
+                        Ok(expr)
+                    }
+                    _ => Err("missing `)`".to_owned()),
+                }
+            }
+            Token::Close => Err("unexpected `)`".to_owned()),
+            Token::Op(op) => Err(format!("unexpected `{}`", op)),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(input: &str) -> Result<Expr, String> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+        };
+        let expr = parser.or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err("unexpected input after the expression".to_owned());
+        }
+        Ok(expr)
+    }
+
+    pub fn eval(&self, sample: Sample) -> f64 {
+        let bool = |b: bool| if b { 1.0 } else { 0.0 };
+        let previous = sample.previous.unwrap_or(sample.value);
+        match self {
+            Expr::Num(n) => *n,
+            Expr::Var(Var::Value) => sample.value,
+            Expr::Var(Var::Previous) => previous,
+            Expr::Var(Var::Delta) => sample.value - previous,
+            Expr::Var(Var::Trend) => match sample.value.partial_cmp(&previous) {
+                Some(Ordering::Greater) => 1.0,
+                Some(Ordering::Less) => -1.0,
+                _ => 0.0,
+            },
+            Expr::Not(operand) => bool(operand.eval(sample) == 0.0),
+            Expr::Neg(operand) => -operand.eval(sample),
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval(sample);
+                // Short circuit, like the operators usually do
+                match op {
+                    Op::Or if lhs != 0.0 => return 1.0,
+                    Op::And if lhs == 0.0 => return 0.0,
+                    _ => {}
+                }
+                let rhs = rhs.eval(sample);
+                match op {
+                    Op::Or | Op::And => bool(rhs != 0.0),
+                    Op::Eq => bool(lhs == rhs),
+                    Op::Ne => bool(lhs != rhs),
+                    Op::Lt => bool(lhs < rhs),
+                    Op::Le => bool(lhs <= rhs),
+                    Op::Gt => bool(lhs > rhs),
+                    Op::Ge => bool(lhs >= rhs),
+                    Op::Add => lhs + rhs,
+                    Op::Sub => lhs - rhs,
+                    Op::Mul => lhs * rhs,
+                    Op::Div => lhs / rhs,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::threshold::{Expr, Sample, Threshold};
+    use serde_derive::Deserialize;
+
+    fn sample(value: f64, previous: Option<f64>) -> Sample {
+        Sample { value, previous }
+    }
+
+    #[test]
+    fn test_threshold_above() {
+        #[derive(Deserialize)]
+        struct Config {
+            threshold: Threshold,
+        }
+
+        let config: Config = toml::from_str("threshold = 90").unwrap();
+        assert!(config.threshold.reached(sample(91.0, None)));
+        assert!(!config.threshold.reached(sample(90.0, None)));
+
+        let config: Config = toml::from_str("threshold = \"value > 90\"").unwrap();
+        assert!(config.threshold.reached(sample(91.0, None)));
+        assert!(toml::from_str::<Config>("threshold = \"value >\"").is_err());
+    }
+
+    #[test]
+    fn test_expr_eval() {
+        let expr = Expr::parse("value > 90 && trend == up").unwrap();
+        assert_eq!(expr.eval(sample(95.0, Some(80.0))), 1.0);
+        assert_eq!(expr.eval(sample(95.0, Some(99.0))), 0.0);
+        assert_eq!(expr.eval(sample(85.0, Some(80.0))), 0.0);
+        // Without a previous value, the trend is flat
+        assert_eq!(expr.eval(sample(95.0, None)), 0.0);
+
+        let expr = Expr::parse("!(delta < -10) || value * 2 >= 100 - previous").unwrap();
+        assert_eq!(expr.eval(sample(10.0, Some(30.0))), 0.0);
+        assert_eq!(expr.eval(sample(40.0, Some(60.0))), 1.0);
+        assert_eq!(expr.eval(sample(10.0, Some(15.0))), 1.0);
+    }
+
+    #[test]
+    fn test_expr_parse_errors() {
+        assert!(Expr::parse("value >").is_err());
+        assert!(Expr::parse("(value > 1").is_err());
+        assert!(Expr::parse("value > 1 )").is_err());
+        assert!(Expr::parse("temperature > 1").is_err());
+        assert!(Expr::parse("value = 1").is_err());
+        assert!(Expr::parse("1.2.3").is_err());
+    }
+}
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
 use std::fmt::Display;
 use std::fs::{File, OpenOptions};
 use std::io::prelude::*;
@@ -6,18 +7,25 @@ use std::io::BufReader;
 use std::path::{Path, PathBuf};
 use std::prelude::v1::String;
 use std::process::Command;
+use std::time::{Duration, Instant};
 
 use regex::Regex;
 use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
 use serde_json::value::Value;
 
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
 use crate::blocks::Block;
 use crate::config::Config;
 use crate::errors::*;
+use crate::widget::I3BarWidget;
 
 pub const USR_SHARE_PATH: &str = "/usr/share/i3status-rust";
 
-pub fn escape_pango_text(text: String) -> String {
+/// Escapes text to be shown as is in pango markup. Widgets rendering markup apply this to
+/// the text they are given, blocks only need it when building markup themselves.
+pub fn escape_markup(text: &str) -> String {
     text.chars()
         .map(|x| match x {
             '&' => "&amp;".to_string(),
@@ -29,6 +37,53 @@ pub fn escape_pango_text(text: String) -> String {
         .collect()
 }
 
+/// Shortens `text` to at most `max_width` columns, ending it with `ellipsis` if anything was
+/// cut. Wide characters, e.g. CJK or emoji, take two columns, and combining characters are
+/// kept with the character before them.
+pub fn truncate_width(text: &str, max_width: usize, ellipsis: &str) -> String {
+    if text.width() <= max_width {
+        return text.to_owned();
+    }
+    let mut available = max_width.saturating_sub(ellipsis.width());
+    let mut truncated = String::new();
+    for c in text.chars() {
+        let width = c.width().unwrap_or(0);
+        if width > available {
+            break;
+        }
+        available -= width;
+        truncated.push(c);
+    }
+    truncated.push_str(ellipsis);
+    truncated
+}
+
+/// The `max_width` columns of `text` starting at its character `offset`, as if `text` was
+/// repeated with a gap in between, for scrolling it. Text that fits is returned as is.
+pub fn scroll_width(text: &str, max_width: usize, offset: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_owned();
+    }
+    const GAP: &str = "   ";
+    let chars: Vec<char> = text.chars().chain(GAP.chars()).collect();
+    let mut available = max_width;
+    let mut scrolled = String::new();
+    for c in chars.iter().cycle().skip(offset % chars.len()) {
+        let width = c.width().unwrap_or(0);
+        if width > available {
+            break;
+        }
+        available -= width;
+        scrolled.push(*c);
+    }
+    scrolled
+}
+
+/// Wraps pango markup in a span setting its font family.
+pub fn pango_font_span(font: &str, markup: &str) -> String {
+    format!("<span font='{}'>{}</span>", escape_markup(font), markup)
+}
+
 pub fn format_speed(
     bytes_speed: u64,
     total_digits: usize,
@@ -111,6 +166,14 @@ pub fn xdg_config_home() -> PathBuf {
     PathBuf::from(&config_path)
 }
 
+pub fn xdg_data_home() -> PathBuf {
+    let data_path = std::env::var("XDG_DATA_HOME").unwrap_or(format!(
+        "{}/.local/share",
+        std::env::var("HOME").unwrap_or_else(|_| "".to_string())
+    ));
+    PathBuf::from(&data_path)
+}
+
 pub fn deserialize_file<T>(file: &str) -> Result<T>
 where
     T: DeserializeOwned,
@@ -162,6 +225,23 @@ pub fn has_command(block_name: &str, command: &str) -> Result<bool> {
     Ok(exit_status.success())
 }
 
+/// Fails unless `interval` is at least `min`, so a block can't be made to update in a busy loop.
+pub fn check_min_interval(block_name: &str, interval: Duration, min: Duration) -> Result<()> {
+    if interval < min {
+        return Err(ConfigurationError(
+            block_name.to_owned(),
+            (
+                format!(
+                    "'interval' of {:?} is too short, it has to be at least {:?}",
+                    interval, min
+                ),
+                "interval too short".to_owned(),
+            ),
+        ));
+    }
+    Ok(())
+}
+
 macro_rules! match_range {
     ($a:expr, default: ($default:expr) {$($lower:expr ; $upper:expr => $e:expr),+}) => (
         match $a {
@@ -211,6 +291,16 @@ impl PrintState {
     }
 }
 
+/// Renders a widget tagged with the id of its block. i3bar sends the instance back with
+/// click events, so clicks can be traced to a block even if its widgets have no name.
+fn with_instance(widget: &dyn I3BarWidget, block_id: &str) -> String {
+    let mut rendered = widget.get_rendered().clone();
+    if rendered.get("instance").is_none() {
+        rendered["instance"] = Value::String(block_id.to_owned());
+    }
+    rendered.to_string()
+}
+
 pub fn print_blocks(
     order: &[String],
     block_map: &HashMap<String, &mut dyn Block>,
@@ -263,7 +353,7 @@ pub fn print_blocks(
             if state.has_predecessor { "," } else { "" },
             separator.to_string()
         );
-        print!("{}", first.to_string());
+        print!("{}", with_instance(first, block_id));
         state.set_last_bg(color.to_owned());
         state.set_predecessor(true);
 
@@ -271,7 +361,7 @@ pub fn print_blocks(
             print!(
                 "{}{}",
                 if state.has_predecessor { "," } else { "" },
-                widget.to_string()
+                with_instance(*widget, block_id)
             );
             state.set_last_bg(String::from(
                 widget.get_rendered()["background"]
@@ -334,6 +424,212 @@ pub fn format_percent_bar(percent: f32) -> String {
         .collect()
 }
 
+/// Picks the glyph for `percent` from `glyphs`, which split 0-100 into equal bands.
+pub fn format_percent_gauge(percent: f32, glyphs: &[String]) -> String {
+    if glyphs.is_empty() {
+        return String::new();
+    }
+    let percent = percent.min(100.0).max(0.0);
+    let index = ((percent / 100.0 * glyphs.len() as f32) as usize).min(glyphs.len() - 1);
+    glyphs[index].clone()
+}
+
+/// The glyphs for `{gauge}` placeholders when a block doesn't set its `gauge` option.
+pub fn default_gauge() -> Vec<String> {
+    ["🌑", "🌒", "🌓", "🌔", "🌕"]
+        .iter()
+        .map(|s| (*s).to_owned())
+        .collect()
+}
+
+/// A linear transform applied to a block's raw reading before it is displayed, computed as
+/// `value * scale / divisor + offset`. Blocks take it as a `scaling` table, e.g.
+/// `scaling = { scale = 100, divisor = 4 }`.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct Scaling {
+    #[serde(default = "Scaling::default_factor")]
+    pub scale: f64,
+    #[serde(default = "Scaling::default_factor")]
+    pub divisor: f64,
+    #[serde(default)]
+    pub offset: f64,
+}
+
+impl Scaling {
+    fn default_factor() -> f64 {
+        1.0
+    }
+
+    pub fn validated(self, block_name: &str) -> Result<Self> {
+        if self.divisor == 0.0 {
+            return Err(BlockError(
+                block_name.to_owned(),
+                "scaling divisor must not be zero".to_owned(),
+            ));
+        }
+        Ok(self)
+    }
+
+    pub fn apply(&self, value: f64) -> f64 {
+        value * self.scale / self.divisor + self.offset
+    }
+}
+
+impl Default for Scaling {
+    fn default() -> Self {
+        Scaling {
+            scale: Scaling::default_factor(),
+            divisor: Scaling::default_factor(),
+            offset: 0.0,
+        }
+    }
+}
+
+/// How values are brought to the number of decimals they are shown with. Blocks take it as a
+/// `rounding` option, so that e.g. a nearly full disk can be shown as 99% rather than 100%.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rounding {
+    Round,
+    Floor,
+    Ceil,
+}
+
+impl Rounding {
+    /// Formats `value` with `precision` decimals.
+    pub fn format(self, value: f64, precision: usize) -> String {
+        // Values like 0.29 are slightly below themselves once scaled, so allow for some error
+        const EPSILON: f64 = 1e-9;
+        let factor = 10f64.powi(precision as i32);
+        let value = match self {
+            Rounding::Round => value,
+            Rounding::Floor => (value * factor + EPSILON).floor() / factor,
+            Rounding::Ceil => (value * factor - EPSILON).ceil() / factor,
+        };
+        format!("{:.*}", precision, value)
+    }
+}
+
+/// Whether byte sizes are counted in powers of 1000 (MB, GB) or 1024 (MiB, GiB). Blocks
+/// take it as a `unit_base` option of `1000` or `1024`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "u64")]
+pub enum UnitBase {
+    Decimal,
+    Binary,
+}
+
+impl UnitBase {
+    pub fn factor(self) -> f64 {
+        match self {
+            UnitBase::Decimal => 1000.0,
+            UnitBase::Binary => 1024.0,
+        }
+    }
+
+    /// `bytes` in units of `factor^power` bytes, e.g. GB or GiB for a power of 3.
+    pub fn bytes_in(self, bytes: f64, power: i32) -> f64 {
+        bytes / self.factor().powi(power)
+    }
+
+    /// The name of the unit of `factor^power` bytes, for powers from 0 to 4.
+    pub fn label(self, power: i32) -> &'static str {
+        const DECIMAL: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+        const BINARY: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let labels = match self {
+            UnitBase::Decimal => &DECIMAL,
+            UnitBase::Binary => &BINARY,
+        };
+        labels[power.max(0).min(4) as usize]
+    }
+}
+
+impl TryFrom<u64> for UnitBase {
+    type Error = String;
+
+    fn try_from(base: u64) -> ::std::result::Result<Self, Self::Error> {
+        match base {
+            1000 => Ok(UnitBase::Decimal),
+            1024 => Ok(UnitBase::Binary),
+            _ => Err(format!("unit_base must be 1000 or 1024, got {}", base)),
+        }
+    }
+}
+
+impl Default for UnitBase {
+    fn default() -> Self {
+        UnitBase::Binary
+    }
+}
+
+impl Default for Rounding {
+    fn default() -> Self {
+        Rounding::Round
+    }
+}
+
+/// The last samples of an ever increasing counter, e.g. bytes received, over a sliding
+/// window. Rates are averaged over the whole window, or over what has been seen of it so far,
+/// so they don't jump around at short update intervals.
+#[derive(Debug, Clone)]
+pub struct CounterWindow {
+    window: Duration,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl CounterWindow {
+    /// A window of zero, or one shorter than the update interval, makes rates the change
+    /// since the previous sample.
+    pub fn new(window: Duration) -> Self {
+        CounterWindow {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Adds the counter's value at `time`. A counter which went back, e.g. because it was
+    /// reset, starts the window over.
+    pub fn push(&mut self, time: Instant, value: u64) {
+        if self.samples.back().map_or(false, |&(_, last)| value < last) {
+            self.samples.clear();
+        }
+        self.samples.push_back((time, value));
+        // The oldest sample kept starts the window, so it may be older than the window
+        if let Some(start) = time.checked_sub(self.window) {
+            while self.samples.len() > 2 && self.samples[1].0 <= start {
+                self.samples.pop_front();
+            }
+        }
+    }
+
+    /// Forgets all samples, e.g. when the counter now counts something else.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// How much the counter increased over the window, and in how much time. `None` until
+    /// there are two samples.
+    pub fn delta(&self) -> Option<(u64, Duration)> {
+        let (first_time, first) = self.samples.front()?;
+        let (last_time, last) = self.samples.back()?;
+        if self.samples.len() < 2 {
+            return None;
+        }
+        Some((last - first, last_time.duration_since(*first_time)))
+    }
+
+    /// The increase per second over the window, or 0 until there are two samples.
+    pub fn rate(&self) -> f64 {
+        match self.delta() {
+            Some((delta, time)) if time > Duration::from_secs(0) => {
+                delta as f64 / time.as_secs_f64()
+            }
+            _ => 0.0,
+        }
+    }
+}
+
 // TODO: Allow for other non-additive tints
 pub fn add_colors(a: &str, b: &str) -> ::std::result::Result<String, Box<dyn std::error::Error>> {
     let (r_a, g_a, b_a, a_a) = color_from_rgba(a)?;
@@ -452,7 +748,12 @@ macro_rules! if_debug {
 
 #[cfg(test)]
 mod tests {
-    use crate::util::{color_from_rgba, has_command};
+    use crate::util::{
+        check_min_interval, color_from_rgba, escape_markup, format_percent_gauge, has_command,
+        pango_font_span, scroll_width, truncate_width, CounterWindow, Rounding, UnitBase,
+    };
+    use std::convert::TryFrom;
+    use std::time::{Duration, Instant};
 
     #[test]
     // we assume sh is always available
@@ -471,6 +772,15 @@ mod tests {
         let has_command = has_command.unwrap();
         assert!(!has_command)
     }
+    #[test]
+    fn test_check_min_interval() {
+        let min = Duration::from_secs(1);
+        assert!(check_min_interval("none", Duration::from_secs(1), min).is_ok());
+        assert!(check_min_interval("none", Duration::from_secs(60), min).is_ok());
+        assert!(check_min_interval("none", Duration::from_millis(500), min).is_err());
+        assert!(check_min_interval("none", Duration::from_secs(0), min).is_err());
+    }
+
     #[test]
     fn test_color_from_rgba() {
         let valid_rgb = "#AABBCC"; //rgb
@@ -495,4 +805,108 @@ mod tests {
         let rgba = color_from_rgba(invalid);
         assert!(rgba.is_err());
     }
+
+    #[test]
+    fn test_escape_markup() {
+        assert_eq!(escape_markup("Tom & Jerry <3"), "Tom &amp; Jerry &lt;3");
+        assert_eq!(escape_markup("it's"), "it&#39;s");
+    }
+
+    #[test]
+    fn test_truncate_width() {
+        assert_eq!(truncate_width("short", 5, "…"), "short");
+        assert_eq!(truncate_width("a long title", 6, "…"), "a lon…");
+        assert_eq!(truncate_width("a long title", 6, ""), "a long");
+        // Wide characters take two columns, and are never split
+        assert_eq!(truncate_width("日本語の歌", 5, "…"), "日本…");
+        assert_eq!(truncate_width("日本語の歌", 6, "…"), "日本…");
+        assert_eq!(truncate_width("🎵🎶🎵 song", 5, "…"), "🎵🎶…");
+        // Combining accents stay with their letter
+        assert_eq!(truncate_width("cafe\u{301} noir", 5, "…"), "cafe\u{301}…");
+        assert_eq!(truncate_width("abc", 0, "…"), "…");
+    }
+
+    #[test]
+    fn test_scroll_width() {
+        assert_eq!(scroll_width("short", 10, 3), "short");
+        assert_eq!(scroll_width("abcdef", 4, 0), "abcd");
+        assert_eq!(scroll_width("abcdef", 4, 4), "ef  ");
+        // Wraps around after the gap
+        assert_eq!(scroll_width("abcdef", 4, 8), " abc");
+        assert_eq!(scroll_width("abcdef", 4, 9), "abcd");
+        // Wide characters don't overflow the width
+        assert_eq!(scroll_width("日本語です", 5, 0), "日本");
+    }
+
+    #[test]
+    fn test_pango_font_span() {
+        assert_eq!(
+            pango_font_span("Font Awesome 5 Free", "5 &amp; 6"),
+            "<span font='Font Awesome 5 Free'>5 &amp; 6</span>"
+        );
+        assert_eq!(pango_font_span("a'b", "x"), "<span font='a&#39;b'>x</span>");
+    }
+
+    #[test]
+    fn test_counter_window() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut window = CounterWindow::new(Duration::from_secs(5));
+        window.push(at(0), 100);
+        assert_eq!(window.rate(), 0.0);
+        // Warming up, the rate is over the samples there are
+        window.push(at(1), 200);
+        assert_eq!(window.rate(), 100.0);
+        window.push(at(2), 200);
+        assert_eq!(window.rate(), 50.0);
+        for secs in 3..=10 {
+            window.push(at(secs), 200 + (secs - 2) * 10);
+        }
+        assert_eq!(window.delta(), Some((50, Duration::from_secs(5))));
+        assert_eq!(window.rate(), 10.0);
+        // A reset counter starts over
+        window.push(at(11), 5);
+        assert_eq!(window.rate(), 0.0);
+
+        let mut window = CounterWindow::new(Duration::from_secs(0));
+        window.push(at(0), 0);
+        window.push(at(1), 10);
+        window.push(at(2), 40);
+        assert_eq!(window.rate(), 30.0);
+    }
+
+    #[test]
+    fn test_format_percent_gauge() {
+        let glyphs: Vec<String> = vec!["a".into(), "b".into(), "c".into(), "d".into()];
+        assert_eq!(format_percent_gauge(0.0, &glyphs), "a");
+        assert_eq!(format_percent_gauge(24.9, &glyphs), "a");
+        assert_eq!(format_percent_gauge(25.0, &glyphs), "b");
+        assert_eq!(format_percent_gauge(99.0, &glyphs), "d");
+        assert_eq!(format_percent_gauge(100.0, &glyphs), "d");
+        assert_eq!(format_percent_gauge(150.0, &glyphs), "d");
+        assert_eq!(format_percent_gauge(-5.0, &glyphs), "a");
+        assert_eq!(format_percent_gauge(50.0, &[]), "");
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(Rounding::Round.format(99.96, 1), "100.0");
+        assert_eq!(Rounding::Floor.format(99.96, 1), "99.9");
+        assert_eq!(Rounding::Ceil.format(99.91, 1), "100.0");
+        assert_eq!(Rounding::Floor.format(99.9, 0), "99");
+        assert_eq!(Rounding::Ceil.format(0.1, 0), "1");
+        // Exact values are left alone
+        assert_eq!(Rounding::Floor.format(0.29, 2), "0.29");
+        assert_eq!(Rounding::Ceil.format(0.29, 2), "0.29");
+    }
+
+    #[test]
+    fn test_unit_base() {
+        assert_eq!(UnitBase::Decimal.bytes_in(2_500_000_000.0, 3), 2.5);
+        assert_eq!(UnitBase::Binary.bytes_in(3.0 * 1024.0 * 1024.0, 2), 3.0);
+        assert_eq!(UnitBase::Decimal.label(3), "GB");
+        assert_eq!(UnitBase::Binary.label(3), "GiB");
+        assert_eq!(UnitBase::try_from(1000), Ok(UnitBase::Decimal));
+        assert!(UnitBase::try_from(1042).is_err());
+    }
 }
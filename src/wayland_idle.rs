@@ -0,0 +1,254 @@
+//! A minimal Wayland client for the `ext-idle-notify-v1` protocol, which is all the idle
+//! module needs from the compositor. It speaks the wire protocol directly, only binding
+//! `wl_seat` and `ext_idle_notifier_v1`.
+
+use std::env;
+use std::io::{BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::errors::*;
+
+// The ids of the objects we create, in the order they are created
+const DISPLAY: u32 = 1;
+const REGISTRY: u32 = 2;
+const CALLBACK: u32 = 3;
+const SEAT: u32 = 4;
+const NOTIFIER: u32 = 5;
+const NOTIFICATION: u32 = 6;
+
+/// Idle time is only known once it reaches this, earlier it is reported as 0.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Follows whether the seat is idle, from the `idled` and `resumed` events of the compositor.
+pub struct WaylandIdle {
+    block_name: &'static str,
+    state: Arc<Mutex<IdleState>>,
+}
+
+#[derive(Default)]
+struct IdleState {
+    /// When input last happened, if the seat is idle
+    idle_since: Option<Instant>,
+    /// Why the connection was lost
+    error: Option<String>,
+    /// Called when the seat becomes idle or active
+    wake: Option<Box<dyn Fn() + Send>>,
+}
+
+struct Message {
+    object: u32,
+    opcode: u16,
+    args: Vec<u8>,
+}
+
+impl Message {
+    fn uint(&self, offset: usize) -> u32 {
+        self.args
+            .get(offset..offset + 4)
+            .map_or(0, |b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// The string at `offset`, and the offset after it.
+    fn string(&self, offset: usize) -> (String, usize) {
+        let len = self.uint(offset) as usize;
+        let start = offset + 4;
+        let text = self
+            .args
+            .get(start..start + len.saturating_sub(1))
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+            .unwrap_or_default();
+        (text, start + ((len + 3) & !3))
+    }
+}
+
+fn socket_path() -> Option<PathBuf> {
+    let display = env::var_os("WAYLAND_DISPLAY").unwrap_or_else(|| "wayland-0".into());
+    let display = PathBuf::from(display);
+    if display.is_absolute() {
+        Some(display)
+    } else {
+        env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join(display))
+    }
+}
+
+fn send(
+    block_name: &str,
+    stream: &mut UnixStream,
+    object: u32,
+    opcode: u16,
+    args: &[u8],
+) -> Result<()> {
+    let mut message = Vec::with_capacity(8 + args.len());
+    message.extend_from_slice(&object.to_ne_bytes());
+    message.extend_from_slice(&((((8 + args.len()) as u32) << 16) | opcode as u32).to_ne_bytes());
+    message.extend_from_slice(args);
+    stream
+        .write_all(&message)
+        .block_error(block_name, "failed to write to the Wayland socket")
+}
+
+fn push_string(args: &mut Vec<u8>, text: &str) {
+    args.extend_from_slice(&(text.len() as u32 + 1).to_ne_bytes());
+    args.extend_from_slice(text.as_bytes());
+    args.push(0);
+    while args.len() % 4 != 0 {
+        args.push(0);
+    }
+}
+
+fn receive(block_name: &str, reader: &mut impl Read) -> Result<Message> {
+    let mut header = [0; 8];
+    reader
+        .read_exact(&mut header)
+        .block_error(block_name, "failed to read from the Wayland socket")?;
+    let object = u32::from_ne_bytes([header[0], header[1], header[2], header[3]]);
+    let size_opcode = u32::from_ne_bytes([header[4], header[5], header[6], header[7]]);
+    let mut args = vec![0; ((size_opcode >> 16) as usize).saturating_sub(8)];
+    reader
+        .read_exact(&mut args)
+        .block_error(block_name, "failed to read from the Wayland socket")?;
+    let message = Message {
+        object,
+        opcode: size_opcode as u16,
+        args,
+    };
+    // wl_display.error
+    if message.object == DISPLAY && message.opcode == 0 {
+        let (text, _) = message.string(8);
+        return Err(BlockError(
+            block_name.to_owned(),
+            format!("Wayland protocol error: {}", text),
+        ));
+    }
+    Ok(message)
+}
+
+/// wl_registry.bind of the global `name` as `id`.
+fn bind(
+    block_name: &str,
+    stream: &mut UnixStream,
+    name: u32,
+    interface: &str,
+    id: u32,
+) -> Result<()> {
+    let mut args = name.to_ne_bytes().to_vec();
+    push_string(&mut args, interface);
+    args.extend_from_slice(&1u32.to_ne_bytes());
+    args.extend_from_slice(&id.to_ne_bytes());
+    send(block_name, stream, REGISTRY, 0, &args)
+}
+
+impl WaylandIdle {
+    pub fn new(block_name: &'static str) -> Result<Self> {
+        let path = socket_path().block_error(block_name, "XDG_RUNTIME_DIR is not set")?;
+        let mut stream = UnixStream::connect(&path).block_error(
+            block_name,
+            &format!("failed to connect to Wayland at {}", path.display()),
+        )?;
+        let mut reader = BufReader::new(
+            stream
+                .try_clone()
+                .block_error(block_name, "failed to clone the Wayland socket")?,
+        );
+
+        // wl_display.get_registry, then wl_display.sync to know when all globals were sent
+        send(block_name, &mut stream, DISPLAY, 1, &REGISTRY.to_ne_bytes())?;
+        send(block_name, &mut stream, DISPLAY, 0, &CALLBACK.to_ne_bytes())?;
+        let mut seat = None;
+        let mut notifier = None;
+        loop {
+            let message = receive(block_name, &mut reader)?;
+            match (message.object, message.opcode) {
+                (REGISTRY, 0) => {
+                    let (interface, _) = message.string(4);
+                    match interface.as_str() {
+                        "wl_seat" if seat.is_none() => seat = Some(message.uint(0)),
+                        "ext_idle_notifier_v1" => notifier = Some(message.uint(0)),
+                        _ => {}
+                    }
+                }
+                (CALLBACK, 0) => break,
+                _ => {}
+            }
+        }
+        let seat = seat.block_error(block_name, "the compositor has no seat")?;
+        let notifier = notifier.block_error(
+            block_name,
+            "the compositor doesn't support the ext-idle-notify-v1 protocol",
+        )?;
+
+        bind(block_name, &mut stream, seat, "wl_seat", SEAT)?;
+        bind(
+            block_name,
+            &mut stream,
+            notifier,
+            "ext_idle_notifier_v1",
+            NOTIFIER,
+        )?;
+        // ext_idle_notifier_v1.get_idle_notification
+        let mut args = NOTIFICATION.to_ne_bytes().to_vec();
+        args.extend_from_slice(&(IDLE_TIMEOUT.as_millis() as u32).to_ne_bytes());
+        args.extend_from_slice(&SEAT.to_ne_bytes());
+        send(block_name, &mut stream, NOTIFIER, 1, &args)?;
+
+        let state = Arc::new(Mutex::new(IdleState::default()));
+        let thread_state = state.clone();
+        thread::Builder::new()
+            .name("activity_wayland".into())
+            .spawn(move || {
+                // Keep the connection open for as long as this thread runs
+                let _stream = stream;
+                loop {
+                    let message = receive(block_name, &mut reader);
+                    let mut state = thread_state.lock().unwrap();
+                    match message {
+                        Ok(message) if message.object == NOTIFICATION => {
+                            // idled is sent once there was no input for the timeout
+                            state.idle_since = match message.opcode {
+                                0 => Instant::now().checked_sub(IDLE_TIMEOUT),
+                                _ => None,
+                            };
+                            if let Some(ref wake) = state.wake {
+                                wake();
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            state.error = Some(match e {
+                                BlockError(_, message) => message,
+                                e => e.to_string(),
+                            });
+                            break;
+                        }
+                    }
+                }
+            })
+            .block_error(block_name, "failed to start the Wayland thread")?;
+
+        Ok(WaylandIdle { block_name, state })
+    }
+
+    pub fn set_wake(&self, wake: Box<dyn Fn() + Send>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.wake = Some(wake);
+        }
+    }
+
+    /// Milliseconds since the last input, like XScreenSaver's idle time.
+    pub fn idle(&self) -> Result<u64> {
+        let state = self
+            .state
+            .lock()
+            .block_error(self.block_name, "failed to acquire lock")?;
+        if let Some(ref error) = state.error {
+            return Err(BlockError(self.block_name.to_owned(), error.clone()));
+        }
+        Ok(state
+            .idle_since
+            .map_or(0, |since| since.elapsed().as_millis() as u64))
+    }
+}
@@ -1,9 +1,11 @@
+use std::time::{Duration, Instant};
+
 use serde_derive::Deserialize;
 use serde_json::value::Value;
 
 use crate::themes::Theme;
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
 pub enum State {
     Idle,
     Info,
@@ -23,9 +25,79 @@ impl State {
             Critical => (&theme.critical_bg, &theme.critical_fg),
         }
     }
+
+    /// How alarming the state is. Only warning and critical count as alerts.
+    fn alert_level(self) -> u8 {
+        match self {
+            State::Warning => 1,
+            State::Critical => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Keeps a widget in a warning or critical state for a minimum duration after it was last
+/// set, so that a short spike doesn't just flash by.
+#[derive(Debug, Clone, Default)]
+pub struct AlertHold {
+    held: Option<(State, Instant)>,
+}
+
+impl AlertHold {
+    /// Returns the state to show when the widget is set to `state`.
+    pub fn apply(&mut self, state: State, min_duration: Option<Duration>) -> State {
+        let min_duration = match min_duration {
+            Some(min_duration) => min_duration,
+            None => return state,
+        };
+        if let Some((held, since)) = self.held {
+            if held.alert_level() > state.alert_level() && since.elapsed() < min_duration {
+                return held;
+            }
+        }
+        self.held = if state.alert_level() > 0 {
+            Some((state, Instant::now()))
+        } else {
+            None
+        };
+        state
+    }
 }
 
 pub trait I3BarWidget {
     fn to_string(&self) -> String;
     fn get_rendered(&self) -> &Value;
+    /// The text shown, without the icon.
+    fn text(&self) -> &str;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::widget::{AlertHold, State};
+    use std::thread;
+    use std::time::Duration;
+
+    const HOLD: Option<Duration> = Some(Duration::from_millis(50));
+
+    #[test]
+    fn test_alert_hold_downgrade() {
+        let mut hold = AlertHold::default();
+        assert_eq!(hold.apply(State::Critical, HOLD), State::Critical);
+        assert_eq!(hold.apply(State::Idle, HOLD), State::Critical);
+        assert_eq!(hold.apply(State::Warning, HOLD), State::Critical);
+        thread::sleep(Duration::from_millis(60));
+        assert_eq!(hold.apply(State::Idle, HOLD), State::Idle);
+        // Nothing is held without a minimum duration
+        assert_eq!(hold.apply(State::Critical, None), State::Critical);
+        assert_eq!(hold.apply(State::Idle, None), State::Idle);
+    }
+
+    #[test]
+    fn test_alert_hold_upgrade() {
+        let mut hold = AlertHold::default();
+        assert_eq!(hold.apply(State::Warning, HOLD), State::Warning);
+        assert_eq!(hold.apply(State::Critical, HOLD), State::Critical);
+        // The upgrade is held from when it happened
+        assert_eq!(hold.apply(State::Warning, HOLD), State::Critical);
+    }
 }
@@ -2,13 +2,19 @@ use serde_json::value::Value;
 
 use super::super::widget::I3BarWidget;
 use crate::config::Config;
-use crate::widget::State;
+use crate::util::{escape_markup, pango_font_span};
+use crate::widget::{AlertHold, State};
 
 #[derive(Clone, Debug)]
 pub struct ButtonWidget {
     content: Option<String>,
+    /// Shown by the bar instead of `content` when it runs out of space
+    short_content: Option<String>,
+    /// Whether `content` is pango markup rather than text to be escaped
+    raw: bool,
     icon: Option<String>,
     state: State,
+    alert_hold: AlertHold,
     id: String,
     rendered: Value,
     cached_output: Option<String>,
@@ -19,8 +25,11 @@ impl ButtonWidget {
     pub fn new(config: Config, id: &str) -> Self {
         ButtonWidget {
             content: None,
+            short_content: None,
+            raw: false,
             icon: None,
             state: State::Idle,
+            alert_hold: AlertHold::default(),
             id: String::from(id),
             rendered: json!({
                 "full_text": "",
@@ -43,16 +52,19 @@ impl ButtonWidget {
 
     pub fn with_content(mut self, content: Option<String>) -> Self {
         self.content = content;
+        self.raw = false;
         self.update();
         self
     }
 
     pub fn with_text(mut self, content: &str) -> Self {
         self.content = Some(String::from(content));
+        self.raw = false;
         self.update();
         self
     }
 
+    #[cfg_attr(not(feature = "dbus"), allow(dead_code))]
     pub fn with_state(mut self, state: State) -> Self {
         self.state = state;
         self.update();
@@ -61,6 +73,20 @@ impl ButtonWidget {
 
     pub fn set_text<S: Into<String>>(&mut self, content: S) {
         self.content = Some(content.into());
+        self.raw = false;
+        self.update();
+    }
+
+    /// Sets pango markup as the text, for blocks emitting markup on purpose. Anything
+    /// data-derived in it has to be escaped with `escape_markup`.
+    pub fn set_text_raw<S: Into<String>>(&mut self, markup: S) {
+        self.content = Some(markup.into());
+        self.raw = true;
+        self.update();
+    }
+
+    pub fn set_short_text<S: Into<String>>(&mut self, content: S) {
+        self.short_content = Some(content.into());
         self.update();
     }
 
@@ -69,18 +95,32 @@ impl ButtonWidget {
         self.update();
     }
 
+    pub fn clear_icon(&mut self) {
+        self.icon = None;
+        self.update();
+    }
+
     pub fn set_state(&mut self, state: State) {
-        self.state = state;
+        self.state = self.alert_hold.apply(state, self.config.min_alert_duration);
         self.update();
     }
 
     fn update(&mut self) {
         let (key_bg, key_fg) = self.state.theme_keys(&self.config.theme);
 
+        // The text is always rendered as markup, so plain text has to be escaped
+        let content = self.content.as_deref().unwrap_or("");
+        let markup = if self.raw {
+            content.to_owned()
+        } else {
+            escape_markup(content)
+        };
+
         self.rendered = json!({
-            "full_text": format!("{}{} ",
-                                self.icon.clone().unwrap_or_else(|| String::from(" ")),
-                                self.content.clone().unwrap_or_else(|| String::from(""))),
+            "full_text": self.config.icon_position.full_text(
+                self.icon.as_deref(),
+                &markup
+            ),
             "separator": false,
             "name": self.id.clone(),
             "separator_block_width": 0,
@@ -89,6 +129,28 @@ impl ButtonWidget {
             "markup": "pango"
         });
 
+        if let Some(ref short_content) = self.short_content {
+            let markup = if self.raw {
+                short_content.to_owned()
+            } else {
+                escape_markup(short_content)
+            };
+            self.rendered["short_text"] = Value::String(
+                self.config
+                    .icon_position
+                    .full_text(self.icon.as_deref(), &markup),
+            );
+        }
+
+        if let Some(ref font) = self.config.font {
+            for key in &["full_text", "short_text"] {
+                if let Some(text) = self.rendered.get(*key).and_then(Value::as_str) {
+                    let text = pango_font_span(font, text);
+                    self.rendered[*key] = Value::String(text);
+                }
+            }
+        }
+
         self.cached_output = Some(self.rendered.to_string());
     }
 }
@@ -103,4 +165,8 @@ impl I3BarWidget for ButtonWidget {
     fn get_rendered(&self) -> &Value {
         &self.rendered
     }
+
+    fn text(&self) -> &str {
+        self.content.as_deref().unwrap_or("")
+    }
 }
@@ -3,13 +3,15 @@ use serde_json::value::Value;
 
 use super::super::widget::I3BarWidget;
 use crate::config::Config;
-use crate::widget::State;
+use crate::util::{escape_markup, pango_font_span};
+use crate::widget::{AlertHold, State};
 
 #[derive(Clone, Debug)]
 pub struct GraphWidget {
     content: Option<String>,
     icon: Option<String>,
     state: State,
+    alert_hold: AlertHold,
     rendered: Value,
     cached_output: Option<String>,
     config: Config,
@@ -21,6 +23,7 @@ impl GraphWidget {
             content: None,
             icon: None,
             state: State::Idle,
+            alert_hold: AlertHold::default(),
             rendered: json!({
                 "full_text": "",
                 "separator": false,
@@ -85,7 +88,7 @@ impl GraphWidget {
     }
 
     pub fn set_state(&mut self, state: State) {
-        self.state = state;
+        self.state = self.alert_hold.apply(state, self.config.min_alert_duration);
         self.update();
     }
 
@@ -93,15 +96,24 @@ impl GraphWidget {
         let (key_bg, key_fg) = self.state.theme_keys(&self.config.theme);
 
         self.rendered = json!({
-            "full_text": format!("{}{} ",
-                                self.icon.clone().unwrap_or_else(|| String::from(" ")),
-                                self.content.clone().unwrap_or_else(|| String::from(""))),
+            "full_text": self.config.icon_position.full_text(
+                self.icon.as_deref(),
+                self.content.as_deref().unwrap_or("")
+            ),
             "separator": false,
             "separator_block_width": 0,
             "background": key_bg.to_owned(),
             "color": key_fg.to_owned()
         });
 
+        if let Some(ref font) = self.config.font {
+            // the text isn't markup, so it has to be escaped to go inside the span
+            let full_text = self.rendered["full_text"].as_str().unwrap_or("").to_owned();
+            self.rendered["full_text"] =
+                Value::String(pango_font_span(font, &escape_markup(&full_text)));
+            self.rendered["markup"] = Value::String("pango".to_owned());
+        }
+
         self.cached_output = Some(self.rendered.to_string());
     }
 }
@@ -116,4 +128,8 @@ impl I3BarWidget for GraphWidget {
     fn get_rendered(&self) -> &Value {
         &self.rendered
     }
+
+    fn text(&self) -> &str {
+        self.content.as_deref().unwrap_or("")
+    }
 }
@@ -4,7 +4,8 @@ use serde_json::value::Value;
 
 use crate::config::Config;
 use crate::errors::*;
-use crate::widget::{I3BarWidget, State};
+use crate::util::{escape_markup, pango_font_span};
+use crate::widget::{AlertHold, I3BarWidget, State};
 
 #[derive(Clone, Debug)]
 pub struct RotatingTextWidget {
@@ -16,6 +17,7 @@ pub struct RotatingTextWidget {
     content: String,
     icon: Option<String>,
     state: State,
+    alert_hold: AlertHold,
     rendered: Value,
     cached_output: Option<String>,
     config: Config,
@@ -39,6 +41,7 @@ impl RotatingTextWidget {
             content: String::new(),
             icon: None,
             state: State::Idle,
+            alert_hold: AlertHold::default(),
             rendered: json!({
                 "full_text": "",
                 "separator": false,
@@ -77,7 +80,7 @@ impl RotatingTextWidget {
     }
 
     pub fn set_state(&mut self, state: State) {
-        self.state = state;
+        self.state = self.alert_hold.apply(state, self.config.min_alert_duration);
         self.update();
     }
 
@@ -133,9 +136,10 @@ impl RotatingTextWidget {
         let (key_bg, key_fg) = self.state.theme_keys(&self.config.theme);
 
         self.rendered = json!({
-            "full_text": format!("{}{} ",
-                                self.icon.clone().unwrap_or_else(|| String::from(" ")),
-                                self.get_rotated_content()),
+            "full_text": self.config.icon_position.full_text(
+                self.icon.as_deref(),
+                &self.get_rotated_content()
+            ),
             "separator": false,
             "separator_block_width": 0,
             "min_width": if self.content == "" {"".to_string()} else {"0".repeat(self.width+self.icon.clone().unwrap_or_else(|| String::from(" ")).chars().count()+1)},
@@ -144,6 +148,14 @@ impl RotatingTextWidget {
             "color": key_fg
         });
 
+        if let Some(ref font) = self.config.font {
+            // the text isn't markup, so it has to be escaped to go inside the span
+            let full_text = self.rendered["full_text"].as_str().unwrap_or("").to_owned();
+            self.rendered["full_text"] =
+                Value::String(pango_font_span(font, &escape_markup(&full_text)));
+            self.rendered["markup"] = Value::String("pango".to_owned());
+        }
+
         self.cached_output = Some(self.rendered.to_string());
     }
 
@@ -185,4 +197,8 @@ impl I3BarWidget for RotatingTextWidget {
     fn get_rendered(&self) -> &Value {
         &self.rendered
     }
+
+    fn text(&self) -> &str {
+        &self.content
+    }
 }
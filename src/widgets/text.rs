@@ -2,24 +2,36 @@ use serde_json::value::Value;
 
 use super::super::widget::I3BarWidget;
 use crate::config::Config;
-use crate::widget::State;
+use crate::errors::*;
+use crate::util::{escape_markup, pango_font_span, scroll_width, truncate_width};
+use crate::widget::{AlertHold, State};
 
 #[derive(Clone, Debug)]
 pub struct TextWidget {
     content: Option<String>,
+    /// Shown by the bar instead of `content` when it runs out of space
+    short_content: Option<String>,
     icon: Option<String>,
     state: State,
+    alert_hold: AlertHold,
     rendered: Value,
     cached_output: Option<String>,
     config: Config,
+    /// Longer text is cut to this many columns
+    max_width: Option<usize>,
+    /// Scroll longer text by a character each time it is set again, instead of cutting it
+    scroll: bool,
+    scroll_pos: usize,
 }
 
 impl TextWidget {
     pub fn new(config: Config) -> Self {
         TextWidget {
             content: None,
+            short_content: None,
             icon: None,
             state: State::Idle,
+            alert_hold: AlertHold::default(),
             rendered: json!({
                 "full_text": "",
                 "separator": false,
@@ -29,9 +41,24 @@ impl TextWidget {
             }),
             config,
             cached_output: None,
+            max_width: None,
+            scroll: false,
+            scroll_pos: 0,
         }
     }
 
+    pub fn with_max_width(mut self, max_width: Option<usize>) -> Self {
+        self.max_width = max_width;
+        self.update();
+        self
+    }
+
+    pub fn with_scroll(mut self, scroll: bool) -> Self {
+        self.scroll = scroll;
+        self.update();
+        self
+    }
+
     pub fn with_icon(mut self, name: &str) -> Self {
         self.icon = self.config.icons.get(name).cloned();
         self.update();
@@ -51,33 +78,83 @@ impl TextWidget {
     }
 
     pub fn set_text(&mut self, content: String) {
+        if self.content.as_ref() == Some(&content) {
+            self.scroll_pos += 1;
+        } else {
+            self.scroll_pos = 0;
+        }
         self.content = Some(content);
         self.update();
     }
 
+    pub fn set_short_text(&mut self, content: String) {
+        self.short_content = Some(content);
+        self.update();
+    }
+
+    /// Shows `error` in the critical state with the error icon, and just "error" when the
+    /// bar is short of space.
+    pub fn set_error(&mut self, error: &Error) {
+        self.content = Some(match error {
+            BlockError(_, message) => message.clone(),
+            error => error.to_string(),
+        });
+        self.short_content = Some("error".to_owned());
+        self.icon = self.config.icons.get("error").cloned();
+        self.state = State::Critical;
+        self.update();
+    }
+
     pub fn set_icon(&mut self, name: &str) {
         self.icon = self.config.icons.get(name).cloned();
         self.update();
     }
 
     pub fn set_state(&mut self, state: State) {
-        self.state = state;
+        self.state = self.alert_hold.apply(state, self.config.min_alert_duration);
         self.update();
     }
 
     fn update(&mut self) {
         let (key_bg, key_fg) = self.state.theme_keys(&self.config.theme);
 
+        let content = self.content.as_deref().unwrap_or("");
+        let content = match self.max_width {
+            Some(max_width) if self.scroll => scroll_width(content, max_width, self.scroll_pos),
+            Some(max_width) => truncate_width(content, max_width, &self.config.ellipsis),
+            None => content.to_owned(),
+        };
+
         self.rendered = json!({
-            "full_text": format!("{}{} ",
-                                self.icon.clone().unwrap_or_else(|| String::from(" ")),
-                                self.content.clone().unwrap_or_else(|| String::from(""))),
+            "full_text": self.config.icon_position.full_text(
+                self.icon.as_deref(),
+                &content
+            ),
             "separator": false,
             "separator_block_width": 0,
             "background": key_bg.to_owned(),
             "color": key_fg.to_owned()
         });
 
+        if let Some(ref short_content) = self.short_content {
+            self.rendered["short_text"] = Value::String(
+                self.config
+                    .icon_position
+                    .full_text(self.icon.as_deref(), short_content),
+            );
+        }
+
+        if let Some(ref font) = self.config.font {
+            // the text isn't markup, so it has to be escaped to go inside the span
+            for key in &["full_text", "short_text"] {
+                if let Some(text) = self.rendered.get(*key).and_then(Value::as_str) {
+                    let text = pango_font_span(font, &escape_markup(text));
+                    self.rendered[*key] = Value::String(text);
+                }
+            }
+            self.rendered["markup"] = Value::String("pango".to_owned());
+        }
+
         self.cached_output = Some(self.rendered.to_string());
     }
 }
@@ -92,4 +169,53 @@ impl I3BarWidget for TextWidget {
     fn get_rendered(&self) -> &Value {
         &self.rendered
     }
+
+    fn text(&self) -> &str {
+        self.content.as_deref().unwrap_or("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::widget::I3BarWidget;
+    use crate::widgets::text::TextWidget;
+
+    fn full_text(widget: &TextWidget) -> &str {
+        widget.get_rendered()["full_text"].as_str().unwrap()
+    }
+
+    #[test]
+    fn test_max_width() {
+        let mut widget = TextWidget::new(Config::default()).with_max_width(Some(4));
+        widget.set_text("abcdef".to_owned());
+        assert_eq!(full_text(&widget), " abc… ");
+        // Setting the same text again doesn't move it without scrolling
+        widget.set_text("abcdef".to_owned());
+        assert_eq!(full_text(&widget), " abc… ");
+        widget.set_text("abc".to_owned());
+        assert_eq!(full_text(&widget), " abc ");
+    }
+
+    #[test]
+    fn test_scroll() {
+        let mut widget = TextWidget::new(Config::default())
+            .with_max_width(Some(4))
+            .with_scroll(true);
+        // "abcdef" and a gap of three spaces make nine positions to wrap around
+        let shown: Vec<String> = (0..10)
+            .map(|_| {
+                widget.set_text("abcdef".to_owned());
+                full_text(&widget).to_owned()
+            })
+            .collect();
+        assert_eq!(shown[0], " abcd ");
+        assert_eq!(shown[1], " bcde ");
+        assert_eq!(shown[4], " ef   ");
+        assert_eq!(shown[8], "  abc ");
+        assert_eq!(shown[9], " abcd ");
+        // New text starts over
+        widget.set_text("ghijkl".to_owned());
+        assert_eq!(full_text(&widget), " ghij ");
+    }
 }
@@ -0,0 +1,81 @@
+//! One connection to the X server for all the blocks which need it. Xlib is not thread-safe
+//! by itself, so every call goes through `SharedDisplay::with`, which holds a lock for it.
+
+use std::env;
+use std::ffi::CString;
+use std::ptr;
+use std::sync::{Arc, Mutex, Weak};
+
+use lazy_static::lazy_static;
+use x11::xlib::{Display, XCloseDisplay, XOpenDisplay};
+
+use crate::errors::*;
+
+struct DisplayConnection(*mut Display);
+
+// The pointer is only used while holding CALLS
+unsafe impl Send for DisplayConnection {}
+unsafe impl Sync for DisplayConnection {}
+
+impl Drop for DisplayConnection {
+    fn drop(&mut self) {
+        let _lock = CALLS.lock();
+        unsafe {
+            XCloseDisplay(self.0);
+        }
+    }
+}
+
+lazy_static! {
+    /// The open connection, if any block still holds it
+    static ref OPEN: Mutex<Weak<DisplayConnection>> = Mutex::new(Weak::new());
+    /// Held during every Xlib call on the connection
+    static ref CALLS: Mutex<()> = Mutex::new(());
+}
+
+/// A handle on the X display named by `DISPLAY`. The connection is opened by the first
+/// handle and closed when the last one is dropped.
+#[derive(Clone)]
+pub struct SharedDisplay(Arc<DisplayConnection>);
+
+impl SharedDisplay {
+    pub fn open(block: &str) -> Result<Self> {
+        let mut open = OPEN.lock().block_error(block, "failed to acquire lock")?;
+        if let Some(connection) = open.upgrade() {
+            return Ok(SharedDisplay(connection));
+        }
+
+        // The name has to outlive the call, Xlib falls back to $DISPLAY itself without one
+        let name = env::var("DISPLAY").ok();
+        let name_cstr = name
+            .as_deref()
+            .map(CString::new)
+            .transpose()
+            .block_error(block, "DISPLAY contains a nul byte")?;
+        let display = {
+            let _lock = CALLS.lock().block_error(block, "failed to acquire lock")?;
+            unsafe { XOpenDisplay(name_cstr.as_ref().map_or(ptr::null(), |name| name.as_ptr())) }
+        };
+        if display.is_null() {
+            // e.g. under Wayland without Xwayland, or on a TTY
+            return Err(BlockError(
+                block.to_owned(),
+                format!(
+                    "failed to open X display {}",
+                    name.as_deref().unwrap_or("N/A")
+                ),
+            ));
+        }
+        let connection = Arc::new(DisplayConnection(display));
+        *open = Arc::downgrade(&connection);
+        Ok(SharedDisplay(connection))
+    }
+
+    /// Runs `f` with the display, no other thread makes Xlib calls on it meanwhile.
+    pub fn with<T>(&self, f: impl FnOnce(*mut Display) -> T) -> T {
+        let _lock = CALLS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        f((self.0).0)
+    }
+}